@@ -0,0 +1,234 @@
+use crate::{option_value, Error};
+use dialogue_graph::{
+    condition::dynamic::DynCondition,
+    format::{dot, text, Loss},
+    DialogueGraph,
+};
+use std::{
+    fs,
+    io::{Read, Write},
+    path::Path,
+};
+
+/// A format graphs are read or written in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Format {
+    Json,
+    Text,
+    Dot,
+}
+
+impl Format {
+    fn parse(name: &str) -> Result<Self, Error> {
+        match name {
+            "json" => Ok(Format::Json),
+            "text" => Ok(Format::Text),
+            "dot" => Ok(Format::Dot),
+            _ => Err(Error::Usage(format!("unknown format {:?}", name))),
+        }
+    }
+
+    /// Returns the format a file's extension stands for.
+    fn of(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        match extension.to_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "txt" | "dialogue" => Some(Format::Text),
+            "dot" | "gv" => Some(Format::Dot),
+            _ => None,
+        }
+    }
+}
+
+/// The options of the `convert` command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Options {
+    input: String,
+    output: String,
+    from: Format,
+    to: Format,
+}
+
+impl Options {
+    fn parse<I>(mut args: I) -> Result<Self, Error>
+    where
+        I: Iterator<Item = String>,
+    {
+        let mut files = Vec::new();
+        let mut from = None;
+        let mut to = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--from" => from = Some(Format::parse(&option_value(&mut args, "--from")?)?),
+                "--to" => to = Some(Format::parse(&option_value(&mut args, "--to")?)?),
+                _ if arg.starts_with("--") => {
+                    return Err(Error::Usage(format!("unknown option {:?}", arg)))
+                }
+                _ if files.len() < 2 => files.push(arg),
+                _ => return Err(Error::Usage(format!("unexpected argument {:?}", arg))),
+            }
+        }
+        if files.len() < 2 {
+            return Err(Error::Usage(
+                "convert needs an input and an output file".to_owned(),
+            ));
+        }
+        let output = files.pop().unwrap_or_default();
+        let input = files.pop().unwrap_or_default();
+        let from = match from.or_else(|| Format::of(&input)) {
+            Some(Format::Dot) => {
+                return Err(Error::Usage("DOT files cannot be read".to_owned()));
+            }
+            Some(format) => format,
+            None => {
+                return Err(Error::Usage(format!(
+                    "cannot tell the format of {:?}, give it with --from",
+                    input
+                )))
+            }
+        };
+        let to = to.or_else(|| Format::of(&output)).ok_or_else(|| {
+            Error::Usage(format!(
+                "cannot tell the format of {:?}, give it with --to",
+                output
+            ))
+        })?;
+        Ok(Options {
+            input,
+            output,
+            from,
+            to,
+        })
+    }
+}
+
+/// Convert a graph from one format to another, returning the content left
+/// out or changed in the conversion.
+fn convert(source: &str, from: Format, to: Format) -> Result<(String, Vec<Loss>), String> {
+    let graph: DialogueGraph<DynCondition> = match from {
+        Format::Json => serde_json::from_str(source).map_err(|error| error.to_string())?,
+        Format::Text => text::read(source).map_err(|error| error.to_string())?,
+        Format::Dot => return Err("DOT files cannot be read".to_owned()),
+    };
+    Ok(match to {
+        Format::Json => {
+            let json = serde_json::to_string_pretty(&graph).map_err(|error| error.to_string())?;
+            (json + "\n", Vec::new())
+        }
+        Format::Text => text::write(&graph),
+        Format::Dot => dot::write(&graph),
+    })
+}
+
+/// Run the `convert` command with its arguments, printing losses to
+/// `warnings`. The input `-` is read from `input`, and the output `-` is
+/// written to `output`.
+pub(crate) fn run<I, R, W, E>(
+    args: I,
+    input: &mut R,
+    output: &mut W,
+    warnings: &mut E,
+) -> Result<(), Error>
+where
+    I: Iterator<Item = String>,
+    R: Read,
+    W: Write,
+    E: Write,
+{
+    let options = Options::parse(args)?;
+    let read_error = |error| Error::Read {
+        path: options.input.clone(),
+        error,
+    };
+    let source = if options.input == "-" {
+        let mut source = String::new();
+        let _ = input.read_to_string(&mut source).map_err(read_error)?;
+        source
+    } else {
+        fs::read_to_string(&options.input).map_err(read_error)?
+    };
+
+    let (converted, losses) = convert(&source, options.from, options.to)
+        .map_err(|error| Error::Content(format!("cannot parse {}: {}", options.input, error)))?;
+    for loss in &losses {
+        writeln!(warnings, "warning: {}", loss)?;
+    }
+    if options.output == "-" {
+        output.write_all(converted.as_bytes())?;
+    } else {
+        fs::write(&options.output, converted)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, Error> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse(&["graph.json", "graph.DOT"]).unwrap();
+        assert_eq!((options.from, options.to), (Format::Json, Format::Dot));
+
+        let options = parse(&["--to", "text", "-", "--from", "json", "-"]).unwrap();
+        assert_eq!((options.from, options.to), (Format::Json, Format::Text));
+
+        assert!(parse(&["graph.json", "graph"]).is_err());
+        assert!(parse(&["graph.gv", "graph.json"]).is_err());
+        assert!(parse(&["graph.json"]).is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        let json = r#"{
+            "data": {
+                "nodes": [{ "id": "start", "text": "A" }, "B"],
+                "node_holes": [],
+                "edge_property": "directed",
+                "edges": [[0, 1, { "condition": { "type": "true" }, "once": true }]]
+            },
+            "version": 1
+        }"#;
+        let run = |args: &[&str], input: &str| {
+            let mut output = Vec::new();
+            let mut warnings = Vec::new();
+            run(
+                args.iter().map(|arg| arg.to_string()),
+                &mut input.as_bytes(),
+                &mut output,
+                &mut warnings,
+            )
+            .unwrap();
+            (
+                String::from_utf8(output).unwrap(),
+                String::from_utf8(warnings).unwrap(),
+            )
+        };
+
+        let (text, warnings) = run(&["--from", "json", "--to", "text", "-", "-"], json);
+        assert_eq!(
+            text,
+            "version 1\n\nnode 0: \"A\" id=\"start\"\nnode 1: \"B\"\n\nedge 0: 0 -> 1 once\n"
+        );
+        assert!(warnings.is_empty());
+
+        let (dot, warnings) = run(&["--from", "text", "--to", "dot", "-", "-"], &text);
+        assert!(dot.contains("0 -> 1;"));
+        assert_eq!(
+            warnings,
+            "warning: node ids are left out (1)\n\
+             warning: edge traversal limits are left out (1)\n"
+        );
+
+        let (round_trip, _) = run(&["--from", "text", "--to", "json", "-", "-"], &text);
+        let expected: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&round_trip).unwrap(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Command line tools for dialogue graphs.
+
+mod convert;
+mod play;
+mod replay;
+mod validate;
+
+use dialogue_graph::{
+    condition::dynamic::DynCondition, conversation::ConversationError, library::Library,
+    variable::Value, DialogueGraph,
+};
+use serde::de::DeserializeOwned;
+use std::{env, fmt, fs, io, process};
+
+const USAGE: &str = "\
+Usage: dialogue-graph <command> [options]
+
+Commands:
+    play <file>           Play a dialogue graph in the terminal
+    validate <file>...    Check dialogue graphs for broken content
+    convert <in> <out>    Convert a dialogue graph to another format
+    replay <file> <transcript>...
+                          Check transcripts of playthroughs against a dialogue graph
+    help                  Print this message
+
+Options for play:
+    --set <name>=<value>    Set a variable before starting, as many times as needed
+    --seed <number>         Seed random choices
+    --random                Make every choice at random instead of asking
+    --max-steps <number>    Stop after the given number of choices, 1000 by default
+    --start <id>            Start at the node with the given id
+    --library <file>        Load the graphs edges call from a library file
+    --record <file>         Write a transcript of the playthrough, for replay
+
+Options for validate:
+    --format <text|json>    Print diagnostics as text or JSON, or --json
+    --deny-warnings         Fail on warnings as well as errors
+    --library <file>        Check calls and links against a library file
+
+Options for convert:
+    --from <json|text>      Read the input in the given format
+    --to <json|text|dot>    Write the output in the given format
+    Formats default to those of the file extensions: .json, .txt or
+    .dialogue for text, and .dot or .gv. Use - for standard input or output.
+    Content the output format cannot hold is reported as warnings.
+
+Options for replay:
+    --start <id>            Start at the node with the given id
+    --library <file>        Load the graphs edges call from a library file
+
+Exit status is 0 on success, 1 on failure and 2 on a malformed command line.";
+
+/// An error which stops a command.
+#[derive(Debug)]
+pub(crate) enum Error {
+    /// The command line is malformed.
+    Usage(String),
+    /// A file cannot be read.
+    Read { path: String, error: io::Error },
+    /// A file does not hold what the command needs.
+    Parse {
+        path: String,
+        error: serde_json::Error,
+    },
+    /// The content cannot be used as asked.
+    Content(String),
+    /// The conversation cannot proceed.
+    Conversation(ConversationError),
+    /// Input or output failed.
+    Io(io::Error),
+    /// Validation found problems, which have been printed.
+    Invalid { errors: usize, warnings: usize },
+    /// Transcripts did not match, which has been printed.
+    Failed { failed: usize, total: usize },
+}
+
+impl Error {
+    /// Returns the code to exit the process with.
+    fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(message) | Error::Content(message) => f.write_str(message),
+            Error::Read { path, error } => write!(f, "cannot read {}: {}", path, error),
+            Error::Parse { path, error } => write!(f, "cannot parse {}: {}", path, error),
+            Error::Conversation(error) => error.fmt(f),
+            Error::Io(error) => error.fmt(f),
+            Error::Invalid { errors, warnings } => write!(
+                f,
+                "validation failed with {} errors and {} warnings",
+                errors, warnings
+            ),
+            Error::Failed { failed, total } => {
+                write!(f, "{} of {} transcripts failed", failed, total)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<ConversationError> for Error {
+    fn from(error: ConversationError) -> Self {
+        Error::Conversation(error)
+    }
+}
+
+/// Read a JSON file.
+pub(crate) fn load<T>(path: &str) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    let json = fs::read_to_string(path).map_err(|error| Error::Read {
+        path: path.to_owned(),
+        error,
+    })?;
+    serde_json::from_str(&json).map_err(|error| Error::Parse {
+        path: path.to_owned(),
+        error,
+    })
+}
+
+/// Read a dialogue graph file.
+pub(crate) fn load_graph(path: &str) -> Result<DialogueGraph<DynCondition>, Error> {
+    load(path)
+}
+
+/// Read a library file.
+pub(crate) fn load_library(path: &str) -> Result<Library<DynCondition>, Error> {
+    load(path)
+}
+
+/// Returns the value of an option, or an error if it is missing.
+pub(crate) fn option_value<I>(args: &mut I, option: &str) -> Result<String, Error>
+where
+    I: Iterator<Item = String>,
+{
+    args.next()
+        .ok_or_else(|| Error::Usage(format!("{} needs a value", option)))
+}
+
+/// Parse the value of a variable given on the command line: `true`, `false`,
+/// an integer, or else a string.
+pub(crate) fn parse_value(text: &str) -> Value {
+    match text {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => text
+            .parse::<i64>()
+            .map(Value::Int)
+            .unwrap_or_else(|_| Value::from(text)),
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let result = match args.next().as_deref() {
+        Some("play") => play::run(args, &mut stdin.lock(), &mut stdout.lock()),
+        Some("validate") => validate::run(args, &mut stdout.lock()),
+        Some("convert") => convert::run(
+            args,
+            &mut stdin.lock(),
+            &mut stdout.lock(),
+            &mut io::stderr(),
+        ),
+        Some("replay") => replay::run(args, &mut stdout.lock()),
+        Some("help") | Some("--help") | Some("-h") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(command) => Err(Error::Usage(format!("unknown command {:?}", command))),
+        None => Err(Error::Usage("no command given".to_owned())),
+    };
+    if let Err(error) = result {
+        eprintln!("error: {}", error);
+        if let Error::Usage(_) = error {
+            eprintln!("\n{}", USAGE);
+        }
+        process::exit(error.exit_code());
+    }
+}
@@ -0,0 +1,366 @@
+use crate::{load_graph, load_library, option_value, parse_value, Error};
+use dialogue_graph::{
+    condition::dynamic::DynCondition,
+    conversation::Conversation,
+    library::Library,
+    markup,
+    simulation::DEFAULT_MAX_STEPS,
+    transcript::{self, Step, Transcript},
+    variable::Value,
+    DialogueGraph,
+};
+use std::{
+    fs,
+    io::{BufRead, Write},
+};
+
+/// The options of the `play` command.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Options {
+    file: String,
+    library: Option<String>,
+    start: Option<String>,
+    seed: Option<u64>,
+    random: bool,
+    max_steps: Option<usize>,
+    variables: Vec<(String, Value)>,
+    record: Option<String>,
+}
+
+impl Options {
+    fn parse<I>(mut args: I) -> Result<Self, Error>
+    where
+        I: Iterator<Item = String>,
+    {
+        let mut options = Options::default();
+        let mut file = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--set" => {
+                    let assignment = option_value(&mut args, "--set")?;
+                    let end = assignment.find('=').ok_or_else(|| {
+                        Error::Usage(format!("expected <name>=<value>, not {:?}", assignment))
+                    })?;
+                    options.variables.push((
+                        assignment[..end].to_owned(),
+                        parse_value(&assignment[end + 1..]),
+                    ));
+                }
+                "--seed" => {
+                    let seed = option_value(&mut args, "--seed")?;
+                    let seed = seed
+                        .parse()
+                        .map_err(|_| Error::Usage(format!("invalid seed {:?}", seed)))?;
+                    options.seed = Some(seed);
+                }
+                "--random" => options.random = true,
+                "--max-steps" => {
+                    let steps = option_value(&mut args, "--max-steps")?;
+                    let steps = steps
+                        .parse()
+                        .map_err(|_| Error::Usage(format!("invalid step count {:?}", steps)))?;
+                    options.max_steps = Some(steps);
+                }
+                "--start" => options.start = Some(option_value(&mut args, "--start")?),
+                "--library" => options.library = Some(option_value(&mut args, "--library")?),
+                "--record" => options.record = Some(option_value(&mut args, "--record")?),
+                _ if arg.starts_with("--") => {
+                    return Err(Error::Usage(format!("unknown option {:?}", arg)))
+                }
+                _ if file.is_none() => file = Some(arg),
+                _ => return Err(Error::Usage(format!("unexpected argument {:?}", arg))),
+            }
+        }
+        options.file = file.ok_or_else(|| Error::Usage("play needs a file".to_owned()))?;
+        Ok(options)
+    }
+}
+
+/// Run the `play` command with its arguments.
+pub(crate) fn run<I, R, W>(args: I, input: &mut R, output: &mut W) -> Result<(), Error>
+where
+    I: Iterator<Item = String>,
+    R: BufRead,
+    W: Write,
+{
+    let options = Options::parse(args)?;
+    let graph = load_graph(&options.file)?;
+    let library = match &options.library {
+        Some(path) => Some(load_library(path)?),
+        None => None,
+    };
+    let mut transcript = Transcript::new();
+    play(
+        &graph,
+        library.as_ref(),
+        &options,
+        input,
+        output,
+        &mut transcript,
+    )?;
+    if let Some(path) = &options.record {
+        fs::write(path, transcript.to_string())?;
+    }
+    Ok(())
+}
+
+/// Ask the player to pick one of a number of choices, returning the number
+/// picked counting from 0, `Some(None)` for a random choice, or `None` to
+/// quit.
+fn prompt<R, W>(count: usize, input: &mut R, output: &mut W) -> Result<Option<Option<usize>>, Error>
+where
+    R: BufRead,
+    W: Write,
+{
+    loop {
+        write!(output, "> ")?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        match line.trim() {
+            "q" | "quit" => return Ok(None),
+            "r" | "random" => return Ok(Some(None)),
+            answer => match answer.parse::<usize>() {
+                Ok(number) if number >= 1 && number <= count => return Ok(Some(Some(number - 1))),
+                _ => writeln!(
+                    output,
+                    "Enter a number from 1 to {}, r for a random choice or q to quit.",
+                    count
+                )?,
+            },
+        }
+    }
+}
+
+/// Play a conversation, recording the lines shown and the choices made in a
+/// transcript.
+fn play<R, W>(
+    graph: &DialogueGraph<DynCondition>,
+    library: Option<&Library<DynCondition>>,
+    options: &Options,
+    input: &mut R,
+    output: &mut W,
+    transcript: &mut Transcript,
+) -> Result<(), Error>
+where
+    R: BufRead,
+    W: Write,
+{
+    let start = match &options.start {
+        Some(id) => graph
+            .find(id)
+            .ok_or_else(|| Error::Content(format!("no node has the id {:?}", id)))?,
+        None => graph
+            .entry()
+            .ok_or_else(|| Error::Content("the graph has no nodes".to_owned()))?,
+    };
+    let mut conversation = Conversation::new(graph, start);
+    if let Some(library) = library {
+        conversation = conversation.with_library(library);
+    }
+    if let Some(seed) = options.seed {
+        conversation = conversation.with_seed(seed);
+    }
+    for (name, value) in &options.variables {
+        let _ = conversation.variables_mut().set(name, value.clone());
+        transcript.push(Step::Set {
+            name: name.clone(),
+            value: value.clone(),
+        });
+    }
+    let max_steps = options.max_steps.unwrap_or(DEFAULT_MAX_STEPS);
+    let mut positions = vec![conversation.position()];
+    for step in 0..=max_steps {
+        positions.extend(conversation.advance()?);
+        for position in positions.drain(..) {
+            if let Some(node) = conversation.node_at(&position) {
+                let line = transcript::line(node, &conversation.context());
+                writeln!(output, "{}", line)?;
+                transcript.push(Step::Expect(line));
+            }
+        }
+        let choices = conversation.choices();
+        if choices.is_empty() {
+            writeln!(output, "[end]")?;
+            transcript.push(Step::End);
+            return Ok(());
+        }
+        if step == max_steps {
+            break;
+        }
+        let context = conversation.context();
+        for (number, edge) in choices.iter().enumerate() {
+            let label = match &conversation.graph().data[*edge].text {
+                Some(text) => markup::strip(text),
+                None => match conversation.graph().data.edge_endpoints(*edge) {
+                    Some((_, target)) => {
+                        transcript::line(&conversation.graph().data[target], &context)
+                    }
+                    None => String::new(),
+                },
+            };
+            writeln!(output, "  {}. {}", number + 1, label)?;
+        }
+
+        let picked = if options.random {
+            None
+        } else {
+            match prompt(choices.len(), input, output)? {
+                Some(picked) => picked,
+                None => return Ok(()),
+            }
+        };
+        let edge = match picked {
+            Some(index) => choices[index],
+            None => {
+                let edge = conversation
+                    .random_choice()
+                    .ok_or_else(|| Error::Content("no choice is open".to_owned()))?;
+                let number = choices.iter().position(|choice| *choice == edge);
+                writeln!(output, "> {}", number.map_or(0, |number| number + 1))?;
+                edge
+            }
+        };
+        if let Some(number) = choices.iter().position(|choice| *choice == edge) {
+            transcript.push(Step::Choose(number + 1));
+        }
+        let _ = conversation.choose(edge)?;
+        positions.push(conversation.position());
+    }
+    writeln!(output, "[stopped after {} steps]", max_steps)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dialogue_graph::{condition::Comparison, Edge, Node};
+
+    fn graph() -> DialogueGraph<DynCondition> {
+        let mut graph = DialogueGraph::new();
+        let hello = graph
+            .data
+            .add_node(Node::new("Hello, [b]{name}[/b].").with_speaker("Guard"));
+        let pass = graph.data.add_node("You may pass.".into());
+        let bribe = graph.data.add_node("Thank you kindly.".into());
+        let bye = graph.data.add_node("Move along.".into());
+        let _ = graph
+            .data
+            .add_edge(hello, pass, Edge::new(DynCondition::True));
+        let rich = DynCondition::Compare {
+            variable: "gold".to_owned(),
+            comparison: Comparison::GreaterOrEqual,
+            value: Value::Int(10),
+        };
+        let _ = graph
+            .data
+            .add_edge(hello, bribe, Edge::new(rich).with_text("Offer gold"));
+        let _ = graph
+            .data
+            .add_edge(bribe, bye, Edge::new(DynCondition::True).auto());
+        graph
+    }
+
+    fn session(options: &Options, input: &str) -> (String, Transcript) {
+        let mut output = Vec::new();
+        let mut transcript = Transcript::new();
+        play(
+            &graph(),
+            None,
+            options,
+            &mut input.as_bytes(),
+            &mut output,
+            &mut transcript,
+        )
+        .unwrap();
+        (String::from_utf8(output).unwrap(), transcript)
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let args = [
+            "--set",
+            "gold=10",
+            "graph.json",
+            "--seed",
+            "3",
+            "--random",
+            "--max-steps",
+            "50",
+        ];
+        let options = Options::parse(args.iter().map(|arg| arg.to_string())).unwrap();
+
+        assert_eq!(options.file, "graph.json");
+        assert_eq!(options.seed, Some(3));
+        assert!(options.random);
+        assert_eq!(options.max_steps, Some(50));
+        assert_eq!(options.variables, vec![("gold".to_owned(), Value::Int(10))]);
+        assert!(Options::parse(vec!["--seed".to_owned()].into_iter()).is_err());
+        assert!(Options::parse(Vec::new().into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_play() {
+        let mut options = Options::default();
+        options.variables = vec![
+            ("name".to_owned(), Value::from("Ada")),
+            ("gold".to_owned(), Value::Int(10)),
+        ];
+
+        let (output, recorded) = session(&options, "3\n2\n");
+        assert_eq!(
+            output,
+            "Guard: Hello, Ada.\n  1. You may pass.\n  2. Offer gold\n> \
+             Enter a number from 1 to 2, r for a random choice or q to quit.\n> \
+             Thank you kindly.\nMove along.\n[end]\n"
+        );
+        assert_eq!(
+            recorded.to_string(),
+            "set name = \"Ada\"\nset gold = 10\nexpect Guard: Hello, Ada.\nchoose 2\n\
+             expect Thank you kindly.\nexpect Move along.\nend\n"
+        );
+        let graph = graph();
+        assert_eq!(recorded.check(Conversation::new(&graph, 0.into())), Ok(()));
+
+        options.variables.pop();
+        assert_eq!(
+            session(&options, "q\n").0,
+            "Guard: Hello, Ada.\n  1. You may pass.\n> "
+        );
+    }
+
+    #[test]
+    fn test_play_at_random() {
+        let mut options = Options::default();
+        options.random = true;
+        options.seed = Some(1);
+
+        assert!(session(&options, "").0.ends_with("[end]\n"));
+    }
+
+    #[test]
+    fn test_play_at_random_stops() {
+        let mut graph = DialogueGraph::new();
+        let hub = graph.data.add_node("Again?".into());
+        let _ = graph.data.add_edge(hub, hub, Edge::new(DynCondition::True));
+        let mut options = Options::default();
+        options.random = true;
+        options.max_steps = Some(3);
+
+        let mut output = Vec::new();
+        play(
+            &graph,
+            None,
+            &options,
+            &mut "".as_bytes(),
+            &mut output,
+            &mut Transcript::new(),
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("> 1").count(), 3);
+        assert!(output.ends_with("[stopped after 3 steps]\n"));
+    }
+}
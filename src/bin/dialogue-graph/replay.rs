@@ -0,0 +1,136 @@
+use crate::{load_graph, load_library, option_value, Error};
+use dialogue_graph::{
+    condition::dynamic::DynCondition, conversation::Conversation, library::Library,
+    transcript::Transcript, DialogueGraph,
+};
+use std::{fs, io::Write};
+
+/// The options of the `replay` command.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Options {
+    file: String,
+    transcripts: Vec<String>,
+    library: Option<String>,
+    start: Option<String>,
+}
+
+impl Options {
+    fn parse<I>(mut args: I) -> Result<Self, Error>
+    where
+        I: Iterator<Item = String>,
+    {
+        let mut options = Options::default();
+        let mut files = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--start" => options.start = Some(option_value(&mut args, "--start")?),
+                "--library" => options.library = Some(option_value(&mut args, "--library")?),
+                _ if arg.starts_with("--") => {
+                    return Err(Error::Usage(format!("unknown option {:?}", arg)))
+                }
+                _ => files.push(arg),
+            }
+        }
+        if files.len() < 2 {
+            return Err(Error::Usage(
+                "replay needs a file and at least one transcript".to_owned(),
+            ));
+        }
+        options.file = files.remove(0);
+        options.transcripts = files;
+        Ok(options)
+    }
+}
+
+/// Check a transcript against a graph, returning what went wrong if it does
+/// not match.
+fn replay(
+    graph: &DialogueGraph<DynCondition>,
+    library: Option<&Library<DynCondition>>,
+    start: Option<&str>,
+    text: &str,
+) -> Result<(), String> {
+    let transcript = Transcript::parse(text).map_err(|error| error.to_string())?;
+    let start = match start {
+        Some(id) => graph
+            .find(id)
+            .ok_or_else(|| format!("no node has the id {:?}", id))?,
+        None => graph.entry().ok_or("the graph has no nodes")?,
+    };
+    let mut conversation = Conversation::new(graph, start);
+    if let Some(library) = library {
+        conversation = conversation.with_library(library);
+    }
+    transcript
+        .check(conversation)
+        .map_err(|error| error.to_string())
+}
+
+/// Run the `replay` command with its arguments.
+pub(crate) fn run<I, W>(args: I, output: &mut W) -> Result<(), Error>
+where
+    I: Iterator<Item = String>,
+    W: Write,
+{
+    let options = Options::parse(args)?;
+    let graph = load_graph(&options.file)?;
+    let library = match &options.library {
+        Some(path) => Some(load_library(path)?),
+        None => None,
+    };
+    let mut failed = 0;
+    for path in &options.transcripts {
+        let result = fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| replay(&graph, library.as_ref(), options.start.as_deref(), &text));
+        match result {
+            Ok(()) => writeln!(output, "{}: ok", path)?,
+            Err(error) => {
+                failed += 1;
+                writeln!(output, "{}: failed: {}", path, error)?;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(Error::Failed {
+            failed,
+            total: options.transcripts.len(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dialogue_graph::{Edge, Node};
+
+    #[test]
+    fn test_replay() {
+        let mut graph = DialogueGraph::new();
+        let halt = graph.data.add_node(Node::new("Halt!").with_id("halt"));
+        let pass = graph.data.add_node("Pass.".into());
+        let _ = graph
+            .data
+            .add_edge(halt, pass, Edge::new(DynCondition::True));
+
+        assert_eq!(
+            replay(
+                &graph,
+                None,
+                None,
+                "expect Halt!\nchoose 1\nexpect Pass.\nend"
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            replay(&graph, None, Some("halt"), "expect Halt!\nend"),
+            Err(
+                "line 2: the conversation differs from the transcript\n- end\n+ choose 1"
+                    .to_owned()
+            )
+        );
+        assert!(replay(&graph, None, Some("gate"), "end").is_err());
+        assert!(replay(&graph, None, None, "jump").is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{load_library, option_value, Error};
+use dialogue_graph::{
+    condition::dynamic::DynCondition,
+    library::Library,
+    validation::{Diagnostic, Severity},
+    DialogueGraph,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{fs, io::Write};
+
+/// How diagnostics are printed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// The options of the `validate` command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Options {
+    files: Vec<String>,
+    library: Option<String>,
+    format: Format,
+    deny_warnings: bool,
+}
+
+impl Options {
+    fn parse<I>(mut args: I) -> Result<Self, Error>
+    where
+        I: Iterator<Item = String>,
+    {
+        let mut options = Options {
+            files: Vec::new(),
+            library: None,
+            format: Format::Text,
+            deny_warnings: false,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--format" => {
+                    options.format = match option_value(&mut args, "--format")?.as_str() {
+                        "text" => Format::Text,
+                        "json" => Format::Json,
+                        format => return Err(Error::Usage(format!("unknown format {:?}", format))),
+                    }
+                }
+                "--json" => options.format = Format::Json,
+                "--library" => options.library = Some(option_value(&mut args, "--library")?),
+                "--deny-warnings" => options.deny_warnings = true,
+                _ if arg.starts_with("--") => {
+                    return Err(Error::Usage(format!("unknown option {:?}", arg)))
+                }
+                _ => options.files.push(arg),
+            }
+        }
+        if options.files.is_empty() {
+            return Err(Error::Usage("validate needs at least one file".to_owned()));
+        }
+        Ok(options)
+    }
+}
+
+/// A diagnostic about a file, as printed.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+struct Report {
+    severity: Severity,
+    message: String,
+    #[serde(flatten)]
+    details: Value,
+}
+
+impl Report {
+    fn new(diagnostic: &Diagnostic) -> Self {
+        Self {
+            severity: diagnostic.severity(),
+            message: diagnostic.to_string(),
+            details: serde_json::to_value(diagnostic).unwrap_or(Value::Null),
+        }
+    }
+
+    /// A file-level error which stops the file from being checked further.
+    fn error(kind: &str, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            details: json!({ "kind": kind }),
+        }
+    }
+}
+
+/// Check a graph file, returning its diagnostics.
+fn check(json: &str, library: Option<&Library<DynCondition>>) -> Vec<Report> {
+    let value: Value = match serde_json::from_str(json) {
+        Ok(value) => value,
+        Err(error) => return vec![Report::error("invalid_file", error.to_string())],
+    };
+
+    // Conditions of unknown types cannot be deserialized, so check them one
+    // by one to report every edge that has one.
+    let mut reports = Vec::new();
+    let edges = value["data"]["edges"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    for (index, edge) in edges.iter().enumerate() {
+        let condition = &edge[2]["condition"];
+        if let Err(error) = serde_json::from_value::<DynCondition>(condition.clone()) {
+            reports.push(Report {
+                severity: Severity::Error,
+                message: format!("edge {} has an invalid condition: {}", index, error),
+                details: json!({ "kind": "invalid_condition", "edge": index }),
+            });
+        }
+    }
+    if !reports.is_empty() {
+        return reports;
+    }
+
+    let graph: DialogueGraph<DynCondition> = match serde_json::from_value(value) {
+        Ok(graph) => graph,
+        Err(error) => return vec![Report::error("invalid_file", error.to_string())],
+    };
+    let mut diagnostics = graph.validate_structure(library).diagnostics;
+    diagnostics.extend(graph.validate().diagnostics);
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let mut report = Report::new(diagnostic);
+            // Opaque conditions read from a file are never true, as no type
+            // is left to evaluate them.
+            if let Diagnostic::UnknownCondition { .. } = diagnostic {
+                report.severity = Severity::Error;
+            }
+            report
+        })
+        .collect()
+}
+
+/// Run the `validate` command with its arguments.
+pub(crate) fn run<I, W>(args: I, output: &mut W) -> Result<(), Error>
+where
+    I: Iterator<Item = String>,
+    W: Write,
+{
+    let options = Options::parse(args)?;
+    let library = match &options.library {
+        Some(path) => Some(load_library(path)?),
+        None => None,
+    };
+    let files: Vec<(String, Vec<Report>)> = options
+        .files
+        .iter()
+        .map(|path| {
+            let reports = match fs::read_to_string(path) {
+                Ok(json) => check(&json, library.as_ref()),
+                Err(error) => vec![Report::error("unreadable_file", error.to_string())],
+            };
+            (path.clone(), reports)
+        })
+        .collect();
+    report(&files, &options, output)
+}
+
+/// Print the diagnostics of each file, failing if there are errors, or
+/// warnings when they are denied.
+fn report<W>(
+    files: &[(String, Vec<Report>)],
+    options: &Options,
+    output: &mut W,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    let count = |severity: Severity| {
+        files
+            .iter()
+            .flat_map(|(_, reports)| reports)
+            .filter(|report| report.severity == severity)
+            .count()
+    };
+    let errors = count(Severity::Error);
+    let warnings = count(Severity::Warning);
+
+    match options.format {
+        Format::Text => {
+            for (path, reports) in files {
+                for report in reports {
+                    let severity = match report.severity {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                    };
+                    writeln!(output, "{}: {}: {}", path, severity, report.message)?;
+                }
+            }
+            writeln!(
+                output,
+                "{} files checked: {} errors, {} warnings",
+                files.len(),
+                errors,
+                warnings
+            )?;
+        }
+        Format::Json => {
+            let files: Vec<Value> = files
+                .iter()
+                .map(|(path, reports)| json!({ "file": path, "diagnostics": reports }))
+                .collect();
+            let summary = json!({ "files": files, "errors": errors, "warnings": warnings });
+            writeln!(output, "{}", summary)?;
+        }
+    }
+
+    if errors > 0 || (options.deny_warnings && warnings > 0) {
+        Err(Error::Invalid { errors, warnings })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GRAPH: &str = r#"{
+        "version": 0,
+        "data": {
+            "nodes": ["A", "B", "Lost"],
+            "node_holes": [],
+            "edge_property": "directed",
+            "edges": [[0, 1, { "condition": { "type": "true" } }]]
+        }
+    }"#;
+
+    #[test]
+    fn test_check() {
+        let reports = check(GRAPH, None);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].severity, Severity::Warning);
+        assert_eq!(
+            reports[0].details,
+            json!({ "kind": "unreachable_node", "node": 2 })
+        );
+
+        let unknown = GRAPH.replace(r#""type": "true""#, r#""type": "weather""#);
+        let reports = check(&unknown, None);
+        assert_eq!(
+            reports[0].details,
+            json!({ "kind": "invalid_condition", "edge": 0 })
+        );
+
+        let opaque = GRAPH.replace(
+            r#""type": "true""#,
+            r#""type": "opaque", "name": "Weather", "value": null"#,
+        );
+        let reports = check(&opaque, None);
+        assert_eq!(reports[1].severity, Severity::Error);
+        assert_eq!(
+            reports[1].details,
+            json!({ "kind": "unknown_condition", "edge": 0, "name": "Weather" })
+        );
+
+        let reports = check("{", None);
+        assert_eq!(reports[0].details, json!({ "kind": "invalid_file" }));
+    }
+
+    #[test]
+    fn test_report() {
+        let mut options = Options::parse(vec!["a.json".to_owned()].into_iter()).unwrap();
+        let files = vec![("a.json".to_owned(), check(GRAPH, None))];
+
+        let mut output = Vec::new();
+        assert!(report(&files, &options, &mut output).is_ok());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "a.json: warning: node 2 cannot be reached from the entry node\n\
+             1 files checked: 0 errors, 1 warnings\n"
+        );
+
+        options.format = Format::Json;
+        options.deny_warnings = true;
+        let mut output = Vec::new();
+        assert!(report(&files, &options, &mut output).is_err());
+        let output: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(output["warnings"], 1);
+        assert_eq!(output["files"][0]["diagnostics"][0]["severity"], "warning");
+    }
+}
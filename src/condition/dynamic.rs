@@ -0,0 +1,513 @@
+use crate::{
+    condition::{satisfiability, Comparison, Condition, Context},
+    variable, DialogueGraph,
+};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::any;
+
+/// A condition tree built at runtime, used to analyse and simplify
+/// conditions regardless of their static type.
+///
+/// Any condition can be converted with [`Condition::to_dynamic`].
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::condition::{dynamic::DynCondition, And, Condition, Not, True};
+/// let condition = And::new(True::new(), Not::new(Not::new(True::new())));
+///
+/// assert_eq!(condition.to_dynamic().simplify(), DynCondition::True);
+/// ```
+///
+/// [`Condition::to_dynamic`]: ../trait.Condition.html#method.to_dynamic
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DynCondition {
+    /// A condition that always evaluates to true.
+    True,
+    /// A condition that always evaluates to false.
+    False,
+    /// A condition that evaluates as true if its inner condition evaluates as
+    /// false.
+    Not {
+        /// The condition to evaluate on.
+        condition: Box<DynCondition>,
+    },
+    /// A condition that evaluates as true if both inner conditions evaluate as
+    /// true.
+    And {
+        /// The first condition to evaluate on.
+        left: Box<DynCondition>,
+        /// The second condition to evaluate on.
+        right: Box<DynCondition>,
+    },
+    /// A condition that evaluates as true if either inner condition evaluates
+    /// as true.
+    Or {
+        /// The first condition to evaluate on.
+        left: Box<DynCondition>,
+        /// The second condition to evaluate on.
+        right: Box<DynCondition>,
+    },
+    /// A condition that evaluates as true if a variable is set and compares to
+    /// a value as given.
+    Compare {
+        /// The name of the variable to compare.
+        variable: String,
+        /// The comparison to make.
+        comparison: Comparison,
+        /// The value to compare the variable to.
+        value: variable::Value,
+    },
+    /// A condition that evaluates as true if a node has been visited in the
+    /// conversation.
+    Visited {
+        /// The node to check.
+        node: NodeIndex,
+    },
+    /// A condition that evaluates as true if a node has been visited at least
+    /// a given number of times in the conversation.
+    VisitCount {
+        /// The node to check.
+        node: NodeIndex,
+        /// The least number of visits.
+        count: u32,
+    },
+    /// A condition that evaluates as true if an edge has been traversed in the
+    /// conversation.
+    EdgeTaken {
+        /// The edge to check.
+        edge: EdgeIndex,
+    },
+    /// A condition whose structure is not known, identified by its type name
+    /// and serialized form. Opaque conditions cannot be evaluated and always
+    /// evaluate as false.
+    Opaque {
+        /// The name of the condition's type.
+        name: String,
+        /// The serialized condition.
+        value: Value,
+    },
+}
+
+/// Whether an edge can be traversed regardless of the state of the game.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Openness {
+    /// The edge's condition always evaluates as true.
+    AlwaysOpen,
+    /// The edge's condition always evaluates as false.
+    AlwaysClosed,
+    /// The edge's condition depends on the state of the game.
+    Conditional,
+}
+
+/// The simplified condition of an edge in a `DialogueGraph`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimplifiedEdge {
+    /// The index of the edge.
+    pub edge: EdgeIndex,
+    /// The simplified condition.
+    pub condition: DynCondition,
+    /// Whether the edge is statically open or closed.
+    pub openness: Openness,
+}
+
+/// The kind of a chain of binary operators.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Operator {
+    And,
+    Or,
+}
+
+impl DynCondition {
+    /// Create an opaque condition from any serializable condition.
+    pub fn opaque<T>(condition: &T) -> Self
+    where
+        T: Serialize + ?Sized,
+    {
+        Self::Opaque {
+            name: any::type_name::<T>().to_owned(),
+            value: serde_json::to_value(condition).unwrap_or(Value::Null),
+        }
+    }
+
+    /// Create a new `Not` condition.
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(condition: DynCondition) -> Self {
+        Self::Not {
+            condition: Box::new(condition),
+        }
+    }
+
+    /// Create a new `And` condition.
+    pub fn and(left: DynCondition, right: DynCondition) -> Self {
+        Self::And {
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    /// Create a new `Or` condition.
+    pub fn or(left: DynCondition, right: DynCondition) -> Self {
+        Self::Or {
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    /// Returns the number of conditions in the tree.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Not { condition } => 1 + condition.size(),
+            Self::And { left, right } | Self::Or { left, right } => 1 + left.size() + right.size(),
+            _ => 1,
+        }
+    }
+
+    /// Returns whether the condition is constant.
+    pub fn openness(&self) -> Openness {
+        match self {
+            Self::True => Openness::AlwaysOpen,
+            Self::False => Openness::AlwaysClosed,
+            _ => Openness::Conditional,
+        }
+    }
+
+    /// Returns whether some state of the game satisfies the condition.
+    ///
+    /// Variable comparisons and contradictions between identical conditions
+    /// are taken into account; opaque conditions are assumed to be
+    /// independent of each other. Conditions too large to analyse are assumed
+    /// to be satisfiable.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::condition::{dynamic::DynCondition, And, Comparison, Compare, Condition};
+    /// let condition = And::new(
+    ///     Compare::new("gold", Comparison::GreaterOrEqual, 10),
+    ///     Compare::new("gold", Comparison::Less, 5),
+    /// );
+    ///
+    /// assert!(!condition.to_dynamic().is_satisfiable());
+    /// ```
+    pub fn is_satisfiable(&self) -> bool {
+        match satisfiability::disjunctive_normal_form(self) {
+            Some(terms) => !terms.is_empty(),
+            None => true,
+        }
+    }
+
+    /// Returns an equivalent condition with double negations removed,
+    /// constants folded and duplicated or complementary operands of `And` and
+    /// `Or` merged.
+    ///
+    /// Negations are first pushed down to the leaves using De Morgan's laws so
+    /// that constants and complementary operands are exposed, then factored
+    /// back out where every operand of an `And` or `Or` is negated.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::condition::{dynamic::DynCondition, Condition, Not, Or, True};
+    /// let opaque = DynCondition::opaque("has_key");
+    /// let condition = DynCondition::or(opaque.clone(), Not::new(True::new()).to_dynamic());
+    ///
+    /// assert_eq!(condition.simplify(), opaque);
+    /// ```
+    pub fn simplify(&self) -> DynCondition {
+        self.normalize(false).fold().factor()
+    }
+
+    /// Push negations down to the leaves.
+    fn normalize(&self, negate: bool) -> DynCondition {
+        match self {
+            Self::True if negate => Self::False,
+            Self::False if negate => Self::True,
+            Self::Not { condition } => condition.normalize(!negate),
+            Self::And { left, right } if negate => {
+                Self::or(left.normalize(true), right.normalize(true))
+            }
+            Self::Or { left, right } if negate => {
+                Self::and(left.normalize(true), right.normalize(true))
+            }
+            Self::And { left, right } => Self::and(left.normalize(false), right.normalize(false)),
+            Self::Or { left, right } => Self::or(left.normalize(false), right.normalize(false)),
+            leaf if negate => Self::not(leaf.clone()),
+            leaf => leaf.clone(),
+        }
+    }
+
+    /// Fold constants and merge duplicated and complementary operands of a
+    /// condition in negation normal form.
+    fn fold(self) -> DynCondition {
+        let operator = match &self {
+            Self::And { .. } => Operator::And,
+            Self::Or { .. } => Operator::Or,
+            _ => return self,
+        };
+        // The identity element of the operator, and the element that absorbs
+        // the whole chain.
+        let (identity, absorbing) = match operator {
+            Operator::And => (Self::True, Self::False),
+            Operator::Or => (Self::False, Self::True),
+        };
+
+        let mut operands: Vec<DynCondition> = Vec::new();
+        for operand in self.into_operands(operator) {
+            let operand = operand.fold();
+            if operand == absorbing {
+                return absorbing;
+            }
+            if operand == identity || operands.contains(&operand) {
+                continue;
+            }
+            if operands.iter().any(|other| other.complements(&operand)) {
+                return absorbing;
+            }
+            operands.push(operand);
+        }
+
+        let mut operands = operands.into_iter();
+        let first = match operands.next() {
+            Some(first) => first,
+            None => return identity,
+        };
+        operands.fold(first, |left, right| match operator {
+            Operator::And => Self::and(left, right),
+            Operator::Or => Self::or(left, right),
+        })
+    }
+
+    /// Flatten a chain of the given operator into its operands.
+    fn into_operands(self, operator: Operator) -> Vec<DynCondition> {
+        match self {
+            Self::And { left, right } if operator == Operator::And => {
+                let mut operands = left.into_operands(operator);
+                operands.extend(right.into_operands(operator));
+                operands
+            }
+            Self::Or { left, right } if operator == Operator::Or => {
+                let mut operands = left.into_operands(operator);
+                operands.extend(right.into_operands(operator));
+                operands
+            }
+            other => vec![other],
+        }
+    }
+
+    /// Returns whether one condition is the negation of the other.
+    fn complements(&self, other: &DynCondition) -> bool {
+        match (self, other) {
+            (Self::Not { condition }, other) | (other, Self::Not { condition }) => {
+                **condition == *other
+            }
+            _ => false,
+        }
+    }
+
+    /// Factor negations out of operators whose operands are all negated.
+    fn factor(self) -> DynCondition {
+        match self {
+            Self::And { left, right } => match (left.factor(), right.factor()) {
+                (Self::Not { condition: left }, Self::Not { condition: right }) => {
+                    Self::not(Self::Or { left, right })
+                }
+                (left, right) => Self::and(left, right),
+            },
+            Self::Or { left, right } => match (left.factor(), right.factor()) {
+                (Self::Not { condition: left }, Self::Not { condition: right }) => {
+                    Self::not(Self::And { left, right })
+                }
+                (left, right) => Self::or(left, right),
+            },
+            other => other,
+        }
+    }
+}
+
+impl Condition for DynCondition {
+    fn evaluate(&self) -> bool {
+        self.evaluate_with(&Context::new())
+    }
+
+    fn evaluate_with(&self, context: &Context<'_>) -> bool {
+        match self {
+            Self::True => true,
+            Self::False => false,
+            Self::Not { condition } => !condition.evaluate_with(context),
+            Self::And { left, right } => {
+                left.evaluate_with(context) && right.evaluate_with(context)
+            }
+            Self::Or { left, right } => left.evaluate_with(context) || right.evaluate_with(context),
+            Self::Compare {
+                variable,
+                comparison,
+                value,
+            } => match context.variable(variable) {
+                Some(variable) => comparison.test(variable, value),
+                None => false,
+            },
+            Self::Visited { node } => context.visit_count(*node) > 0,
+            Self::VisitCount { node, count } => context.visit_count(*node) >= *count,
+            Self::EdgeTaken { edge } => context.traversal_count(*edge) > 0,
+            Self::Opaque { .. } => false,
+        }
+    }
+
+    fn to_dynamic(&self) -> DynCondition {
+        self.clone()
+    }
+
+    fn renumber(
+        &mut self,
+        nodes: &dyn Fn(NodeIndex) -> Option<NodeIndex>,
+        edges: &dyn Fn(EdgeIndex) -> Option<EdgeIndex>,
+    ) -> bool {
+        match self {
+            Self::Not { condition } => condition.renumber(nodes, edges),
+            Self::And { left, right } | Self::Or { left, right } => {
+                let left = left.renumber(nodes, edges);
+                right.renumber(nodes, edges) && left
+            }
+            Self::Visited { node } | Self::VisitCount { node, .. } => renumber(node, nodes),
+            Self::EdgeTaken { edge } => renumber(edge, edges),
+            Self::Opaque { .. } => false,
+            Self::True | Self::False | Self::Compare { .. } => true,
+        }
+    }
+}
+
+/// Rewrite an index, returning whether it could be.
+pub(crate) fn renumber<I>(index: &mut I, map: &dyn Fn(I) -> Option<I>) -> bool
+where
+    I: Copy,
+{
+    match map(*index) {
+        Some(renumbered) => {
+            *index = renumbered;
+            true
+        }
+        None => false,
+    }
+}
+
+impl<T> DialogueGraph<T>
+where
+    T: Condition,
+{
+    /// Simplify the condition of every edge in the graph, determining which
+    /// edges are statically open or closed.
+    pub fn simplify_conditions(&self) -> Vec<SimplifiedEdge> {
+        self.data
+            .edge_indices()
+            .filter_map(|edge| {
+                let condition = self
+                    .data
+                    .edge_weight(edge)?
+                    .condition
+                    .to_dynamic()
+                    .simplify();
+                Some(SimplifiedEdge {
+                    edge,
+                    openness: condition.openness(),
+                    condition,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        condition::{And, Not, Or, True},
+        Edge,
+    };
+
+    fn leaf(name: &str) -> DynCondition {
+        DynCondition::opaque(name)
+    }
+
+    #[test]
+    fn test_simplify_double_negation() {
+        let condition = DynCondition::not(DynCondition::not(leaf("x")));
+
+        assert_eq!(condition.simplify(), leaf("x"));
+    }
+
+    #[test]
+    fn test_simplify_identities() {
+        let and = DynCondition::and(DynCondition::True, leaf("x"));
+        let or = DynCondition::or(leaf("x"), DynCondition::not(DynCondition::True));
+
+        assert_eq!(and.simplify(), leaf("x"));
+        assert_eq!(or.simplify(), leaf("x"));
+    }
+
+    #[test]
+    fn test_simplify_complements() {
+        let and = DynCondition::and(
+            leaf("x"),
+            DynCondition::and(leaf("y"), DynCondition::not(leaf("x"))),
+        );
+        let or = DynCondition::or(DynCondition::not(leaf("x")), leaf("x"));
+
+        assert_eq!(and.simplify(), DynCondition::False);
+        assert_eq!(or.simplify(), DynCondition::True);
+    }
+
+    #[test]
+    fn test_simplify_duplicates() {
+        let condition = DynCondition::or(leaf("x"), DynCondition::or(leaf("y"), leaf("x")));
+
+        assert_eq!(condition.simplify(), DynCondition::or(leaf("x"), leaf("y")));
+    }
+
+    #[test]
+    fn test_simplify_de_morgan() {
+        let condition = DynCondition::not(DynCondition::and(
+            DynCondition::not(leaf("x")),
+            DynCondition::not(leaf("y")),
+        ));
+
+        assert_eq!(condition.simplify(), DynCondition::or(leaf("x"), leaf("y")));
+    }
+
+    #[test]
+    fn test_simplify_keeps_negated_operators() {
+        let condition = DynCondition::not(DynCondition::and(leaf("x"), leaf("y")));
+
+        assert_eq!(condition.simplify(), condition);
+    }
+
+    #[test]
+    fn test_simplify_conditions() {
+        let mut graph = DialogueGraph::<DynCondition>::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let open = graph.data.add_edge(
+            a,
+            b,
+            Edge::new(Or::new(True::new(), Not::new(True::new())).to_dynamic()),
+        );
+        let closed = graph.data.add_edge(
+            a,
+            b,
+            Edge::new(And::new(True::new(), Not::new(True::new())).to_dynamic()),
+        );
+        let conditional = graph.data.add_edge(a, b, Edge::new(leaf("x")));
+
+        let simplified = graph.simplify_conditions();
+        let openness: Vec<_> = simplified.iter().map(|s| (s.edge, s.openness)).collect();
+
+        assert_eq!(
+            openness,
+            vec![
+                (open, Openness::AlwaysOpen),
+                (closed, Openness::AlwaysClosed),
+                (conditional, Openness::Conditional),
+            ]
+        );
+    }
+}
@@ -1,4 +1,77 @@
+use crate::{
+    conversation::History,
+    variable::{Value, Variables},
+};
+use dynamic::DynCondition;
+use petgraph::graph::{EdgeIndex, NodeIndex};
 use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, fmt};
+
+/// Dynamic conditions
+pub mod dynamic;
+pub(crate) mod satisfiability;
+
+/// The state of the game that conditions are evaluated against.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::Context, variable::{Value, Variables}};
+/// let mut variables = Variables::new();
+/// variables.set("gold", 10);
+/// let context = Context::new().with_variables(&variables);
+///
+/// assert_eq!(context.variable("gold"), Some(&Value::Int(10)));
+/// ```
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Context<'a> {
+    variables: Option<&'a Variables>,
+    history: Option<&'a History>,
+}
+
+impl<'a> Context<'a> {
+    /// Create an empty context, in which no variables are set and nothing has
+    /// been visited.
+    pub fn new() -> Self {
+        Self {
+            variables: None,
+            history: None,
+        }
+    }
+
+    /// Use the given variables.
+    pub fn with_variables(mut self, variables: &'a Variables) -> Self {
+        self.variables = Some(variables);
+        self
+    }
+
+    /// Use the given conversation history.
+    pub fn with_history(mut self, history: &'a History) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Returns the value of a variable, if it is set.
+    pub fn variable(&self, name: &str) -> Option<&'a Value> {
+        self.variables.and_then(|variables| variables.get(name))
+    }
+
+    /// Returns the number of times a node has been visited.
+    pub fn visit_count(&self, node: NodeIndex) -> u32 {
+        self.history.map_or(0, |history| history.visit_count(node))
+    }
+
+    /// Returns the number of times an edge has been traversed.
+    pub fn traversal_count(&self, edge: EdgeIndex) -> u32 {
+        self.history
+            .map_or(0, |history| history.traversal_count(edge))
+    }
+    /// Returns the number of traversals since an edge was last traversed, if
+    /// it has been.
+    pub fn traversals_since(&self, edge: EdgeIndex) -> Option<usize> {
+        self.history
+            .and_then(|history| history.traversals_since(edge))
+    }
+}
 
 /// A conditional weight that must evaluate as true in order to be avaliable to
 /// make an edge clear to traverse.
@@ -6,6 +79,39 @@ use serde::{Deserialize, Serialize};
 pub trait Condition: Serialize + for<'de> Deserialize<'de> {
     /// Returns whether the node can be traversed based on its criteria.
     fn evaluate(&self) -> bool;
+
+    /// Returns whether the node can be traversed based on its criteria and the
+    /// state of the game.
+    ///
+    /// Defaults to `evaluate`, for conditions that do not depend on the state
+    /// of the game.
+    fn evaluate_with(&self, _context: &Context<'_>) -> bool {
+        self.evaluate()
+    }
+
+    /// Returns the dynamic representation of the condition, used to analyse
+    /// and simplify it.
+    ///
+    /// Conditions are opaque by default, identified by their type and
+    /// serialized form.
+    fn to_dynamic(&self) -> DynCondition {
+        DynCondition::opaque(self)
+    }
+
+    /// Rewrite the indices of the nodes and edges the condition refers to
+    /// after the graph they are in is renumbered, returning whether every
+    /// one could be rewritten. Indices which cannot be are left as they are.
+    ///
+    /// Defaults to rewriting nothing and succeeding, for conditions which
+    /// hold no indices. Conditions which hold the indices of nodes or edges
+    /// must override it.
+    fn renumber(
+        &mut self,
+        _nodes: &dyn Fn(NodeIndex) -> Option<NodeIndex>,
+        _edges: &dyn Fn(EdgeIndex) -> Option<EdgeIndex>,
+    ) -> bool {
+        true
+    }
 }
 
 /// A condition that always evaluates to true.
@@ -31,6 +137,10 @@ impl Condition for True {
     fn evaluate(&self) -> bool {
         true
     }
+
+    fn to_dynamic(&self) -> DynCondition {
+        DynCondition::True
+    }
 }
 
 /// A condition that evaluates as true if its inner condition evaluates as
@@ -69,6 +179,22 @@ where
     fn evaluate(&self) -> bool {
         !self.condition.evaluate()
     }
+
+    fn evaluate_with(&self, context: &Context<'_>) -> bool {
+        !self.condition.evaluate_with(context)
+    }
+
+    fn to_dynamic(&self) -> DynCondition {
+        DynCondition::not(self.condition.to_dynamic())
+    }
+
+    fn renumber(
+        &mut self,
+        nodes: &dyn Fn(NodeIndex) -> Option<NodeIndex>,
+        edges: &dyn Fn(EdgeIndex) -> Option<EdgeIndex>,
+    ) -> bool {
+        self.condition.renumber(nodes, edges)
+    }
 }
 
 /// A condition that evaluates as true if both inner conditions evaluate as
@@ -114,6 +240,23 @@ where
     fn evaluate(&self) -> bool {
         self.left.evaluate() && self.right.evaluate()
     }
+
+    fn evaluate_with(&self, context: &Context<'_>) -> bool {
+        self.left.evaluate_with(context) && self.right.evaluate_with(context)
+    }
+
+    fn to_dynamic(&self) -> DynCondition {
+        DynCondition::and(self.left.to_dynamic(), self.right.to_dynamic())
+    }
+
+    fn renumber(
+        &mut self,
+        nodes: &dyn Fn(NodeIndex) -> Option<NodeIndex>,
+        edges: &dyn Fn(EdgeIndex) -> Option<EdgeIndex>,
+    ) -> bool {
+        let left = self.left.renumber(nodes, edges);
+        self.right.renumber(nodes, edges) && left
+    }
 }
 
 /// A condition that evaluates as true if either inner condition evaluates as
@@ -161,6 +304,305 @@ where
     fn evaluate(&self) -> bool {
         self.left.evaluate() || self.right.evaluate()
     }
+
+    fn evaluate_with(&self, context: &Context<'_>) -> bool {
+        self.left.evaluate_with(context) || self.right.evaluate_with(context)
+    }
+
+    fn to_dynamic(&self) -> DynCondition {
+        DynCondition::or(self.left.to_dynamic(), self.right.to_dynamic())
+    }
+
+    fn renumber(
+        &mut self,
+        nodes: &dyn Fn(NodeIndex) -> Option<NodeIndex>,
+        edges: &dyn Fn(EdgeIndex) -> Option<EdgeIndex>,
+    ) -> bool {
+        let left = self.left.renumber(nodes, edges);
+        self.right.renumber(nodes, edges) && left
+    }
+}
+
+/// A comparison between a variable and a value.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    /// The variable equals the value.
+    Equal,
+    /// The variable does not equal the value.
+    NotEqual,
+    /// The variable is less than the value.
+    Less,
+    /// The variable is less than or equal to the value.
+    LessOrEqual,
+    /// The variable is greater than the value.
+    Greater,
+    /// The variable is greater than or equal to the value.
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// Returns whether the comparison holds between the two values.
+    ///
+    /// Values of different kinds are never equal and cannot be ordered.
+    pub fn test(self, left: &Value, right: &Value) -> bool {
+        match left.compare(right) {
+            None => self == Comparison::NotEqual,
+            Some(ordering) => match self {
+                Comparison::Equal => ordering == Ordering::Equal,
+                Comparison::NotEqual => ordering != Ordering::Equal,
+                Comparison::Less => ordering == Ordering::Less,
+                Comparison::LessOrEqual => ordering != Ordering::Greater,
+                Comparison::Greater => ordering == Ordering::Greater,
+                Comparison::GreaterOrEqual => ordering != Ordering::Less,
+            },
+        }
+    }
+
+    /// Returns the comparison that holds whenever this one does not, for
+    /// values of the same kind.
+    pub fn negate(self) -> Self {
+        match self {
+            Comparison::Equal => Comparison::NotEqual,
+            Comparison::NotEqual => Comparison::Equal,
+            Comparison::Less => Comparison::GreaterOrEqual,
+            Comparison::LessOrEqual => Comparison::Greater,
+            Comparison::Greater => Comparison::LessOrEqual,
+            Comparison::GreaterOrEqual => Comparison::Less,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+        })
+    }
+}
+
+/// A condition that evaluates as true if a variable is set and compares to a
+/// value as given.
+///
+/// Without a `Context`, no variables are set and the condition evaluates as
+/// false.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::{Comparison, Compare, Condition, Context}, variable::Variables};
+/// let mut variables = Variables::new();
+/// variables.set("gold", 10);
+/// let context = Context::new().with_variables(&variables);
+///
+/// let compare = Compare::new("gold", Comparison::GreaterOrEqual, 5);
+///
+/// assert_eq!(compare.evaluate_with(&context), true);
+/// assert_eq!(compare.evaluate(), false);
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Compare {
+    /// The name of the variable to compare.
+    pub variable: String,
+    /// The comparison to make.
+    pub comparison: Comparison,
+    /// The value to compare the variable to.
+    pub value: Value,
+}
+
+impl Compare {
+    /// Create a new `Compare` condition.
+    pub fn new<V>(variable: &str, comparison: Comparison, value: V) -> Self
+    where
+        V: Into<Value>,
+    {
+        Self {
+            variable: variable.to_owned(),
+            comparison,
+            value: value.into(),
+        }
+    }
+}
+
+impl Condition for Compare {
+    fn evaluate(&self) -> bool {
+        self.evaluate_with(&Context::new())
+    }
+
+    fn evaluate_with(&self, context: &Context<'_>) -> bool {
+        match context.variable(&self.variable) {
+            Some(variable) => self.comparison.test(variable, &self.value),
+            None => false,
+        }
+    }
+
+    fn to_dynamic(&self) -> DynCondition {
+        DynCondition::Compare {
+            variable: self.variable.clone(),
+            comparison: self.comparison,
+            value: self.value.clone(),
+        }
+    }
+}
+
+/// A condition that evaluates as true if a node has been visited in the
+/// conversation.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::{Condition, Context, Visited}, conversation::History};
+/// # use petgraph::graph::NodeIndex;
+/// let node = NodeIndex::new(0);
+/// let mut history = History::new();
+/// history.record_visit(node);
+///
+/// let visited = Visited::new(node);
+///
+/// assert_eq!(visited.evaluate_with(&Context::new().with_history(&history)), true);
+/// assert_eq!(visited.evaluate(), false);
+/// ```
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Visited {
+    /// The node to check.
+    pub node: NodeIndex,
+}
+
+impl Visited {
+    /// Create a new `Visited` condition.
+    pub fn new(node: NodeIndex) -> Self {
+        Self { node }
+    }
+}
+
+impl Condition for Visited {
+    fn evaluate(&self) -> bool {
+        self.evaluate_with(&Context::new())
+    }
+
+    fn evaluate_with(&self, context: &Context<'_>) -> bool {
+        context.visit_count(self.node) > 0
+    }
+
+    fn to_dynamic(&self) -> DynCondition {
+        DynCondition::Visited { node: self.node }
+    }
+
+    fn renumber(
+        &mut self,
+        nodes: &dyn Fn(NodeIndex) -> Option<NodeIndex>,
+        _edges: &dyn Fn(EdgeIndex) -> Option<EdgeIndex>,
+    ) -> bool {
+        dynamic::renumber(&mut self.node, nodes)
+    }
+}
+
+/// A condition that evaluates as true if a node has been visited at least a
+/// given number of times in the conversation.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::{Condition, Context, VisitCount}, conversation::History};
+/// # use petgraph::graph::NodeIndex;
+/// let node = NodeIndex::new(0);
+/// let mut history = History::new();
+/// history.record_visit(node);
+/// let context = Context::new().with_history(&history);
+///
+/// assert_eq!(VisitCount::new(node, 1).evaluate_with(&context), true);
+/// assert_eq!(VisitCount::new(node, 2).evaluate_with(&context), false);
+/// ```
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VisitCount {
+    /// The node to check.
+    pub node: NodeIndex,
+    /// The least number of visits.
+    pub count: u32,
+}
+
+impl VisitCount {
+    /// Create a new `VisitCount` condition.
+    pub fn new(node: NodeIndex, count: u32) -> Self {
+        Self { node, count }
+    }
+}
+
+impl Condition for VisitCount {
+    fn evaluate(&self) -> bool {
+        self.evaluate_with(&Context::new())
+    }
+
+    fn evaluate_with(&self, context: &Context<'_>) -> bool {
+        context.visit_count(self.node) >= self.count
+    }
+
+    fn to_dynamic(&self) -> DynCondition {
+        DynCondition::VisitCount {
+            node: self.node,
+            count: self.count,
+        }
+    }
+
+    fn renumber(
+        &mut self,
+        nodes: &dyn Fn(NodeIndex) -> Option<NodeIndex>,
+        _edges: &dyn Fn(EdgeIndex) -> Option<EdgeIndex>,
+    ) -> bool {
+        dynamic::renumber(&mut self.node, nodes)
+    }
+}
+
+/// A condition that evaluates as true if an edge has been traversed in the
+/// conversation.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::{Condition, Context, EdgeTaken}, conversation::History};
+/// # use petgraph::graph::EdgeIndex;
+/// let edge = EdgeIndex::new(0);
+/// let mut history = History::new();
+/// history.record_traversal(edge);
+///
+/// let taken = EdgeTaken::new(edge);
+///
+/// assert_eq!(taken.evaluate_with(&Context::new().with_history(&history)), true);
+/// ```
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EdgeTaken {
+    /// The edge to check.
+    pub edge: EdgeIndex,
+}
+
+impl EdgeTaken {
+    /// Create a new `EdgeTaken` condition.
+    pub fn new(edge: EdgeIndex) -> Self {
+        Self { edge }
+    }
+}
+
+impl Condition for EdgeTaken {
+    fn evaluate(&self) -> bool {
+        self.evaluate_with(&Context::new())
+    }
+
+    fn evaluate_with(&self, context: &Context<'_>) -> bool {
+        context.traversal_count(self.edge) > 0
+    }
+
+    fn to_dynamic(&self) -> DynCondition {
+        DynCondition::EdgeTaken { edge: self.edge }
+    }
+
+    fn renumber(
+        &mut self,
+        _nodes: &dyn Fn(NodeIndex) -> Option<NodeIndex>,
+        edges: &dyn Fn(EdgeIndex) -> Option<EdgeIndex>,
+    ) -> bool {
+        dynamic::renumber(&mut self.edge, edges)
+    }
 }
 
 /// A condition that evaluates an inner function with data.
@@ -0,0 +1,330 @@
+use super::{dynamic::DynCondition, Comparison};
+use crate::variable::Value;
+use std::cmp::Ordering;
+
+/// The largest number of terms a condition's disjunctive normal form may
+/// have before analysis gives up on it.
+pub(crate) const TERM_LIMIT: usize = 256;
+
+/// A leaf condition, possibly negated.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Literal {
+    atom: DynCondition,
+    negated: bool,
+}
+
+/// A conjunction of literals.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Term {
+    literals: Vec<Literal>,
+}
+
+/// Returns the disjunctive normal form of a condition with unsatisfiable
+/// terms removed, or `None` if it would exceed `TERM_LIMIT` terms.
+///
+/// An empty result means the condition is unsatisfiable.
+pub(crate) fn disjunctive_normal_form(condition: &DynCondition) -> Option<Vec<Term>> {
+    terms(condition, false)
+}
+
+fn terms(condition: &DynCondition, negated: bool) -> Option<Vec<Term>> {
+    match condition {
+        DynCondition::True if negated => Some(vec![]),
+        DynCondition::True => Some(vec![Term::default()]),
+        DynCondition::False if negated => Some(vec![Term::default()]),
+        DynCondition::False => Some(vec![]),
+        DynCondition::Not { condition } => terms(condition, !negated),
+        DynCondition::And { left, right } if !negated => {
+            product(terms(left, false)?, terms(right, false)?)
+        }
+        DynCondition::Or { left, right } if negated => {
+            product(terms(left, true)?, terms(right, true)?)
+        }
+        DynCondition::And { left, right } | DynCondition::Or { left, right } => {
+            let mut union = terms(left, negated)?;
+            union.extend(terms(right, negated)?);
+            if union.len() > TERM_LIMIT {
+                None
+            } else {
+                Some(union)
+            }
+        }
+        atom => {
+            let term = Term {
+                literals: vec![Literal {
+                    atom: atom.clone(),
+                    negated,
+                }],
+            };
+            if term.is_satisfiable() {
+                Some(vec![term])
+            } else {
+                Some(vec![])
+            }
+        }
+    }
+}
+
+fn product(left: Vec<Term>, right: Vec<Term>) -> Option<Vec<Term>> {
+    let mut product = Vec::new();
+    for left in &left {
+        for right in &right {
+            if let Some(term) = left.conjoin(right) {
+                product.push(term);
+                if product.len() > TERM_LIMIT {
+                    return None;
+                }
+            }
+        }
+    }
+    Some(product)
+}
+
+impl Term {
+    /// Returns the conjunction of both terms, or `None` if it is
+    /// unsatisfiable.
+    pub(crate) fn conjoin(&self, other: &Term) -> Option<Term> {
+        let mut term = self.clone();
+        for literal in &other.literals {
+            if !term.literals.contains(literal) {
+                term.literals.push(literal.clone());
+            }
+        }
+        if term.is_satisfiable() {
+            Some(term)
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether every literal of this term is in the other, so that
+    /// this term holds whenever the other does.
+    pub(crate) fn subsumes(&self, other: &Term) -> bool {
+        self.literals
+            .iter()
+            .all(|literal| other.literals.contains(literal))
+    }
+
+    /// Returns the term restricted to variable comparisons, which keep their
+    /// value for the whole conversation.
+    pub(crate) fn comparisons(&self) -> Term {
+        Term {
+            literals: self
+                .literals
+                .iter()
+                .filter(|literal| matches!(literal.atom, DynCondition::Compare { .. }))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Returns whether some state of the game satisfies every literal.
+    pub(crate) fn is_satisfiable(&self) -> bool {
+        let mut variables: Vec<&str> = Vec::new();
+        for (index, literal) in self.literals.iter().enumerate() {
+            match &literal.atom {
+                DynCondition::Compare { variable, .. } => {
+                    if !variables.contains(&variable.as_str()) {
+                        variables.push(variable);
+                    }
+                }
+                atom => {
+                    let complement = self.literals[index + 1..]
+                        .iter()
+                        .any(|other| other.atom == *atom && other.negated != literal.negated);
+                    if complement {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        variables.into_iter().all(|name| {
+            let comparisons: Vec<(Comparison, &Value, bool)> = self
+                .literals
+                .iter()
+                .filter_map(|literal| match &literal.atom {
+                    DynCondition::Compare {
+                        variable,
+                        comparison,
+                        value,
+                    } if variable == name => Some((*comparison, value, literal.negated)),
+                    _ => None,
+                })
+                .collect();
+            variable_is_satisfiable(&comparisons)
+        })
+    }
+}
+
+/// Returns whether some value of a single variable satisfies every
+/// comparison, where negated comparisons also hold when it is unset.
+fn variable_is_satisfiable(comparisons: &[(Comparison, &Value, bool)]) -> bool {
+    // Comparisons with an unset variable never hold, so their negations do.
+    if comparisons.iter().all(|(_, _, negated)| *negated) {
+        return true;
+    }
+
+    let samples = [Value::Bool(false), Value::Int(0), Value::Str(String::new())];
+    samples.iter().any(|kind| {
+        let mut same_kind = Vec::new();
+        for (comparison, value, negated) in comparisons {
+            if value.compare(kind).is_some() {
+                let comparison = if *negated {
+                    comparison.negate()
+                } else {
+                    *comparison
+                };
+                same_kind.push((comparison, *value));
+            } else if (*comparison == Comparison::NotEqual) == *negated {
+                // Values of different kinds are never equal or ordered.
+                return false;
+            }
+        }
+        kind_is_satisfiable(kind, &same_kind)
+    })
+}
+
+/// Returns whether some value of the same kind as `kind` satisfies every
+/// comparison.
+fn kind_is_satisfiable(kind: &Value, comparisons: &[(Comparison, &Value)]) -> bool {
+    let holds = |candidate: &Value| {
+        comparisons
+            .iter()
+            .all(|(comparison, value)| comparison.test(candidate, value))
+    };
+
+    match kind {
+        Value::Bool(_) => holds(&Value::Bool(false)) || holds(&Value::Bool(true)),
+        Value::Int(_) => {
+            let mut low = i128::from(i64::MIN);
+            let mut high = i128::from(i64::MAX);
+            let mut excluded = Vec::new();
+            for (comparison, value) in comparisons {
+                let value = match value {
+                    Value::Int(value) => i128::from(*value),
+                    _ => continue,
+                };
+                match comparison {
+                    Comparison::Equal => {
+                        low = low.max(value);
+                        high = high.min(value);
+                    }
+                    Comparison::NotEqual => excluded.push(value),
+                    Comparison::Less => high = high.min(value - 1),
+                    Comparison::LessOrEqual => high = high.min(value),
+                    Comparison::Greater => low = low.max(value + 1),
+                    Comparison::GreaterOrEqual => low = low.max(value),
+                }
+            }
+            excluded.sort();
+            excluded.dedup();
+            let excluded = excluded
+                .iter()
+                .filter(|value| low <= **value && **value <= high)
+                .count();
+            low <= high && high - low + 1 > excluded as i128
+        }
+        Value::Str(_) => {
+            if let Some((_, value)) = comparisons
+                .iter()
+                .find(|(comparison, _)| *comparison == Comparison::Equal)
+            {
+                return holds(value);
+            }
+            // Without an exact value, only check that the bounds leave room
+            // for some string.
+            let lower = comparisons.iter().filter(|(comparison, _)| {
+                matches!(comparison, Comparison::Greater | Comparison::GreaterOrEqual)
+            });
+            let upper = comparisons.iter().filter(|(comparison, _)| {
+                matches!(comparison, Comparison::Less | Comparison::LessOrEqual)
+            });
+            lower.clone().all(|(low_comparison, low)| {
+                upper
+                    .clone()
+                    .all(|(high_comparison, high)| match low.compare(high) {
+                        Some(Ordering::Less) => true,
+                        Some(Ordering::Equal) => {
+                            *low_comparison == Comparison::GreaterOrEqual
+                                && *high_comparison == Comparison::LessOrEqual
+                                && holds(low)
+                        }
+                        _ => false,
+                    })
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn compare(comparison: Comparison, value: Value) -> DynCondition {
+        DynCondition::Compare {
+            variable: "gold".to_owned(),
+            comparison,
+            value,
+        }
+    }
+
+    fn is_satisfiable(condition: &DynCondition) -> bool {
+        !disjunctive_normal_form(condition).unwrap().is_empty()
+    }
+
+    #[test]
+    fn test_complementary_atoms() {
+        let atom = DynCondition::opaque("x");
+        let condition = DynCondition::and(atom.clone(), DynCondition::not(atom));
+
+        assert!(!is_satisfiable(&condition));
+    }
+
+    #[test]
+    fn test_integer_ranges() {
+        let empty = DynCondition::and(
+            compare(Comparison::GreaterOrEqual, Value::Int(10)),
+            compare(Comparison::Less, Value::Int(5)),
+        );
+        let single = DynCondition::and(
+            compare(Comparison::GreaterOrEqual, Value::Int(5)),
+            compare(Comparison::LessOrEqual, Value::Int(5)),
+        );
+        let excluded =
+            DynCondition::and(single.clone(), compare(Comparison::NotEqual, Value::Int(5)));
+
+        assert!(!is_satisfiable(&empty));
+        assert!(is_satisfiable(&single));
+        assert!(!is_satisfiable(&excluded));
+    }
+
+    #[test]
+    fn test_negations_hold_when_unset() {
+        let condition = DynCondition::and(
+            DynCondition::not(compare(Comparison::Less, Value::Int(5))),
+            DynCondition::not(compare(Comparison::GreaterOrEqual, Value::Int(5))),
+        );
+
+        assert!(is_satisfiable(&condition));
+        assert!(!is_satisfiable(&DynCondition::and(
+            condition,
+            compare(Comparison::Equal, Value::Int(0))
+        )));
+    }
+
+    #[test]
+    fn test_mixed_kinds() {
+        let condition = DynCondition::and(
+            compare(Comparison::Equal, Value::Bool(true)),
+            compare(Comparison::Equal, Value::Int(1)),
+        );
+        let not_equal = DynCondition::and(
+            compare(Comparison::Equal, Value::Bool(true)),
+            compare(Comparison::NotEqual, Value::Int(1)),
+        );
+
+        assert!(!is_satisfiable(&condition));
+        assert!(is_satisfiable(&not_equal));
+    }
+}
@@ -0,0 +1,1187 @@
+use crate::{
+    condition::{Condition, Context},
+    library::Library,
+    variable::Variables,
+    DialogueGraph, EdgeKind, Node,
+};
+use petgraph::{
+    graph::{EdgeIndex, NodeIndex},
+    visit::EdgeRef,
+};
+use rand::{rngs::StdRng, seq::IteratorRandom, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error, fmt};
+
+/// The most automatic edges followed in a row before the conversation is
+/// assumed to be stuck in a cycle.
+pub const AUTO_ADVANCE_LIMIT: usize = 1024;
+
+/// The nodes visited and edges traversed during a conversation.
+///
+/// Only counts are kept, so a history stays the same size however long the
+/// conversation runs. The order edges were traversed in is only kept by a
+/// history created with [`with_path`](#method.with_path).
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct History {
+    visits: BTreeMap<NodeIndex, u32>,
+    traversals: BTreeMap<EdgeIndex, u32>,
+    /// The number of traversals recorded.
+    total: u64,
+    /// The number of traversals recorded up to each edge's last traversal.
+    last: BTreeMap<EdgeIndex, u64>,
+    path: Option<Vec<EdgeIndex>>,
+}
+
+impl History {
+    /// Create an empty history.
+    pub fn new() -> Self {
+        Self {
+            visits: BTreeMap::new(),
+            traversals: BTreeMap::new(),
+            total: 0,
+            last: BTreeMap::new(),
+            path: None,
+        }
+    }
+
+    /// Keep the order edges are traversed in, for [`path`](#method.path).
+    pub fn with_path(mut self) -> Self {
+        self.path = Some(Vec::new());
+        self
+    }
+
+    /// Returns an empty history which keeps its path if this one does.
+    pub(crate) fn empty_like(&self) -> Self {
+        match self.path {
+            Some(_) => Self::new().with_path(),
+            None => Self::new(),
+        }
+    }
+
+    /// Record a visit to a node.
+    pub fn record_visit(&mut self, node: NodeIndex) {
+        *self.visits.entry(node).or_insert(0) += 1;
+    }
+
+    /// Record a traversal of an edge.
+    pub fn record_traversal(&mut self, edge: EdgeIndex) {
+        *self.traversals.entry(edge).or_insert(0) += 1;
+        self.total += 1;
+        let _ = self.last.insert(edge, self.total);
+        if let Some(path) = &mut self.path {
+            path.push(edge);
+        }
+    }
+
+    /// Returns whether a node has been visited.
+    pub fn visited(&self, node: NodeIndex) -> bool {
+        self.visit_count(node) > 0
+    }
+
+    /// Returns the number of times a node has been visited.
+    pub fn visit_count(&self, node: NodeIndex) -> u32 {
+        self.visits.get(&node).copied().unwrap_or(0)
+    }
+
+    /// Returns whether an edge has been traversed.
+    pub fn taken(&self, edge: EdgeIndex) -> bool {
+        self.traversal_count(edge) > 0
+    }
+
+    /// Returns the number of times an edge has been traversed.
+    pub fn traversal_count(&self, edge: EdgeIndex) -> u32 {
+        self.traversals.get(&edge).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of traversals since an edge was last traversed, if
+    /// it has been.
+    pub fn traversals_since(&self, edge: EdgeIndex) -> Option<usize> {
+        self.last
+            .get(&edge)
+            .map(|last| (self.total - last) as usize)
+    }
+
+    /// Returns an iterator over the visited nodes and their visit counts,
+    /// ordered by index.
+    pub fn visits(&self) -> impl Iterator<Item = (NodeIndex, u32)> + '_ {
+        self.visits.iter().map(|(node, count)| (*node, *count))
+    }
+
+    /// Returns an iterator over the traversed edges and their traversal
+    /// counts, ordered by index.
+    pub fn traversals(&self) -> impl Iterator<Item = (EdgeIndex, u32)> + '_ {
+        self.traversals.iter().map(|(edge, count)| (*edge, *count))
+    }
+
+    /// Returns the traversed edges, in the order they were traversed, or
+    /// nothing unless the history was created to keep them.
+    pub fn path(&self) -> &[EdgeIndex] {
+        self.path.as_deref().unwrap_or(&[])
+    }
+}
+
+/// An error raised when a conversation cannot proceed as asked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConversationError {
+    /// The edge does not lead out of the current node.
+    InvalidEdge(EdgeIndex),
+    /// The edge's condition is not met.
+    ClosedEdge(EdgeIndex),
+    /// The edge has used up its traversals or is cooling down.
+    ExhaustedEdge(EdgeIndex),
+    /// Automatic edges kept leading on from the node without stopping.
+    AutoAdvanceCycle(NodeIndex),
+    /// The edge calls a graph which is not in the library or has no nodes.
+    InvalidCall(EdgeIndex),
+    /// The graph to return to from a called graph is not in the library.
+    MissingCaller(String),
+}
+
+impl fmt::Display for ConversationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversationError::InvalidEdge(edge) => write!(
+                f,
+                "edge {} does not lead out of the current node",
+                edge.index()
+            ),
+            ConversationError::ClosedEdge(edge) => write!(f, "edge {} is closed", edge.index()),
+            ConversationError::ExhaustedEdge(edge) => write!(
+                f,
+                "edge {} has used up its traversals or is cooling down",
+                edge.index()
+            ),
+            ConversationError::AutoAdvanceCycle(node) => write!(
+                f,
+                "automatic edges from node {} did not stop after {} steps",
+                node.index(),
+                AUTO_ADVANCE_LIMIT
+            ),
+            ConversationError::InvalidCall(edge) => write!(
+                f,
+                "edge {} calls a graph which is not in the library or has no nodes",
+                edge.index()
+            ),
+            ConversationError::MissingCaller(graph) => write!(
+                f,
+                "the graph {:?} to return to is not in the library",
+                graph
+            ),
+        }
+    }
+}
+
+impl Error for ConversationError {}
+
+/// An error raised when a saved `ConversationState` does not fit the graph it
+/// is resumed in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StateError {
+    /// The state refers to a graph which is not in the library.
+    MissingGraph(String),
+    /// The state refers to a node which is not in the graph.
+    MissingNode(NodeIndex),
+    /// The state refers to an edge which is not in the graph.
+    MissingEdge(EdgeIndex),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::MissingGraph(name) => {
+                write!(f, "graph {:?} is not in the library", name)
+            }
+            StateError::MissingNode(node) => {
+                write!(f, "node {} is not in the graph", node.index())
+            }
+            StateError::MissingEdge(edge) => {
+                write!(f, "edge {} is not in the graph", edge.index())
+            }
+        }
+    }
+}
+
+impl Error for StateError {}
+
+/// A node in one of the graphs a conversation can be in.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    /// The name of the graph in the library, or `None` for the graph the
+    /// conversation was started in.
+    pub graph: Option<String>,
+    /// The index of the node.
+    pub node: NodeIndex,
+}
+
+impl Position {
+    /// Create a position.
+    pub fn new(graph: Option<&str>, node: NodeIndex) -> Self {
+        Self {
+            graph: graph.map(str::to_owned),
+            node,
+        }
+    }
+}
+
+/// A called graph's caller, which the conversation returns to once the called
+/// graph is finished.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Frame {
+    /// The name of the calling graph in the library, or `None` for the graph
+    /// the conversation was started in.
+    pub graph: Option<String>,
+    /// The index of the node to continue from.
+    pub node: NodeIndex,
+}
+
+/// The state of a conversation, which can be saved and resumed independently
+/// of the `DialogueGraph` it takes place in.
+///
+/// Every field is written, even when empty, so that the state can be read
+/// back from formats which read fields by position.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConversationState {
+    /// The name of the current graph in the library, or `None` for the graph
+    /// the conversation was started in.
+    #[serde(default)]
+    pub graph: Option<String>,
+    /// The index of the current node.
+    pub current: NodeIndex,
+    /// The nodes visited and edges traversed so far in the graph the
+    /// conversation was started in.
+    pub history: History,
+    /// The nodes visited and edges traversed so far in each graph called from
+    /// the library, by name. A graph's history is kept from one call to the
+    /// next, so usage limits and history conditions hold across calls.
+    #[serde(default)]
+    pub histories: BTreeMap<String, History>,
+    /// The callers of the current graph, innermost last.
+    #[serde(default)]
+    pub stack: Vec<Frame>,
+    /// The variables conditions are evaluated against.
+    pub variables: Variables,
+    /// The seed for the next random choice.
+    pub seed: u64,
+}
+
+impl ConversationState {
+    /// Create the state of a conversation starting at the given node.
+    pub fn new(start: NodeIndex) -> Self {
+        let mut history = History::new();
+        history.record_visit(start);
+        Self {
+            graph: None,
+            current: start,
+            history,
+            histories: BTreeMap::new(),
+            stack: Vec::new(),
+            variables: Variables::new(),
+            seed: rand::random(),
+        }
+    }
+
+    /// Check that every node and edge the state refers to is in the graph.
+    pub fn validate<T>(&self, graph: &DialogueGraph<T>) -> Result<(), StateError>
+    where
+        T: Condition,
+    {
+        self.validate_with_library(graph, None)
+    }
+
+    /// Check that every graph the state refers to is in the library, and that
+    /// every node and edge it refers to is in the graph it belongs to.
+    pub fn validate_with_library<T>(
+        &self,
+        graph: &DialogueGraph<T>,
+        library: Option<&Library<T>>,
+    ) -> Result<(), StateError>
+    where
+        T: Condition,
+    {
+        let resolve = |name: &Option<String>| match name {
+            None => Ok(graph),
+            Some(name) => library
+                .and_then(|library| library.get(name))
+                .ok_or_else(|| StateError::MissingGraph(name.clone())),
+        };
+
+        validate_history(resolve(&self.graph)?, Some(self.current), &History::new())?;
+        validate_history(graph, None, &self.history)?;
+        for (name, history) in &self.histories {
+            validate_history(resolve(&Some(name.clone()))?, None, history)?;
+        }
+        for frame in &self.stack {
+            validate_history(resolve(&frame.graph)?, Some(frame.node), &History::new())?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that a node, if any, and every node and edge in a history are in
+/// the graph.
+fn validate_history<T>(
+    graph: &DialogueGraph<T>,
+    node: Option<NodeIndex>,
+    history: &History,
+) -> Result<(), StateError>
+where
+    T: Condition,
+{
+    let nodes = node
+        .into_iter()
+        .chain(history.visits().map(|(node, _)| node));
+    for node in nodes {
+        if graph.data.node_weight(node).is_none() {
+            return Err(StateError::MissingNode(node));
+        }
+    }
+
+    let edges = history
+        .traversals()
+        .map(|(edge, _)| edge)
+        .chain(history.last.keys().copied())
+        .chain(history.path().iter().copied());
+    for edge in edges {
+        if graph.data.edge_weight(edge).is_none() {
+            return Err(StateError::MissingEdge(edge));
+        }
+    }
+
+    Ok(())
+}
+
+/// A position in a `DialogueGraph`, which records the conversation's history
+/// as it moves through the graph.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::{Condition, Not, Visited}, conversation::Conversation, DialogueGraph, Edge};
+/// let mut graph = DialogueGraph::new();
+/// let hub = graph.data.add_node("What do you want to know?".into());
+/// let war = graph.data.add_node("The war took everything from us.".into());
+/// let ask = graph.data.add_edge(hub, war, Edge::new(Not::new(Visited::new(war)).to_dynamic()));
+/// graph.data.add_edge(war, hub, Edge::new(Visited::new(war).to_dynamic()));
+///
+/// let mut conversation = Conversation::new(&graph, hub);
+/// assert_eq!(conversation.choices(), vec![ask]);
+///
+/// conversation.choose(ask).unwrap();
+/// conversation.choose(conversation.choices()[0]).unwrap();
+///
+/// assert_eq!(conversation.current(), hub);
+/// assert_eq!(conversation.history().visit_count(hub), 2);
+/// assert!(conversation.choices().is_empty());
+/// ```
+#[derive(Debug)]
+pub struct Conversation<'a, T>
+where
+    T: Condition,
+{
+    root: &'a DialogueGraph<T>,
+    library: Option<&'a Library<T>>,
+    graph: &'a DialogueGraph<T>,
+    state: ConversationState,
+}
+
+impl<'a, T> Conversation<'a, T>
+where
+    T: Condition,
+{
+    /// Start a conversation at the given node.
+    pub fn new(graph: &'a DialogueGraph<T>, start: NodeIndex) -> Self {
+        Self {
+            root: graph,
+            library: None,
+            graph,
+            state: ConversationState::new(start),
+        }
+    }
+
+    /// Start a conversation at the graph's entry node, if it has one.
+    pub fn start(graph: &'a DialogueGraph<T>) -> Option<Self> {
+        graph.entry().map(|entry| Self::new(graph, entry))
+    }
+
+    /// Resume a saved conversation, checking that the nodes and edges it
+    /// refers to are still in the graph.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::True, conversation::{Conversation, ConversationState}, DialogueGraph, Edge};
+    /// let mut graph = DialogueGraph::new();
+    /// let start = graph.data.add_node("Hello.".into());
+    /// let end = graph.data.add_node("Goodbye.".into());
+    /// let edge = graph.data.add_edge(start, end, Edge::new(True::new()));
+    ///
+    /// let mut conversation = Conversation::new(&graph, start);
+    /// conversation.choose(edge).unwrap();
+    /// let saved = serde_json::to_string(conversation.state()).unwrap();
+    ///
+    /// let state: ConversationState = serde_json::from_str(&saved).unwrap();
+    /// let resumed = Conversation::resume(&graph, state).unwrap();
+    /// assert_eq!(resumed.current(), end);
+    /// ```
+    pub fn resume(
+        graph: &'a DialogueGraph<T>,
+        state: ConversationState,
+    ) -> Result<Self, StateError> {
+        Self::resume_with_library(graph, None, state)
+    }
+
+    /// Resume a saved conversation which may be inside graphs called from the
+    /// library, checking that the graphs, nodes and edges it refers to are
+    /// still there.
+    pub fn resume_with_library(
+        graph: &'a DialogueGraph<T>,
+        library: Option<&'a Library<T>>,
+        mut state: ConversationState,
+    ) -> Result<Self, StateError> {
+        state.validate_with_library(graph, library)?;
+        if let Some(name) = &state.graph {
+            let empty = state.history.empty_like();
+            let _ = state.histories.entry(name.clone()).or_insert(empty);
+        }
+        let mut conversation = Self {
+            root: graph,
+            library,
+            graph,
+            state,
+        };
+        if let Some(current) = conversation.resolve(&conversation.state.graph) {
+            conversation.graph = current;
+        }
+        Ok(conversation)
+    }
+
+    /// Use the given library for the graphs edges call.
+    pub fn with_library(mut self, library: &'a Library<T>) -> Self {
+        self.library = Some(library);
+        self
+    }
+
+    /// Keep the order edges are traversed in, in the history of every graph
+    /// the conversation is in, as coverage checks of paths need. Edges
+    /// traversed before are not in the paths.
+    pub fn with_paths(mut self) -> Self {
+        let histories = self.state.histories.values_mut();
+        for history in std::iter::once(&mut self.state.history).chain(histories) {
+            let _ = history.path.get_or_insert_with(Vec::new);
+        }
+        self
+    }
+
+    /// Use the given seed for the next random choice.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.state.seed = seed;
+        self
+    }
+
+    /// Returns the graph the conversation is currently in.
+    pub fn graph(&self) -> &'a DialogueGraph<T> {
+        self.graph
+    }
+
+    /// Returns the name of the graph the conversation is currently in, or
+    /// `None` for the graph it was started in.
+    pub fn graph_name(&self) -> Option<&str> {
+        self.state.graph.as_deref()
+    }
+
+    /// Returns the callers of the current graph, innermost last.
+    pub fn stack(&self) -> &[Frame] {
+        &self.state.stack
+    }
+
+    /// Returns the graph with the given name in the library, or the graph
+    /// the conversation was started in for `None`.
+    fn resolve(&self, name: &Option<String>) -> Option<&'a DialogueGraph<T>> {
+        match name {
+            None => Some(self.root),
+            Some(name) => self.library.and_then(|library| library.get(name)),
+        }
+    }
+
+    /// Returns the state of the conversation, for saving it.
+    pub fn state(&self) -> &ConversationState {
+        &self.state
+    }
+
+    /// Returns the state of the conversation, ending it.
+    pub fn into_state(self) -> ConversationState {
+        self.state
+    }
+
+    /// Returns the current graph and node.
+    pub fn position(&self) -> Position {
+        Position::new(self.state.graph.as_deref(), self.state.current)
+    }
+
+    /// Returns the index of the current node.
+    pub fn current(&self) -> NodeIndex {
+        self.state.current
+    }
+
+    /// Returns the current node.
+    pub fn node(&self) -> &'a Node {
+        &self.graph.data[self.state.current]
+    }
+
+    /// Returns the node at a position, such as one returned by
+    /// [`advance`](#method.advance), if its graph and node exist.
+    pub fn node_at(&self, position: &Position) -> Option<&'a Node> {
+        self.resolve(&position.graph)
+            .and_then(|graph| graph.data.node_weight(position.node))
+    }
+
+    /// Returns the history of the graph the conversation is currently in.
+    pub fn history(&self) -> &History {
+        match &self.state.graph {
+            None => &self.state.history,
+            // Inserted when the graph is entered.
+            Some(name) => &self.state.histories[name],
+        }
+    }
+
+    /// Returns the history of the graph with the given name in the library,
+    /// or of the graph the conversation was started in for `None`, if the
+    /// conversation has been in it.
+    pub fn history_in(&self, graph: Option<&str>) -> Option<&History> {
+        match graph {
+            None => Some(&self.state.history),
+            Some(name) => self.state.histories.get(name),
+        }
+    }
+
+    /// Returns the history of the graph the conversation is currently in, for
+    /// recording in it.
+    fn history_mut(&mut self) -> &mut History {
+        match &self.state.graph {
+            None => &mut self.state.history,
+            Some(name) => {
+                let empty = self.state.history.empty_like();
+                self.state.histories.entry(name.clone()).or_insert(empty)
+            }
+        }
+    }
+
+    /// Returns the variables conditions are evaluated against.
+    pub fn variables(&self) -> &Variables {
+        &self.state.variables
+    }
+
+    /// Returns the variables conditions are evaluated against, for changing
+    /// them.
+    pub fn variables_mut(&mut self) -> &mut Variables {
+        &mut self.state.variables
+    }
+
+    /// Returns the context conditions are evaluated against.
+    pub fn context(&self) -> Context<'_> {
+        Context::new()
+            .with_variables(&self.state.variables)
+            .with_history(self.history())
+    }
+
+    /// Returns the open edges of the given kind leading out of the current
+    /// node, in the order they were added to the graph.
+    ///
+    /// Edges which have used up their traversals or are cooling down are not
+    /// open.
+    fn open_edges(&self, kind: EdgeKind) -> Vec<EdgeIndex> {
+        let context = self.context();
+        let mut edges: Vec<EdgeIndex> = self
+            .graph
+            .data
+            .edges(self.state.current)
+            .filter(|edge| edge.weight().kind == kind && edge.weight().is_open(edge.id(), &context))
+            .map(|edge| edge.id())
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    /// Returns the open choices leading out of the current node, in the order
+    /// they were added to the graph.
+    ///
+    /// Edges which have used up their traversals or are cooling down are not
+    /// open.
+    pub fn choices(&self) -> Vec<EdgeIndex> {
+        self.open_edges(EdgeKind::Choice)
+    }
+
+    /// Returns the automatic edge the conversation would follow from the
+    /// current node: the first open one added to the graph.
+    pub fn auto_edge(&self) -> Option<EdgeIndex> {
+        self.open_edges(EdgeKind::Auto).into_iter().next()
+    }
+
+    /// Returns whether no open edges of any kind lead out of the current
+    /// node and there is no caller to return to.
+    pub fn is_finished(&self) -> bool {
+        self.state.stack.is_empty() && self.is_exit()
+    }
+
+    /// Returns whether no open edges of any kind lead out of the current
+    /// node, so that a called graph returns from it.
+    fn is_exit(&self) -> bool {
+        self.choices().is_empty() && self.auto_edge().is_none()
+    }
+
+    /// Returns a random open edge leading out of the current node, advancing
+    /// the conversation's seed.
+    pub fn random_choice(&mut self) -> Option<EdgeIndex> {
+        let mut rng = StdRng::seed_from_u64(self.state.seed);
+        let choice = self.choices().into_iter().choose(&mut rng);
+        self.state.seed = rng.next_u64();
+        choice
+    }
+
+    /// Traverse an open edge leading out of the current node, returning the
+    /// index of the node it leads to.
+    ///
+    /// If the edge calls a graph, the conversation moves to that graph's
+    /// entry node instead, and continues from the node the edge leads to once
+    /// the called graph is finished.
+    pub fn choose(&mut self, edge: EdgeIndex) -> Result<NodeIndex, ConversationError> {
+        let graph = self.graph;
+        let (source, target) = graph
+            .data
+            .edge_endpoints(edge)
+            .ok_or(ConversationError::InvalidEdge(edge))?;
+        if source != self.state.current {
+            return Err(ConversationError::InvalidEdge(edge));
+        }
+        let weight = &graph.data[edge];
+        let history = self.history();
+        if !weight.within_limits(
+            history.traversal_count(edge),
+            history.traversals_since(edge),
+        ) {
+            return Err(ConversationError::ExhaustedEdge(edge));
+        }
+        if !weight.condition.evaluate_with(&self.context()) {
+            return Err(ConversationError::ClosedEdge(edge));
+        }
+        let callee = match &weight.call {
+            Some(name) => {
+                let callee = self
+                    .resolve(&weight.call)
+                    .ok_or(ConversationError::InvalidCall(edge))?;
+                let entry = callee.entry().ok_or(ConversationError::InvalidCall(edge))?;
+                Some((name, callee, entry))
+            }
+            None => None,
+        };
+
+        self.history_mut().record_traversal(edge);
+        match callee {
+            Some((name, callee, entry)) => {
+                self.state.stack.push(Frame {
+                    graph: self.state.graph.replace(name.clone()),
+                    node: target,
+                });
+                self.graph = callee;
+                self.enter(entry);
+                Ok(entry)
+            }
+            None => {
+                self.enter(target);
+                Ok(target)
+            }
+        }
+    }
+
+    /// Move to a node in the current graph, recording the visit.
+    fn enter(&mut self, node: NodeIndex) {
+        self.history_mut().record_visit(node);
+        self.state.current = node;
+    }
+
+    /// Return to the caller of the current graph if the current node is an
+    /// exit, returning whether it did.
+    ///
+    /// The caller is missing if the library was replaced after the
+    /// conversation was resumed, in which case the conversation is left where
+    /// it was.
+    pub(crate) fn return_to_caller(&mut self) -> Result<bool, ConversationError> {
+        if !self.is_exit() {
+            return Ok(false);
+        }
+        let frame = match self.state.stack.pop() {
+            Some(frame) => frame,
+            None => return Ok(false),
+        };
+        match self.resolve(&frame.graph) {
+            Some(graph) => self.graph = graph,
+            None => {
+                let name = frame.graph.clone().unwrap_or_default();
+                self.state.stack.push(frame);
+                return Err(ConversationError::MissingCaller(name));
+            }
+        }
+        self.state.graph = frame.graph;
+        self.enter(frame.node);
+        Ok(true)
+    }
+
+    /// Follow automatic edges and return from finished called graphs until
+    /// neither is possible, returning the positions moved to, in order.
+    ///
+    /// If a step fails or more than `AUTO_ADVANCE_LIMIT` steps would be
+    /// taken, the conversation is left where it was and the error is
+    /// returned.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::True, conversation::{Conversation, Position}, DialogueGraph, Edge};
+    /// let mut graph = DialogueGraph::new();
+    /// let greeting = graph.data.add_node("Hello.".into());
+    /// let question = graph.data.add_node("What brings you here?".into());
+    /// let answer = graph.data.add_node("Just passing through.".into());
+    /// graph.data.add_edge(greeting, question, Edge::new(True::new()).auto());
+    /// let reply = graph.data.add_edge(question, answer, Edge::new(True::new()));
+    ///
+    /// let mut conversation = Conversation::new(&graph, greeting);
+    /// assert!(conversation.choices().is_empty());
+    ///
+    /// assert_eq!(conversation.advance().unwrap(), vec![Position::new(None, question)]);
+    /// assert_eq!(conversation.current(), question);
+    /// assert_eq!(conversation.choices(), vec![reply]);
+    /// ```
+    pub fn advance(&mut self) -> Result<Vec<Position>, ConversationError> {
+        let saved = self.state.clone();
+        let graph = self.graph;
+        let mut positions = Vec::new();
+        let mut steps = 0;
+        let error = loop {
+            let node = self.state.current;
+            if let Some(edge) = self.auto_edge() {
+                if steps == AUTO_ADVANCE_LIMIT {
+                    break ConversationError::AutoAdvanceCycle(node);
+                }
+                if let Err(error) = self.choose(edge) {
+                    break error;
+                }
+            } else {
+                match self.return_to_caller() {
+                    Ok(true) => {}
+                    Ok(false) => return Ok(positions),
+                    Err(error) => break error,
+                }
+            }
+            positions.push(self.position());
+            steps += 1;
+        };
+        self.graph = graph;
+        self.state = saved;
+        Err(error)
+    }
+
+    /// Traverse an open edge leading out of the current node, then follow
+    /// any automatic edges from the node it leads to, returning the index of
+    /// the node the conversation stops at.
+    pub fn step(&mut self, edge: EdgeIndex) -> Result<NodeIndex, ConversationError> {
+        self.choose(edge)?;
+        self.advance()?;
+        Ok(self.state.current)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        condition::{dynamic::DynCondition, EdgeTaken, True, VisitCount},
+        Edge,
+    };
+
+    #[test]
+    fn test_choose_records_history() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let there = graph.data.add_edge(a, b, Edge::new(True::new()));
+        let back = graph.data.add_edge(b, a, Edge::new(True::new()));
+
+        let mut conversation = Conversation::new(&graph, a).with_paths();
+        for _ in 0..2 {
+            conversation.choose(there).unwrap();
+            conversation.choose(back).unwrap();
+        }
+
+        let history = conversation.history();
+        assert_eq!(history.visits().collect::<Vec<_>>(), vec![(a, 3), (b, 2)]);
+        assert_eq!(history.traversal_count(there), 2);
+        assert_eq!(history.path(), &[there, back, there, back]);
+    }
+
+    #[test]
+    fn test_history_without_path() {
+        let (a, b) = (EdgeIndex::new(0), EdgeIndex::new(1));
+        let mut history = History::new();
+        for edge in &[a, b, b, a, b] {
+            history.record_traversal(*edge);
+        }
+
+        assert!(history.path().is_empty());
+        assert_eq!(history.traversals_since(a), Some(1));
+        assert_eq!(history.traversals_since(b), Some(0));
+        assert_eq!(history.traversals_since(EdgeIndex::new(2)), None);
+        assert_eq!(history.traversal_count(b), 3);
+    }
+
+    #[test]
+    fn test_choose_rejects_other_edges() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let elsewhere = graph.data.add_edge(b, a, Edge::new(DynCondition::True));
+        let closed = graph.data.add_edge(a, b, Edge::new(DynCondition::False));
+
+        let mut conversation = Conversation::new(&graph, a);
+
+        assert_eq!(
+            conversation.choose(elsewhere),
+            Err(ConversationError::InvalidEdge(elsewhere))
+        );
+        assert_eq!(
+            conversation.choose(closed),
+            Err(ConversationError::ClosedEdge(closed))
+        );
+        assert_eq!(conversation.current(), a);
+    }
+
+    #[test]
+    fn test_history_conditions() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let loop_edge = graph.data.add_edge(a, a, Edge::new(DynCondition::True));
+        let twice = graph
+            .data
+            .add_edge(a, b, Edge::new(VisitCount::new(a, 3).to_dynamic()));
+        let after = graph
+            .data
+            .add_edge(a, b, Edge::new(EdgeTaken::new(twice).to_dynamic()));
+
+        let mut conversation = Conversation::new(&graph, a);
+        conversation.choose(loop_edge).unwrap();
+        assert_eq!(conversation.choices(), vec![loop_edge]);
+
+        conversation.choose(loop_edge).unwrap();
+        assert_eq!(conversation.choices(), vec![loop_edge, twice]);
+
+        conversation.choose(twice).unwrap();
+        let mut again = Conversation::new(&graph, a);
+        again.state.history = conversation.history().clone();
+        assert_eq!(again.choices(), vec![loop_edge, twice, after]);
+    }
+
+    #[test]
+    fn test_usage_limits() {
+        let mut graph = DialogueGraph::new();
+        let hub = graph.data.add_node("Hub".into());
+        let once = graph.data.add_edge(hub, hub, Edge::new(True::new()).once());
+        let twice = graph
+            .data
+            .add_edge(hub, hub, Edge::new(True::new()).with_max_uses(2));
+        let bark = graph
+            .data
+            .add_edge(hub, hub, Edge::new(True::new()).with_cooldown(2));
+
+        let mut conversation = Conversation::new(&graph, hub);
+        conversation.choose(once).unwrap();
+        assert_eq!(conversation.choices(), vec![twice, bark]);
+        assert_eq!(
+            conversation.choose(once),
+            Err(ConversationError::ExhaustedEdge(once))
+        );
+
+        conversation.choose(bark).unwrap();
+        assert_eq!(conversation.choices(), vec![twice]);
+        conversation.choose(twice).unwrap();
+        assert_eq!(conversation.choices(), vec![twice]);
+        conversation.choose(twice).unwrap();
+        assert_eq!(conversation.choices(), vec![bark]);
+    }
+
+    #[test]
+    fn test_advance_follows_first_open_auto_edge() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let c = graph.data.add_node("C".into());
+        let d = graph.data.add_node("D".into());
+        let _ = graph
+            .data
+            .add_edge(a, b, Edge::new(DynCondition::False).auto());
+        let open = graph
+            .data
+            .add_edge(a, c, Edge::new(DynCondition::True).auto());
+        let _ = graph
+            .data
+            .add_edge(a, b, Edge::new(DynCondition::True).auto());
+        let choice = graph.data.add_edge(c, d, Edge::new(DynCondition::True));
+        let jump = graph
+            .data
+            .add_edge(d, a, Edge::new(DynCondition::True).auto().once());
+
+        let mut conversation = Conversation::new(&graph, a).with_paths();
+        assert_eq!(conversation.advance(), Ok(vec![Position::new(None, c)]));
+        assert_eq!(conversation.current(), c);
+
+        assert_eq!(conversation.step(choice), Ok(c));
+        assert_eq!(conversation.history().path(), &[open, choice, jump, open]);
+        assert!(!conversation.is_finished());
+    }
+
+    #[test]
+    fn test_advance_cycle() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let _ = graph.data.add_edge(a, b, Edge::new(True::new()).auto());
+        let _ = graph.data.add_edge(b, a, Edge::new(True::new()).auto());
+
+        let mut conversation = Conversation::new(&graph, a).with_paths();
+
+        assert_eq!(
+            conversation.advance(),
+            Err(ConversationError::AutoAdvanceCycle(a))
+        );
+        assert_eq!(conversation.current(), a);
+        assert!(conversation.history().path().is_empty());
+    }
+
+    #[test]
+    fn test_call_and_return() {
+        let mut goodbye = DialogueGraph::new();
+        let farewell = goodbye.data.add_node("Farewell.".into());
+
+        let mut shop = DialogueGraph::new();
+        let welcome = shop.data.add_node("Welcome.".into());
+        let thanks = shop.data.add_node("Thanks.".into());
+        let buy = shop
+            .data
+            .add_edge(welcome, thanks, Edge::new(DynCondition::True).once());
+        let _ = shop.data.add_edge(
+            thanks,
+            welcome,
+            Edge::new(DynCondition::True)
+                .auto()
+                .once()
+                .with_call("goodbye"),
+        );
+
+        let mut library = Library::new();
+        let _ = library.insert("goodbye", goodbye);
+        let _ = library.insert("shop", shop);
+
+        let mut village = DialogueGraph::new();
+        let a = village.data.add_node("A".into());
+        let b = village.data.add_node("B".into());
+        let enter = village
+            .data
+            .add_edge(a, b, Edge::new(DynCondition::True).with_call("shop"));
+
+        let mut conversation = Conversation::new(&village, a)
+            .with_library(&library)
+            .with_paths();
+        assert_eq!(conversation.choose(enter), Ok(welcome));
+        assert_eq!(conversation.graph_name(), Some("shop"));
+        assert_eq!(conversation.history().path(), &[]);
+
+        assert_eq!(conversation.choose(buy), Ok(thanks));
+        assert_eq!(
+            conversation.advance(),
+            Ok(vec![
+                Position::new(Some("goodbye"), farewell),
+                Position::new(Some("shop"), welcome),
+                Position::new(None, b),
+            ])
+        );
+        assert_eq!(conversation.history().path(), &[enter]);
+        assert_eq!(
+            conversation.history_in(Some("shop")).unwrap().path(),
+            &[buy, EdgeIndex::new(1)]
+        );
+        assert!(conversation.stack().is_empty());
+        assert!(conversation.is_finished());
+    }
+
+    #[test]
+    fn test_history_kept_across_calls() {
+        let mut shop = DialogueGraph::new();
+        let welcome = shop.data.add_node("Welcome.".into());
+        let first = shop.data.add_node("First time here?".into());
+        let _ = shop
+            .data
+            .add_edge(welcome, first, Edge::new(DynCondition::True).auto().once());
+        let mut library = Library::new();
+        let _ = library.insert("shop", shop);
+
+        let mut village = DialogueGraph::new();
+        let square = village.data.add_node("Square".into());
+        let enter = village.data.add_edge(
+            square,
+            square,
+            Edge::new(DynCondition::True).with_call("shop"),
+        );
+
+        let mut conversation = Conversation::new(&village, square).with_library(&library);
+        conversation.choose(enter).unwrap();
+        assert_eq!(
+            conversation.advance(),
+            Ok(vec![
+                Position::new(Some("shop"), first),
+                Position::new(None, square),
+            ])
+        );
+
+        conversation.choose(enter).unwrap();
+        assert_eq!(
+            conversation.advance(),
+            Ok(vec![Position::new(None, square)])
+        );
+        let shop_history = conversation.history_in(Some("shop")).unwrap();
+        assert_eq!(shop_history.visit_count(welcome), 2);
+        assert_eq!(shop_history.visit_count(first), 1);
+
+        let state = conversation.into_state();
+        let resumed = Conversation::resume_with_library(&village, Some(&library), state).unwrap();
+        assert_eq!(resumed.history().visit_count(square), 3);
+    }
+
+    #[test]
+    fn test_missing_caller() {
+        let cellar = || {
+            let mut cellar = DialogueGraph::new();
+            let _ = cellar.data.add_node("Dark in here.".into());
+            cellar
+        };
+        let mut shop = DialogueGraph::new();
+        let counter = shop.data.add_node("Counter".into());
+        let down = shop.data.add_edge(
+            counter,
+            counter,
+            Edge::new(DynCondition::True).with_call("cellar"),
+        );
+        let mut library = Library::new();
+        let _ = library.insert("shop", shop);
+        let _ = library.insert("cellar", cellar());
+        let mut other = Library::new();
+        let _ = other.insert("cellar", cellar());
+
+        let mut village = DialogueGraph::new();
+        let square = village.data.add_node("Square".into());
+        let enter = village.data.add_edge(
+            square,
+            square,
+            Edge::new(DynCondition::True).with_call("shop"),
+        );
+        let mut conversation = Conversation::new(&village, square).with_library(&library);
+        conversation.choose(enter).unwrap();
+        conversation.choose(down).unwrap();
+
+        let mut conversation = conversation.with_library(&other);
+        let state = conversation.state().clone();
+        assert_eq!(
+            conversation.advance(),
+            Err(ConversationError::MissingCaller("shop".to_owned()))
+        );
+        assert_eq!(conversation.state(), &state);
+    }
+
+    #[test]
+    fn test_invalid_call() {
+        let library = Library::new();
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let _ = graph.data.add_edge(a, a, Edge::new(True::new()).auto());
+        let call = graph
+            .data
+            .add_edge(a, b, Edge::new(True::new()).with_call("missing"));
+
+        let mut conversation = Conversation::new(&graph, a).with_library(&library);
+
+        assert_eq!(
+            conversation.choose(call),
+            Err(ConversationError::InvalidCall(call))
+        );
+        assert_eq!(conversation.current(), a);
+    }
+
+    #[test]
+    fn test_resume_inside_call() {
+        let mut shop = DialogueGraph::new();
+        let welcome = shop.data.add_node("Welcome.".into());
+        let mut library = Library::new();
+        let _ = library.insert("shop", shop);
+
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let call = graph
+            .data
+            .add_edge(a, b, Edge::new(True::new()).with_call("shop"));
+
+        let mut conversation = Conversation::new(&graph, a).with_library(&library);
+        conversation.choose(call).unwrap();
+        let state = conversation.into_state();
+
+        assert_eq!(
+            Conversation::resume(&graph, state.clone()).unwrap_err(),
+            StateError::MissingGraph("shop".to_owned())
+        );
+        let mut resumed = Conversation::resume_with_library(&graph, Some(&library), state).unwrap();
+        assert_eq!(resumed.current(), welcome);
+        assert_eq!(resumed.advance(), Ok(vec![Position::new(None, b)]));
+        assert_eq!(resumed.current(), b);
+    }
+
+    #[test]
+    fn test_resume_checks_graph() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let edge = graph.data.add_edge(a, b, Edge::new(True::new()));
+
+        let mut conversation = Conversation::new(&graph, a);
+        conversation.choose(edge).unwrap();
+        let state = conversation.into_state();
+
+        let mut patched = DialogueGraph::<True>::new();
+        let _ = patched.data.add_node("A".into());
+        let _ = patched.data.add_node("B".into());
+        assert_eq!(
+            Conversation::resume(&patched, state.clone()).unwrap_err(),
+            StateError::MissingEdge(edge)
+        );
+
+        let _ = patched.data.remove_node(b);
+        assert_eq!(
+            Conversation::resume(&patched, state).unwrap_err(),
+            StateError::MissingNode(b)
+        );
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let _ = graph.data.add_edge(a, b, Edge::new(True::new()));
+        let _ = graph.data.add_edge(a, a, Edge::new(True::new()));
+
+        let mut conversation = Conversation::new(&graph, a).with_seed(7);
+        conversation.variables_mut().set("gold", 3);
+        let choice = conversation.random_choice().unwrap();
+        conversation.choose(choice).unwrap();
+
+        let saved = serde_json::to_string(conversation.state()).unwrap();
+        let state: ConversationState = serde_json::from_str(&saved).unwrap();
+
+        assert_eq!(&state, conversation.state());
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{condition::Condition, conversation::History, DialogueGraph};
+use petgraph::{
+    graph::{EdgeIndex, NodeIndex},
+    visit::EdgeRef,
+};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// How a path through a graph ends.
+#[derive(Copy, Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PathEnd {
+    /// The path ends at a node no edges lead out of.
+    Exit,
+    /// The path's last edge leads back to a node already on the path.
+    Cycle,
+    /// The path reached the most edges allowed before it ended.
+    Limit,
+}
+
+/// A path through a `DialogueGraph`.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct Path {
+    /// The nodes on the path in order, from the node it starts at. The last
+    /// node of a path ending in a cycle is also earlier on the path.
+    pub nodes: Vec<NodeIndex>,
+    /// The edges on the path in order.
+    pub edges: Vec<EdgeIndex>,
+    /// How the path ends.
+    pub end: PathEnd,
+}
+
+/// Iterator over the paths through a `DialogueGraph`, created by
+/// [`DialogueGraph::paths`](../struct.DialogueGraph.html#method.paths).
+#[allow(missing_debug_implementations)]
+pub struct Paths<'a, T>
+where
+    T: Condition,
+{
+    graph: &'a DialogueGraph<T>,
+    start: Option<NodeIndex>,
+    max_length: usize,
+    /// The edges left to follow out of each node on the current path.
+    stack: Vec<Vec<EdgeIndex>>,
+    nodes: Vec<NodeIndex>,
+    edges: Vec<EdgeIndex>,
+}
+
+impl<T> Paths<'_, T>
+where
+    T: Condition,
+{
+    /// Returns the edges leading out of a node in order of index, last first
+    /// so that they are followed in order.
+    fn outgoing(&self, node: NodeIndex) -> Vec<EdgeIndex> {
+        let mut edges: Vec<EdgeIndex> = self.graph.data.edges(node).map(|edge| edge.id()).collect();
+        edges.sort_by(|a, b| b.cmp(a));
+        edges
+    }
+
+    /// Returns the current path, ended with the given edge and node.
+    fn path(&self, edge: EdgeIndex, node: NodeIndex, end: PathEnd) -> Path {
+        let mut nodes = self.nodes.clone();
+        let mut edges = self.edges.clone();
+        nodes.push(node);
+        edges.push(edge);
+        Path { nodes, edges, end }
+    }
+}
+
+impl<T> Iterator for Paths<'_, T>
+where
+    T: Condition,
+{
+    type Item = Path;
+
+    fn next(&mut self) -> Option<Path> {
+        if let Some(start) = self.start.take() {
+            let outgoing = self.outgoing(start);
+            if outgoing.is_empty() {
+                return Some(Path {
+                    nodes: vec![start],
+                    edges: Vec::new(),
+                    end: PathEnd::Exit,
+                });
+            }
+            self.nodes.push(start);
+            self.stack.push(outgoing);
+        }
+
+        loop {
+            let edge = match self.stack.last_mut()?.pop() {
+                Some(edge) => edge,
+                None => {
+                    let _ = self.stack.pop();
+                    let _ = self.nodes.pop();
+                    let _ = self.edges.pop();
+                    continue;
+                }
+            };
+            let (_, target) = self.graph.data.edge_endpoints(edge)?;
+            if self.nodes.contains(&target) {
+                return Some(self.path(edge, target, PathEnd::Cycle));
+            }
+            let outgoing = self.outgoing(target);
+            if outgoing.is_empty() {
+                return Some(self.path(edge, target, PathEnd::Exit));
+            }
+            if self.edges.len() + 1 >= self.max_length {
+                return Some(self.path(edge, target, PathEnd::Limit));
+            }
+            self.nodes.push(target);
+            self.edges.push(edge);
+            self.stack.push(outgoing);
+        }
+    }
+}
+
+impl<T> DialogueGraph<T>
+where
+    T: Condition,
+{
+    /// Returns an iterator over every simple path from a node to the nodes
+    /// no edges lead out of, following edges in order of index.
+    ///
+    /// Edges are followed regardless of their conditions. A path which would
+    /// visit a node twice ends at the edge closing the cycle, and a path
+    /// which reaches `max_length` edges ends there, so every edge reachable
+    /// within `max_length` edges of the start is on at least one path.
+    ///
+    /// The number of paths can grow exponentially with the size of the
+    /// graph, so take only as many as needed from the iterator.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::True, coverage::PathEnd, DialogueGraph, Edge};
+    /// let mut graph = DialogueGraph::new();
+    /// let hub = graph.data.add_node("What do you want?".into());
+    /// let bye = graph.data.add_node("Goodbye.".into());
+    /// graph.data.add_edge(hub, hub, Edge::new(True::new()));
+    /// graph.data.add_edge(hub, bye, Edge::new(True::new()));
+    ///
+    /// let ends: Vec<PathEnd> = graph.paths(hub, 10).map(|path| path.end).collect();
+    /// assert_eq!(ends, vec![PathEnd::Cycle, PathEnd::Exit]);
+    /// ```
+    pub fn paths(&self, start: NodeIndex, max_length: usize) -> Paths<'_, T> {
+        Paths {
+            graph: self,
+            start: Some(start),
+            max_length: max_length.max(1),
+            stack: Vec::new(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+}
+
+/// The nodes and edges of a graph which no recorded conversation reached.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// The number of nodes in the graph.
+    pub nodes: usize,
+    /// The number of edges in the graph.
+    pub edges: usize,
+    /// The nodes never visited.
+    pub unvisited_nodes: Vec<NodeIndex>,
+    /// The edges never traversed.
+    pub untraversed_edges: Vec<EdgeIndex>,
+}
+
+impl CoverageReport {
+    /// Returns whether every node was visited and every edge traversed.
+    pub fn is_complete(&self) -> bool {
+        self.unvisited_nodes.is_empty() && self.untraversed_edges.is_empty()
+    }
+}
+
+/// Records the histories of conversations in a graph, such as those played
+/// by tests, to report what they did not reach.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::True, conversation::Conversation, coverage::Coverage, DialogueGraph, Edge};
+/// let mut graph = DialogueGraph::new();
+/// let hello = graph.data.add_node("Hello.".into());
+/// let yes = graph.data.add_node("Yes.".into());
+/// let no = graph.data.add_node("No.".into());
+/// let agree = graph.data.add_edge(hello, yes, Edge::new(True::new()));
+/// let refuse = graph.data.add_edge(hello, no, Edge::new(True::new()));
+///
+/// let mut conversation = Conversation::new(&graph, hello).with_paths();
+/// conversation.choose(agree).unwrap();
+///
+/// let mut coverage = Coverage::new();
+/// coverage.record(conversation.history());
+///
+/// let report = coverage.report(&graph);
+/// assert_eq!(report.unvisited_nodes, vec![no]);
+/// assert_eq!(report.untraversed_edges, vec![refuse]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Coverage {
+    visited: BTreeSet<NodeIndex>,
+    traversed: BTreeSet<EdgeIndex>,
+    traces: Vec<Vec<EdgeIndex>>,
+}
+
+impl Coverage {
+    /// Create a recorder with no conversations recorded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the history of a conversation.
+    ///
+    /// A conversation which called graphs in a library has a history for
+    /// each of them, returned by [`Conversation::history_in`]. Only the
+    /// history of the graph the coverage is reported for should be recorded.
+    ///
+    /// [`Conversation::history_in`]: ../conversation/struct.Conversation.html#method.history_in
+    pub fn record(&mut self, history: &History) {
+        self.visited.extend(history.visits().map(|(node, _)| node));
+        self.traversed
+            .extend(history.traversals().map(|(edge, _)| edge));
+        self.traces.push(history.path().to_vec());
+    }
+
+    /// Returns whether a recorded conversation followed the path from its
+    /// start, taking the conversations to have started where the path does.
+    ///
+    /// Only histories which keep their paths, such as those of conversations
+    /// created [`with_paths`], can follow a path with edges.
+    ///
+    /// [`with_paths`]: ../conversation/struct.Conversation.html#method.with_paths
+    pub fn covers(&self, path: &Path) -> bool {
+        self.traces
+            .iter()
+            .any(|trace| trace.starts_with(&path.edges))
+    }
+
+    /// Returns the nodes and edges of a graph which no recorded conversation
+    /// reached.
+    pub fn report<T>(&self, graph: &DialogueGraph<T>) -> CoverageReport
+    where
+        T: Condition,
+    {
+        CoverageReport {
+            nodes: graph.data.node_count(),
+            edges: graph.data.edge_count(),
+            unvisited_nodes: graph
+                .data
+                .node_indices()
+                .filter(|node| !self.visited.contains(node))
+                .collect(),
+            untraversed_edges: graph
+                .data
+                .edge_indices()
+                .filter(|edge| !self.traversed.contains(edge))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{condition::True, conversation::Conversation, Edge};
+
+    #[test]
+    fn test_paths() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let c = graph.data.add_node("C".into());
+        let d = graph.data.add_node("D".into());
+        let ab = graph.data.add_edge(a, b, Edge::new(True::new()));
+        let ac = graph.data.add_edge(a, c, Edge::new(True::new()));
+        let bc = graph.data.add_edge(b, c, Edge::new(True::new()));
+        let ca = graph.data.add_edge(c, a, Edge::new(True::new()));
+        let cd = graph.data.add_edge(c, d, Edge::new(True::new()));
+
+        let paths: Vec<Path> = graph.paths(a, 10).collect();
+        let edges: Vec<(&[EdgeIndex], PathEnd)> = paths
+            .iter()
+            .map(|path| (path.edges.as_slice(), path.end))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                (&[ab, bc, ca][..], PathEnd::Cycle),
+                (&[ab, bc, cd][..], PathEnd::Exit),
+                (&[ac, ca][..], PathEnd::Cycle),
+                (&[ac, cd][..], PathEnd::Exit),
+            ]
+        );
+        assert_eq!(paths[1].nodes, vec![a, b, c, d]);
+
+        let ends: Vec<PathEnd> = graph.paths(a, 2).map(|path| path.end).collect();
+        assert_eq!(ends, vec![PathEnd::Limit, PathEnd::Cycle, PathEnd::Exit]);
+
+        let exit = graph.paths(d, 10).collect::<Vec<Path>>();
+        assert_eq!(exit.len(), 1);
+        assert_eq!(exit[0].nodes, vec![d]);
+    }
+
+    #[test]
+    fn test_coverage() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let c = graph.data.add_node("C".into());
+        let ab = graph.data.add_edge(a, b, Edge::new(True::new()));
+        let ac = graph.data.add_edge(a, c, Edge::new(True::new()));
+        let paths: Vec<Path> = graph.paths(a, 10).collect();
+
+        let mut coverage = Coverage::new();
+        assert!(!coverage.report(&graph).is_complete());
+
+        let mut conversation = Conversation::new(&graph, a).with_paths();
+        let _ = conversation.choose(ab).unwrap();
+        coverage.record(conversation.history());
+        assert!(coverage.covers(&paths[0]));
+        assert!(!coverage.covers(&paths[1]));
+
+        let mut conversation = Conversation::new(&graph, a).with_paths();
+        let _ = conversation.choose(ac).unwrap();
+        coverage.record(conversation.history());
+        let report = coverage.report(&graph);
+        assert!(report.is_complete());
+        assert_eq!((report.nodes, report.edges), (3, 2));
+        assert!(paths.iter().all(|path| coverage.covers(path)));
+    }
+}
@@ -0,0 +1,476 @@
+use crate::{condition::Condition, DialogueGraph, Edge, Node};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use serde::Serialize;
+use std::fmt;
+
+/// A difference between two `DialogueGraph` objects.
+///
+/// Nodes and edges of the old graph are referred to by their index in the
+/// old graph, and those of the new graph by their index in the new graph.
+#[derive(Copy, Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    /// The node is only in the new graph.
+    AddedNode {
+        /// The index of the node in the new graph.
+        node: NodeIndex,
+    },
+    /// The node is only in the old graph.
+    RemovedNode {
+        /// The index of the node in the old graph.
+        node: NodeIndex,
+    },
+    /// The node's id, speaker, text, key or link changed.
+    ModifiedNode {
+        /// The index of the node in the old graph.
+        old: NodeIndex,
+        /// The index of the node in the new graph.
+        new: NodeIndex,
+    },
+    /// The edge is only in the new graph.
+    AddedEdge {
+        /// The index of the edge in the new graph.
+        edge: EdgeIndex,
+    },
+    /// The edge is only in the old graph.
+    RemovedEdge {
+        /// The index of the edge in the old graph.
+        edge: EdgeIndex,
+    },
+    /// The edge's condition changed.
+    ModifiedCondition {
+        /// The index of the edge in the old graph.
+        old: EdgeIndex,
+        /// The index of the edge in the new graph.
+        new: EdgeIndex,
+    },
+    /// The edge's kind, usage limits, call or text changed.
+    ModifiedEdge {
+        /// The index of the edge in the old graph.
+        old: EdgeIndex,
+        /// The index of the edge in the new graph.
+        new: EdgeIndex,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::AddedNode { node } => write!(f, "added node {}", node.index()),
+            Change::RemovedNode { node } => write!(f, "removed node {}", node.index()),
+            Change::ModifiedNode { old, new } => {
+                write!(f, "modified node {} (now {})", old.index(), new.index())
+            }
+            Change::AddedEdge { edge } => write!(f, "added edge {}", edge.index()),
+            Change::RemovedEdge { edge } => write!(f, "removed edge {}", edge.index()),
+            Change::ModifiedCondition { old, new } => write!(
+                f,
+                "modified the condition of edge {} (now {})",
+                old.index(),
+                new.index()
+            ),
+            Change::ModifiedEdge { old, new } => {
+                write!(f, "modified edge {} (now {})", old.index(), new.index())
+            }
+        }
+    }
+}
+
+/// The differences between two `DialogueGraph` objects, and how their nodes
+/// and edges correspond.
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+pub struct Diff {
+    /// The differences, with those to nodes first, each in order of index.
+    pub changes: Vec<Change>,
+    /// The nodes in both graphs, as pairs of their old and new indices.
+    pub nodes: Vec<(NodeIndex, NodeIndex)>,
+    /// The edges in both graphs, as pairs of their old and new indices.
+    pub edges: Vec<(EdgeIndex, EdgeIndex)>,
+}
+
+impl Diff {
+    /// Returns whether the graphs are the same.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl<T> DialogueGraph<T>
+where
+    T: Condition + PartialEq,
+{
+    /// Returns whether both graphs have the same nodes and the same edges
+    /// between them, regardless of the order they were added in.
+    ///
+    /// Nodes are compared by id, text and link, and edges by those of the
+    /// nodes they connect and by their condition and options. Unlike `==`,
+    /// the graphs' versions and the indices of their nodes and edges do not
+    /// matter, except within conditions, which are compared as they are.
+    ///
+    /// This is not a full isomorphism check: nodes with the same id, text and
+    /// link cannot be told apart, so graphs which connect such nodes
+    /// differently, such as two `"..."` nodes each leading to a different
+    /// one of two others, can compare as equivalent. Give nodes ids to tell
+    /// them apart.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::True, DialogueGraph, Edge};
+    /// let mut graph = DialogueGraph::new();
+    /// let hello = graph.data.add_node("Hello.".into());
+    /// let bye = graph.data.add_node("Goodbye.".into());
+    /// graph.data.add_edge(hello, bye, Edge::new(True::new()));
+    ///
+    /// let mut reordered = DialogueGraph::new();
+    /// let bye = reordered.data.add_node("Goodbye.".into());
+    /// let hello = reordered.data.add_node("Hello.".into());
+    /// reordered.data.add_edge(hello, bye, Edge::new(True::new()));
+    ///
+    /// assert!(graph != reordered);
+    /// assert!(graph.equivalent(&reordered));
+    /// ```
+    pub fn equivalent(&self, other: &DialogueGraph<T>) -> bool {
+        same_items(&nodes(self), &nodes(other)) && same_items(&edges(self), &edges(other))
+    }
+}
+
+impl<T> DialogueGraph<T>
+where
+    T: Condition + Clone + PartialEq,
+{
+    /// Compare this graph with a newer version of it.
+    ///
+    /// Nodes are matched by id, and nodes left over are matched by text and
+    /// link. Edges are matched by the nodes they connect, preferring edges
+    /// which are the same, so the order nodes and edges were added in does not
+    /// matter. Conditions are compared once the nodes and edges they refer to
+    /// are renumbered to the new graph's.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::True, diff::Change, DialogueGraph, Edge, Node};
+    /// let mut old = DialogueGraph::new();
+    /// let hello = old.data.add_node(Node::new("Hello.").with_id("hello"));
+    /// let bye = old.data.add_node("Goodbye.".into());
+    /// old.data.add_edge(hello, bye, Edge::new(True::new()));
+    ///
+    /// let mut new = DialogueGraph::new();
+    /// let new_bye = new.data.add_node("Goodbye.".into());
+    /// let new_hello = new.data.add_node(Node::new("Hi.").with_id("hello"));
+    /// let edge = new.data.add_edge(new_hello, new_bye, Edge::new(True::new()).once());
+    ///
+    /// let diff = old.diff(&new);
+    /// assert_eq!(diff.changes, vec![
+    ///     Change::ModifiedNode { old: hello, new: new_hello },
+    ///     Change::ModifiedEdge { old: 0.into(), new: edge },
+    /// ]);
+    /// ```
+    pub fn diff(&self, other: &DialogueGraph<T>) -> Diff {
+        let mut diff = Diff::default();
+        let mut old_nodes: Vec<Option<NodeIndex>> = vec![None; self.data.node_count()];
+        let mut new_nodes: Vec<Option<NodeIndex>> = vec![None; other.data.node_count()];
+
+        for old in self.data.node_indices() {
+            if let Some(id) = &self.data[old].id {
+                let new = other.data.node_indices().find(|new| {
+                    new_nodes[new.index()].is_none() && other.data[*new].id.as_ref() == Some(id)
+                });
+                if let Some(new) = new {
+                    old_nodes[old.index()] = Some(new);
+                    new_nodes[new.index()] = Some(old);
+                }
+            }
+        }
+        for old in self.data.node_indices() {
+            if old_nodes[old.index()].is_some() {
+                continue;
+            }
+            let weight = &self.data[old];
+            let new = other.data.node_indices().find(|new| {
+                new_nodes[new.index()].is_none()
+                    && other.data[*new].text == weight.text
+                    && other.data[*new].link == weight.link
+            });
+            if let Some(new) = new {
+                old_nodes[old.index()] = Some(new);
+                new_nodes[new.index()] = Some(old);
+            }
+        }
+
+        for old in self.data.node_indices() {
+            match old_nodes[old.index()] {
+                Some(new) => {
+                    diff.nodes.push((old, new));
+                    if self.data[old] != other.data[new] {
+                        diff.changes.push(Change::ModifiedNode { old, new });
+                    }
+                }
+                None => diff.changes.push(Change::RemovedNode { node: old }),
+            }
+        }
+        for new in other.data.node_indices() {
+            if new_nodes[new.index()].is_none() {
+                diff.changes.push(Change::AddedNode { node: new });
+            }
+        }
+
+        let mut old_edges: Vec<Option<EdgeIndex>> = vec![None; self.data.edge_count()];
+        let mut new_edges: Vec<Option<EdgeIndex>> = vec![None; other.data.edge_count()];
+        // Match edges which are the same before pairing up the rest, so that
+        // reordered parallel edges are not reported as modified.
+        for same in &[true, false] {
+            for old in self.data.edge_indices() {
+                if old_edges[old.index()].is_some() {
+                    continue;
+                }
+                let (source, target) = match self.data.edge_endpoints(old) {
+                    Some((source, target)) => {
+                        (old_nodes[source.index()], old_nodes[target.index()])
+                    }
+                    None => continue,
+                };
+                let new = other.data.edge_indices().find(|new| {
+                    new_edges[new.index()].is_none()
+                        && other
+                            .data
+                            .edge_endpoints(*new)
+                            .map(|(s, t)| (Some(s), Some(t)))
+                            == Some((source, target))
+                        && (!same || {
+                            let (before, after) = (&self.data[old], &other.data[*new]);
+                            before.same_options(after)
+                                && same_condition(
+                                    &before.condition,
+                                    &after.condition,
+                                    &old_nodes,
+                                    &old_edges,
+                                )
+                        })
+                });
+                if let Some(new) = new {
+                    old_edges[old.index()] = Some(new);
+                    new_edges[new.index()] = Some(old);
+                }
+            }
+        }
+
+        for old in self.data.edge_indices() {
+            match old_edges[old.index()] {
+                Some(new) => {
+                    diff.edges.push((old, new));
+                    let (before, after) = (&self.data[old], &other.data[new]);
+                    if !same_condition(&before.condition, &after.condition, &old_nodes, &old_edges)
+                    {
+                        diff.changes.push(Change::ModifiedCondition { old, new });
+                    }
+                    if !before.same_options(after) {
+                        diff.changes.push(Change::ModifiedEdge { old, new });
+                    }
+                }
+                None => diff.changes.push(Change::RemovedEdge { edge: old }),
+            }
+        }
+        for new in other.data.edge_indices() {
+            if new_edges[new.index()].is_none() {
+                diff.changes.push(Change::AddedEdge { edge: new });
+            }
+        }
+
+        diff
+    }
+}
+
+/// Returns whether an old graph's condition is the same as a new graph's once
+/// the nodes and edges it refers to are renumbered to the new graph's.
+fn same_condition<T>(
+    old: &T,
+    new: &T,
+    nodes: &[Option<NodeIndex>],
+    edges: &[Option<EdgeIndex>],
+) -> bool
+where
+    T: Condition + Clone + PartialEq,
+{
+    let mut renumbered = old.clone();
+    let mapped = renumbered.renumber(
+        &|node: NodeIndex| nodes.get(node.index()).copied().flatten(),
+        &|edge: EdgeIndex| edges.get(edge.index()).copied().flatten(),
+    );
+    mapped && renumbered == *new
+}
+
+/// Returns the nodes of a graph.
+fn nodes<T>(graph: &DialogueGraph<T>) -> Vec<&Node>
+where
+    T: Condition,
+{
+    graph
+        .data
+        .raw_nodes()
+        .iter()
+        .map(|node| &node.weight)
+        .collect()
+}
+
+/// Returns the edges of a graph with the nodes they connect.
+fn edges<T>(graph: &DialogueGraph<T>) -> Vec<(&Node, &Node, &Edge<T>)>
+where
+    T: Condition,
+{
+    graph
+        .data
+        .raw_edges()
+        .iter()
+        .map(|edge| {
+            (
+                &graph.data[edge.source()],
+                &graph.data[edge.target()],
+                &edge.weight,
+            )
+        })
+        .collect()
+}
+
+/// Returns whether two lists have the same items, in any order.
+fn same_items<I>(left: &[I], right: &[I]) -> bool
+where
+    I: PartialEq,
+{
+    if left.len() != right.len() {
+        return false;
+    }
+    let mut used = vec![false; right.len()];
+    left.iter().all(|item| {
+        let found = (0..right.len()).find(|index| !used[*index] && right[*index] == *item);
+        match found {
+            Some(index) => {
+                used[index] = true;
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::condition::dynamic::DynCondition;
+
+    #[test]
+    fn test_diff_ignores_order() {
+        let mut old = DialogueGraph::new();
+        let a = old.data.add_node("A".into());
+        let b = old.data.add_node("B".into());
+        let _ = old.data.add_edge(a, b, Edge::new(DynCondition::True));
+        let _ = old.data.add_edge(a, b, Edge::new(DynCondition::False));
+
+        let mut new = DialogueGraph::new();
+        let b = new.data.add_node("B".into());
+        let a = new.data.add_node("A".into());
+        let _ = new.data.add_edge(a, b, Edge::new(DynCondition::False));
+        let _ = new.data.add_edge(a, b, Edge::new(DynCondition::True));
+
+        let diff = old.diff(&new);
+        assert!(diff.is_empty());
+        assert_eq!(diff.edges, vec![(0.into(), 1.into()), (1.into(), 0.into())]);
+    }
+
+    #[test]
+    fn test_diff_changes() {
+        let mut old = DialogueGraph::new();
+        let a = old.data.add_node(Node::new("A").with_id("a"));
+        let b = old.data.add_node("B".into());
+        let c = old.data.add_node("C".into());
+        let ab = old.data.add_edge(a, b, Edge::new(DynCondition::True));
+        let _ = old.data.add_edge(b, c, Edge::new(DynCondition::True));
+
+        let mut new = DialogueGraph::new();
+        let new_a = new.data.add_node(Node::new("A!").with_id("a"));
+        let new_b = new.data.add_node("B".into());
+        let d = new.data.add_node("D".into());
+        let new_ab = new
+            .data
+            .add_edge(new_a, new_b, Edge::new(DynCondition::False));
+        let bd = new.data.add_edge(new_b, d, Edge::new(DynCondition::True));
+
+        assert_eq!(
+            old.diff(&new).changes,
+            vec![
+                Change::ModifiedNode { old: a, new: new_a },
+                Change::RemovedNode { node: c },
+                Change::AddedNode { node: d },
+                Change::ModifiedCondition {
+                    old: ab,
+                    new: new_ab
+                },
+                Change::RemovedEdge { edge: 1.into() },
+                Change::AddedEdge { edge: bd },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_equivalent() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let _ = graph.data.add_edge(a, b, Edge::new(DynCondition::True));
+        let _ = graph.data.add_edge(a, b, Edge::new(DynCondition::True));
+        let _ = graph.data.add_edge(b, a, Edge::new(DynCondition::False));
+
+        let mut rebuilt = DialogueGraph::new();
+        let a = rebuilt.data.add_node("A".into());
+        let _ = rebuilt.data.add_node("B".into());
+        let _ = rebuilt.data.remove_node(a);
+        // Removing a node moves the last node to its index.
+        let b = a;
+        let a = rebuilt.data.add_node("A".into());
+        let _ = rebuilt.data.add_edge(b, a, Edge::new(DynCondition::False));
+        let _ = rebuilt.data.add_edge(a, b, Edge::new(DynCondition::True));
+        let once = rebuilt.data.add_edge(a, b, Edge::new(DynCondition::True));
+        assert!(graph.equivalent(&rebuilt));
+
+        rebuilt.data[once].once = true;
+        assert!(!graph.equivalent(&rebuilt));
+    }
+
+    #[test]
+    fn test_diff_renumbers_conditions() {
+        let mut old = DialogueGraph::new();
+        let a = old.data.add_node("A".into());
+        let b = old.data.add_node("B".into());
+        let ab = old.data.add_edge(a, b, Edge::new(DynCondition::True));
+        let _ = old.data.add_edge(
+            b,
+            a,
+            Edge::new(DynCondition::and(
+                DynCondition::Visited { node: b },
+                DynCondition::EdgeTaken { edge: ab },
+            )),
+        );
+
+        let mut new = DialogueGraph::new();
+        let b = new.data.add_node("B".into());
+        let a = new.data.add_node("A".into());
+        let ba = new.data.add_edge(b, a, Edge::new(DynCondition::False));
+        let ab = new.data.add_edge(a, b, Edge::new(DynCondition::True));
+        new.data[ba].condition = DynCondition::and(
+            DynCondition::Visited { node: b },
+            DynCondition::EdgeTaken { edge: ab },
+        );
+
+        let diff = old.diff(&new);
+        assert!(diff.is_empty());
+        assert_eq!(diff.edges, vec![(0.into(), ab), (1.into(), ba)]);
+
+        new.data[ba].condition = DynCondition::Visited { node: a };
+        assert_eq!(
+            old.diff(&new).changes,
+            vec![Change::ModifiedCondition {
+                old: 1.into(),
+                new: ba
+            }]
+        );
+    }
+}
@@ -0,0 +1,384 @@
+use crate::{
+    condition::Condition,
+    conversation::{Conversation, ConversationError, Position, AUTO_ADVANCE_LIMIT},
+    EdgeKind, Node,
+};
+use petgraph::{graph::EdgeIndex, visit::EdgeRef};
+
+/// The evaluation of a choice leading out of a node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Evaluation {
+    /// The index of the edge.
+    pub edge: EdgeIndex,
+    /// Whether the edge's condition evaluated as true.
+    pub condition: bool,
+    /// Whether the edge's usage limits allow traversing it.
+    pub within_limits: bool,
+}
+
+impl Evaluation {
+    /// Returns whether the edge can be traversed.
+    pub fn is_open(&self) -> bool {
+        self.condition && self.within_limits
+    }
+}
+
+/// The traversal of an edge.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Traversal {
+    /// The index of the edge.
+    pub edge: EdgeIndex,
+    /// How the edge was traversed.
+    pub kind: EdgeKind,
+    /// The position the edge leads out of.
+    pub from: Position,
+    /// The position moved to, which is the entry node of the called graph if
+    /// the edge calls one.
+    pub to: Position,
+}
+
+/// Reacts to the events of a conversation driven by a
+/// [`Stepper`](struct.Stepper.html). Every callback does nothing unless
+/// implemented.
+///
+/// Edges have no side effects of their own: the only action an edge can
+/// execute is calling a graph in the library, which is what
+/// [`action_executed`](#method.action_executed) reports. Effects such as
+/// setting variables or giving items are up to the observer, keyed on the
+/// edges and nodes it is told about.
+pub trait Observer {
+    /// Called when the conversation moves to a node, including the node it
+    /// starts at and the nodes called graphs return to.
+    fn node_entered(&mut self, _position: &Position, _node: &Node) {}
+
+    /// Called when the conversation stops for a choice, with the evaluation
+    /// of every choice leading out of the current node, open or not, in the
+    /// order they were added to the graph.
+    fn choices_presented(&mut self, _position: &Position, _evaluations: &[Evaluation]) {}
+
+    /// Called when a choice is selected, once it has been found open and
+    /// before observers are told it was traversed.
+    fn choice_selected(&mut self, _position: &Position, _edge: EdgeIndex) {}
+
+    /// Called when an edge is traversed, whether chosen or automatic.
+    fn edge_traversed(&mut self, _traversal: &Traversal) {}
+
+    /// Called when a traversed edge calls a graph in the library, which is
+    /// the only action edges can execute, after observers are told it was
+    /// traversed.
+    fn action_executed(&mut self, _edge: EdgeIndex, _graph: &str) {}
+
+    /// Called when no choices are open and there is nothing to return to.
+    fn conversation_ended(&mut self, _position: &Position) {}
+}
+
+impl<O> Observer for &mut O
+where
+    O: Observer + ?Sized,
+{
+    fn node_entered(&mut self, position: &Position, node: &Node) {
+        (**self).node_entered(position, node)
+    }
+
+    fn choices_presented(&mut self, position: &Position, evaluations: &[Evaluation]) {
+        (**self).choices_presented(position, evaluations)
+    }
+
+    fn choice_selected(&mut self, position: &Position, edge: EdgeIndex) {
+        (**self).choice_selected(position, edge)
+    }
+
+    fn edge_traversed(&mut self, traversal: &Traversal) {
+        (**self).edge_traversed(traversal)
+    }
+
+    fn action_executed(&mut self, edge: EdgeIndex, graph: &str) {
+        (**self).action_executed(edge, graph)
+    }
+
+    fn conversation_ended(&mut self, position: &Position) {
+        (**self).conversation_ended(position)
+    }
+}
+
+/// Steps through a `Conversation` from choice to choice, telling observers
+/// about everything that happens on the way.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::True, conversation::{Conversation, Position}, events::{Observer, Stepper}, DialogueGraph, Edge, Node};
+/// #[derive(Default)]
+/// struct Subtitles(Vec<String>);
+///
+/// impl Observer for Subtitles {
+///     fn node_entered(&mut self, _position: &Position, node: &Node) {
+///         self.0.push(node.text.clone());
+///     }
+/// }
+///
+/// let mut graph = DialogueGraph::new();
+/// let hello = graph.data.add_node("Hello.".into());
+/// let question = graph.data.add_node("Need anything?".into());
+/// let bye = graph.data.add_node("Bye.".into());
+/// graph.data.add_edge(hello, question, Edge::new(True::new()).auto());
+/// let leave = graph.data.add_edge(question, bye, Edge::new(True::new()));
+///
+/// let mut subtitles = Subtitles::default();
+/// let mut stepper = Stepper::new(Conversation::new(&graph, hello))
+///     .with_observer(Box::new(&mut subtitles));
+/// assert_eq!(stepper.start().unwrap(), vec![leave]);
+/// assert!(stepper.select(leave).unwrap().is_empty());
+/// drop(stepper);
+///
+/// assert_eq!(subtitles.0, vec!["Hello.", "Need anything?", "Bye."]);
+/// ```
+#[allow(missing_debug_implementations)]
+pub struct Stepper<'a, T>
+where
+    T: Condition,
+{
+    conversation: Conversation<'a, T>,
+    observers: Vec<Box<dyn Observer + 'a>>,
+}
+
+impl<'a, T> Stepper<'a, T>
+where
+    T: Condition,
+{
+    /// Create a stepper driving the given conversation, with no observers.
+    pub fn new(conversation: Conversation<'a, T>) -> Self {
+        Self {
+            conversation,
+            observers: Vec::new(),
+        }
+    }
+
+    /// Tell the given observer about events, after any added before it.
+    pub fn with_observer(mut self, observer: Box<dyn Observer + 'a>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Returns the conversation being driven.
+    pub fn conversation(&self) -> &Conversation<'a, T> {
+        &self.conversation
+    }
+
+    /// Returns the conversation being driven, ending the stepper.
+    pub fn into_conversation(self) -> Conversation<'a, T> {
+        self.conversation
+    }
+
+    /// Enter the node the conversation is at and move on to the first
+    /// choice, returning the open choices, which are empty if the
+    /// conversation has ended.
+    pub fn start(&mut self) -> Result<Vec<EdgeIndex>, ConversationError> {
+        self.entered();
+        self.proceed()
+    }
+
+    /// Traverse an open choice and move on to the next, returning the open
+    /// choices, which are empty if the conversation has ended.
+    ///
+    /// Unlike [`Conversation::advance`], the conversation is left at the
+    /// node it reached if following automatic edges fails, as observers have
+    /// been told about the nodes on the way.
+    ///
+    /// [`Conversation::advance`]: ../conversation/struct.Conversation.html#method.advance
+    pub fn select(&mut self, edge: EdgeIndex) -> Result<Vec<EdgeIndex>, ConversationError> {
+        self.traverse(edge, true)?;
+        self.proceed()
+    }
+
+    /// Tell observers about the node the conversation is at.
+    fn entered(&mut self) {
+        let position = self.conversation.position();
+        let node = self.conversation.node();
+        for observer in &mut self.observers {
+            observer.node_entered(&position, node);
+        }
+    }
+
+    /// Traverse an edge, telling observers about it, and that it was
+    /// selected if it was.
+    fn traverse(&mut self, edge: EdgeIndex, selected: bool) -> Result<(), ConversationError> {
+        let from = self.conversation.position();
+        let graph = self.conversation.graph();
+        let _ = self.conversation.choose(edge)?;
+        if selected {
+            for observer in &mut self.observers {
+                observer.choice_selected(&from, edge);
+            }
+        }
+        let weight = &graph.data[edge];
+        let (kind, call) = (weight.kind, weight.call.as_deref());
+        let traversal = Traversal {
+            edge,
+            kind,
+            from,
+            to: self.conversation.position(),
+        };
+        for observer in &mut self.observers {
+            observer.edge_traversed(&traversal);
+            if let Some(graph) = call {
+                observer.action_executed(edge, graph);
+            }
+        }
+        self.entered();
+        Ok(())
+    }
+
+    /// Follow automatic edges and return from finished called graphs, then
+    /// present the open choices or end the conversation.
+    fn proceed(&mut self) -> Result<Vec<EdgeIndex>, ConversationError> {
+        let mut steps = 0;
+        loop {
+            if let Some(edge) = self.conversation.auto_edge() {
+                if steps == AUTO_ADVANCE_LIMIT {
+                    return Err(ConversationError::AutoAdvanceCycle(
+                        self.conversation.current(),
+                    ));
+                }
+                self.traverse(edge, false)?;
+                steps += 1;
+            } else if self.conversation.return_to_caller()? {
+                self.entered();
+            } else {
+                break;
+            }
+        }
+
+        let position = self.conversation.position();
+        let choices = self.conversation.choices();
+        if choices.is_empty() {
+            for observer in &mut self.observers {
+                observer.conversation_ended(&position);
+            }
+            return Ok(choices);
+        }
+        let context = self.conversation.context();
+        let mut evaluations: Vec<Evaluation> = self
+            .conversation
+            .graph()
+            .data
+            .edges(position.node)
+            .filter(|edge| edge.weight().kind == EdgeKind::Choice)
+            .map(|edge| Evaluation {
+                edge: edge.id(),
+                condition: edge.weight().condition.evaluate_with(&context),
+                within_limits: edge.weight().within_limits(
+                    context.traversal_count(edge.id()),
+                    context.traversals_since(edge.id()),
+                ),
+            })
+            .collect();
+        evaluations.sort_by_key(|evaluation| evaluation.edge);
+        for observer in &mut self.observers {
+            observer.choices_presented(&position, &evaluations);
+        }
+        Ok(choices)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{condition::dynamic::DynCondition, library::Library, DialogueGraph, Edge};
+    use petgraph::graph::NodeIndex;
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl Observer for Log {
+        fn node_entered(&mut self, position: &Position, node: &Node) {
+            let graph = position.graph.as_deref().unwrap_or("root");
+            self.0.push(format!(
+                "enter {}:{} {}",
+                graph,
+                position.node.index(),
+                node.text
+            ));
+        }
+
+        fn choices_presented(&mut self, _position: &Position, evaluations: &[Evaluation]) {
+            let open: Vec<String> = evaluations
+                .iter()
+                .map(|evaluation| format!("{}={}", evaluation.edge.index(), evaluation.is_open()))
+                .collect();
+            self.0.push(format!("choices {}", open.join(" ")));
+        }
+
+        fn choice_selected(&mut self, _position: &Position, edge: EdgeIndex) {
+            self.0.push(format!("select {}", edge.index()));
+        }
+
+        fn edge_traversed(&mut self, traversal: &Traversal) {
+            self.0.push(format!(
+                "traverse {} {:?}",
+                traversal.edge.index(),
+                traversal.kind
+            ));
+        }
+
+        fn action_executed(&mut self, edge: EdgeIndex, graph: &str) {
+            self.0.push(format!("call {} {}", edge.index(), graph));
+        }
+
+        fn conversation_ended(&mut self, position: &Position) {
+            self.0.push(format!("end {}", position.node.index()));
+        }
+    }
+
+    #[test]
+    fn test_events() {
+        let mut shop = DialogueGraph::new();
+        let _ = shop.data.add_node("Welcome to the shop.".into());
+        let mut library = Library::new();
+        let _ = library.insert("shop", shop);
+
+        let mut graph = DialogueGraph::new();
+        let hub = graph.data.add_node("Hub".into());
+        let bye = graph.data.add_node("Bye".into());
+        let secret = graph.data.add_node("Secret".into());
+        let browse = graph.data.add_edge(
+            hub,
+            hub,
+            Edge::new(DynCondition::True).once().with_call("shop"),
+        );
+        let _ = graph
+            .data
+            .add_edge(hub, secret, Edge::new(DynCondition::False));
+        let leave = graph.data.add_edge(hub, bye, Edge::new(DynCondition::True));
+
+        let mut log = Log::default();
+        let conversation = Conversation::new(&graph, hub).with_library(&library);
+        let mut stepper = Stepper::new(conversation).with_observer(Box::new(&mut log));
+        assert_eq!(stepper.start(), Ok(vec![browse, leave]));
+        assert_eq!(stepper.select(browse), Ok(vec![leave]));
+        assert_eq!(
+            stepper.select(browse),
+            Err(ConversationError::ExhaustedEdge(browse))
+        );
+        assert_eq!(stepper.select(leave), Ok(Vec::new()));
+        assert_eq!(stepper.conversation().current(), NodeIndex::new(1));
+        drop(stepper);
+
+        assert_eq!(
+            log.0,
+            vec![
+                "enter root:0 Hub",
+                "choices 0=true 1=false 2=true",
+                "select 0",
+                "traverse 0 Choice",
+                "call 0 shop",
+                "enter shop:0 Welcome to the shop.",
+                "enter root:0 Hub",
+                "choices 0=false 1=false 2=true",
+                "select 2",
+                "traverse 2 Choice",
+                "enter root:1 Bye",
+                "end 1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use super::{text::write_condition, Loss};
+use crate::{
+    condition::{dynamic::DynCondition, Condition},
+    DialogueGraph, EdgeKind, FORMAT_VERSION,
+};
+use std::fmt::Write;
+
+/// Escape a string for a double-quoted DOT label.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write a graph in the DOT language of [Graphviz](https://graphviz.org), to
+/// be drawn.
+///
+/// Nodes are labelled with their text after their speaker, and links are
+/// drawn dashed with the reference they stand for. Edges are labelled with
+/// their text and condition, and automatic edges are drawn dashed.
+///
+/// DOT cannot be read back, so the content left out is reported: a version
+/// other than the current one, ids, keys, traversal limits, calls and opaque
+/// conditions.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::True, format::dot, DialogueGraph, Edge, Node};
+/// let mut graph = DialogueGraph::new();
+/// let a = graph.data.add_node(Node::new("Halt!").with_speaker("Guard"));
+/// let b = graph.data.add_node("Who goes there?".into());
+/// graph.data.add_edge(a, b, Edge::new(True::new()).auto());
+///
+/// let (written, losses) = dot::write(&graph);
+/// assert!(losses.is_empty());
+/// assert!(written.contains("0 [label=\"Guard: Halt!\"];"));
+/// assert!(written.contains("0 -> 1 [style=dashed];"));
+/// ```
+pub fn write<T>(graph: &DialogueGraph<T>) -> (String, Vec<Loss>)
+where
+    T: Condition,
+{
+    let mut text = String::from("digraph {\n");
+    let mut ids = 0;
+    let mut keys = 0;
+    let mut limits = 0;
+    let mut calls = 0;
+    let mut opaque = Vec::new();
+
+    for index in graph.data.node_indices() {
+        let node = &graph.data[index];
+        ids += node.id.is_some() as usize;
+        keys += node.key.is_some() as usize;
+        let label = match (&node.link, &node.speaker) {
+            (Some(link), _) => format!("-> {}", link),
+            (None, Some(speaker)) => format!("{}: {}", speaker, node.text),
+            (None, None) => node.text.clone(),
+        };
+        let _ = write!(text, "    {} [label=\"{}\"", index.index(), escape(&label));
+        if node.link.is_some() {
+            text.push_str(", style=dashed");
+        }
+        text.push_str("];\n");
+    }
+
+    for index in graph.data.edge_indices() {
+        let edge = &graph.data[index];
+        let (source, target) = match graph.data.edge_endpoints(index) {
+            Some(endpoints) => endpoints,
+            None => continue,
+        };
+        keys += edge.key.is_some() as usize;
+        limits += (edge.once || edge.max_uses.is_some() || edge.cooldown.is_some()) as usize;
+        calls += edge.call.is_some() as usize;
+
+        let mut label = edge.text.clone().unwrap_or_default();
+        let condition = edge.condition.to_dynamic();
+        if condition != DynCondition::True {
+            if !label.is_empty() {
+                label.push('\n');
+            }
+            label.push_str("if ");
+            write_condition(&condition, 0, &mut label, &mut opaque);
+        }
+        let mut attributes = Vec::new();
+        if !label.is_empty() {
+            attributes.push(format!("label=\"{}\"", escape(&label)));
+        }
+        if edge.kind == EdgeKind::Auto {
+            attributes.push("style=dashed".to_owned());
+        }
+        let _ = write!(text, "    {} -> {}", source.index(), target.index());
+        if !attributes.is_empty() {
+            let _ = write!(text, " [{}]", attributes.join(", "));
+        }
+        text.push_str(";\n");
+    }
+    text.push_str("}\n");
+
+    let mut losses = Vec::new();
+    if graph.version != FORMAT_VERSION {
+        losses.push(Loss::new(format!(
+            "the version {} is left out",
+            graph.version
+        )));
+    }
+    let counted = [
+        (ids, "node ids"),
+        (keys, "string keys"),
+        (limits, "edge traversal limits"),
+        (calls, "edge calls"),
+    ];
+    for (count, what) in counted.iter() {
+        if *count > 0 {
+            losses.push(Loss::new(format!("{} are left out ({})", what, count)));
+        }
+    }
+    for name in opaque {
+        losses.push(Loss::new(format!(
+            "the opaque condition {:?} is written as false",
+            name
+        )));
+    }
+    (text, losses)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Edge, Node};
+
+    #[test]
+    fn test_write() {
+        let mut graph = DialogueGraph::new();
+        graph.version = 2;
+        let a = graph
+            .data
+            .add_node(Node::new("Say \"hi\"").with_id("a").with_key("hi"));
+        let b = graph.data.add_node(Node::link("other::b"));
+        let condition = DynCondition::and(
+            DynCondition::Visited { node: b },
+            DynCondition::opaque("weather"),
+        );
+        let _ = graph.data.add_edge(
+            a,
+            b,
+            Edge::new(condition).once().with_cooldown(2).with_text("Go"),
+        );
+
+        let (written, losses) = write(&graph);
+
+        assert_eq!(
+            written,
+            "digraph {\n    \
+             0 [label=\"Say \\\"hi\\\"\"];\n    \
+             1 [label=\"-> other::b\", style=dashed];\n    \
+             0 -> 1 [label=\"Go\\nif visited(1) and false\"];\n\
+             }\n"
+        );
+        let messages: Vec<String> = losses.iter().map(Loss::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "the version 2 is left out",
+                "node ids are left out (1)",
+                "string keys are left out (1)",
+                "edge traversal limits are left out (1)",
+                "the opaque condition \"str\" is written as false",
+            ]
+        );
+    }
+}
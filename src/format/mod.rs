@@ -0,0 +1,27 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Graphviz DOT output
+pub mod dot;
+/// A readable text format
+pub mod text;
+
+/// Content a format cannot hold, which was left out or changed when writing a
+/// graph in it.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct Loss {
+    /// What was lost.
+    pub message: String,
+}
+
+impl Loss {
+    pub(crate) fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl fmt::Display for Loss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
@@ -0,0 +1,673 @@
+use super::Loss;
+use crate::{
+    condition::{dynamic::DynCondition, Comparison, Condition},
+    variable::Value,
+    DialogueGraph, Edge, EdgeKind, Node,
+};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use std::{error::Error, fmt, fmt::Write};
+
+/// Words with a meaning in conditions, which variable names are quoted to
+/// avoid.
+const KEYWORDS: &[&str] = &[
+    "and", "or", "not", "true", "false", "visited", "taken", "if",
+];
+
+/// Symbols, longest first so that `->` is not read as `-`.
+const SYMBOLS: &[&str] = &[
+    "->", "==", "!=", "<=", ">=", "<", ">", "(", ")", ",", "=", ":",
+];
+
+/// An error raised when text cannot be read as a graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextError {
+    /// The line of the problem, counting from 1.
+    pub line: usize,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for TextError {}
+
+/// Quote a string as in JSON.
+fn quote(text: &str) -> String {
+    serde_json::Value::from(text).to_string()
+}
+
+/// Returns whether a variable name can be written without quotes.
+fn is_bare(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+        && !KEYWORDS.contains(&name)
+}
+
+/// Write a condition, wrapping it in parentheses if it binds less tightly
+/// than the given precedence. Opaque conditions cannot be written and are
+/// written as `false`, which is how they evaluate, with their names added to
+/// `opaque`.
+pub(crate) fn write_condition(
+    condition: &DynCondition,
+    precedence: u8,
+    text: &mut String,
+    opaque: &mut Vec<String>,
+) {
+    let own = match condition {
+        DynCondition::Or { .. } => 1,
+        DynCondition::And { .. } => 2,
+        DynCondition::Not { .. } => 3,
+        _ => 4,
+    };
+    if own < precedence {
+        text.push('(');
+    }
+    match condition {
+        DynCondition::True => text.push_str("true"),
+        DynCondition::False => text.push_str("false"),
+        DynCondition::Not { condition } => {
+            text.push_str("not ");
+            write_condition(condition, 3, text, opaque);
+        }
+        DynCondition::And { left, right } => {
+            write_condition(left, 2, text, opaque);
+            text.push_str(" and ");
+            write_condition(right, 3, text, opaque);
+        }
+        DynCondition::Or { left, right } => {
+            write_condition(left, 1, text, opaque);
+            text.push_str(" or ");
+            write_condition(right, 2, text, opaque);
+        }
+        DynCondition::Compare {
+            variable,
+            comparison,
+            value,
+        } => {
+            if is_bare(variable) {
+                text.push_str(variable);
+            } else {
+                text.push_str(&quote(variable));
+            }
+            let _ = write!(text, " {} ", comparison);
+            match value {
+                Value::Str(value) => text.push_str(&quote(value)),
+                value => {
+                    let _ = write!(text, "{}", value);
+                }
+            }
+        }
+        DynCondition::Visited { node } => {
+            let _ = write!(text, "visited({})", node.index());
+        }
+        DynCondition::VisitCount { node, count } => {
+            let _ = write!(text, "visited({}, {})", node.index(), count);
+        }
+        DynCondition::EdgeTaken { edge } => {
+            let _ = write!(text, "taken({})", edge.index());
+        }
+        DynCondition::Opaque { name, .. } => {
+            opaque.push(name.clone());
+            text.push_str("false");
+        }
+    }
+    if own < precedence {
+        text.push(')');
+    }
+}
+
+/// Write a graph in the text format.
+///
+/// Each node and edge is written on a line of its own, in order of index:
+///
+/// ```text
+/// version 1
+///
+/// node 0: "Halt!" id="halt" speaker="Guard"
+/// node 1: "" link="gate::open"
+///
+/// edge 0: 0 -> 1 once text="Let me through" if gold >= 10 and not visited(1)
+/// ```
+///
+/// Strings are quoted as in JSON. The optional attributes of nodes are `id`,
+/// `speaker`, `key` and `link`, and those of edges are `auto`, `once`,
+/// `max_uses`, `cooldown`, `call`, `text` and `key`, followed by `if` and the
+/// edge's condition unless it is `true`.
+///
+/// Opaque conditions cannot be written, and are written as `false`.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::{Comparison, Compare}, format::text, DialogueGraph, Edge};
+/// let mut graph = DialogueGraph::new();
+/// let a = graph.data.add_node("Hello.".into());
+/// let b = graph.data.add_node("Goodbye.".into());
+/// graph.data.add_edge(a, b, Edge::new(Compare::new("gold", Comparison::Less, 5)).auto());
+///
+/// let (written, losses) = text::write(&graph);
+/// assert!(losses.is_empty());
+/// assert!(written.ends_with("edge 0: 0 -> 1 auto if gold < 5\n"));
+///
+/// let read = text::read(&written).unwrap();
+/// assert_eq!(text::write(&read).0, written);
+/// ```
+pub fn write<T>(graph: &DialogueGraph<T>) -> (String, Vec<Loss>)
+where
+    T: Condition,
+{
+    let mut text = String::new();
+    let mut losses = Vec::new();
+    let _ = writeln!(text, "version {}", graph.version);
+    if graph.data.node_count() > 0 {
+        text.push('\n');
+    }
+    for index in graph.data.node_indices() {
+        let node = &graph.data[index];
+        let _ = write!(text, "node {}: {}", index.index(), quote(&node.text));
+        let attributes = [
+            ("id", &node.id),
+            ("speaker", &node.speaker),
+            ("key", &node.key),
+            ("link", &node.link),
+        ];
+        for (name, value) in attributes.iter() {
+            if let Some(value) = value {
+                let _ = write!(text, " {}={}", name, quote(value));
+            }
+        }
+        text.push('\n');
+    }
+    if graph.data.edge_count() > 0 {
+        text.push('\n');
+    }
+    for index in graph.data.edge_indices() {
+        let edge = &graph.data[index];
+        let (source, target) = match graph.data.edge_endpoints(index) {
+            Some(endpoints) => endpoints,
+            None => continue,
+        };
+        let _ = write!(
+            text,
+            "edge {}: {} -> {}",
+            index.index(),
+            source.index(),
+            target.index()
+        );
+        if edge.kind == EdgeKind::Auto {
+            text.push_str(" auto");
+        }
+        if edge.once {
+            text.push_str(" once");
+        }
+        if let Some(max_uses) = edge.max_uses {
+            let _ = write!(text, " max_uses={}", max_uses);
+        }
+        if let Some(cooldown) = edge.cooldown {
+            let _ = write!(text, " cooldown={}", cooldown);
+        }
+        let attributes = [
+            ("call", &edge.call),
+            ("text", &edge.text),
+            ("key", &edge.key),
+        ];
+        for (name, value) in attributes.iter() {
+            if let Some(value) = value {
+                let _ = write!(text, " {}={}", name, quote(value));
+            }
+        }
+        let condition = edge.condition.to_dynamic();
+        if condition != DynCondition::True {
+            let mut opaque = Vec::new();
+            text.push_str(" if ");
+            write_condition(&condition, 0, &mut text, &mut opaque);
+            for name in opaque {
+                losses.push(Loss::new(format!(
+                    "edge {}: the opaque condition {:?} is written as false",
+                    index.index(),
+                    name
+                )));
+            }
+        }
+        text.push('\n');
+    }
+    (text, losses)
+}
+
+/// A token of a line in the text format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => f.write_str(word),
+            Token::Str(string) => f.write_str(&quote(string)),
+            Token::Symbol(symbol) => f.write_str(symbol),
+        }
+    }
+}
+
+/// Split a line into tokens, ignoring a comment starting with `#`.
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() && !rest.starts_with('#') {
+        if rest.starts_with('"') {
+            let mut escaped = false;
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| {
+                    let end = !escaped && *c == '"';
+                    escaped = !escaped && *c == '\\';
+                    end
+                })
+                .map(|(end, _)| end)
+                .ok_or("unclosed string")?;
+            let string = serde_json::from_str(&rest[..=end])
+                .map_err(|error| format!("invalid string: {}", error))?;
+            tokens.push(Token::Str(string));
+            rest = &rest[end + 1..];
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else {
+            let start = if rest.starts_with('-') { 1 } else { 0 };
+            let end = rest[start..]
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                .map_or(rest.len(), |end| start + end);
+            if end == 0 || end == start {
+                let c = rest.chars().next().unwrap_or_default();
+                return Err(format!("unexpected character {:?}", c));
+            }
+            tokens.push(Token::Word(rest[..end].to_owned()));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Reads the tokens of a line.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or("unexpected end of line")?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    /// Consume the given symbol or word if it is next, returning whether it
+    /// was.
+    fn eat(&mut self, expected: &str) -> bool {
+        let found = match self.peek() {
+            Some(Token::Symbol(symbol)) => *symbol == expected,
+            Some(Token::Word(word)) => word == expected,
+            _ => false,
+        };
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        if self.eat(expected) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(token) => Err(format!("expected `{}`, found `{}`", expected, token)),
+            None => Err(format!("expected `{}` at the end of the line", expected)),
+        }
+    }
+
+    fn number<N>(&mut self) -> Result<N, String>
+    where
+        N: std::str::FromStr,
+    {
+        match self.next()? {
+            Token::Word(word) => word
+                .parse()
+                .map_err(|_| format!("expected a number, found `{}`", word)),
+            token => Err(format!("expected a number, found `{}`", token)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Str(string) => Ok(string),
+            token => Err(format!("expected a quoted string, found `{}`", token)),
+        }
+    }
+
+    fn end(&self) -> Result<(), String> {
+        match self.peek() {
+            Some(token) => Err(format!("unexpected `{}`", token)),
+            None => Ok(()),
+        }
+    }
+
+    fn or(&mut self) -> Result<DynCondition, String> {
+        let mut condition = self.and()?;
+        while self.eat("or") {
+            condition = DynCondition::or(condition, self.and()?);
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<DynCondition, String> {
+        let mut condition = self.unary()?;
+        while self.eat("and") {
+            condition = DynCondition::and(condition, self.unary()?);
+        }
+        Ok(condition)
+    }
+
+    fn unary(&mut self) -> Result<DynCondition, String> {
+        if self.eat("not") {
+            return Ok(DynCondition::not(self.unary()?));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<DynCondition, String> {
+        if self.eat("(") {
+            let condition = self.or()?;
+            self.expect(")")?;
+            return Ok(condition);
+        }
+        let variable = match self.next()? {
+            Token::Word(word) => match word.as_str() {
+                "true" => return Ok(DynCondition::True),
+                "false" => return Ok(DynCondition::False),
+                "visited" => {
+                    self.expect("(")?;
+                    let node = NodeIndex::new(self.number()?);
+                    let condition = if self.eat(",") {
+                        DynCondition::VisitCount {
+                            node,
+                            count: self.number()?,
+                        }
+                    } else {
+                        DynCondition::Visited { node }
+                    };
+                    self.expect(")")?;
+                    return Ok(condition);
+                }
+                "taken" => {
+                    self.expect("(")?;
+                    let edge = EdgeIndex::new(self.number()?);
+                    self.expect(")")?;
+                    return Ok(DynCondition::EdgeTaken { edge });
+                }
+                _ if is_bare(&word) => word,
+                _ => return Err(format!("expected a condition, found `{}`", word)),
+            },
+            Token::Str(variable) => variable,
+            token => return Err(format!("expected a condition, found `{}`", token)),
+        };
+
+        let comparison = match self.next()? {
+            Token::Symbol("==") => Comparison::Equal,
+            Token::Symbol("!=") => Comparison::NotEqual,
+            Token::Symbol("<") => Comparison::Less,
+            Token::Symbol("<=") => Comparison::LessOrEqual,
+            Token::Symbol(">") => Comparison::Greater,
+            Token::Symbol(">=") => Comparison::GreaterOrEqual,
+            token => return Err(format!("expected a comparison, found `{}`", token)),
+        };
+        let value = match self.next()? {
+            Token::Str(value) => Value::Str(value),
+            Token::Word(word) => match word.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => Value::Int(
+                    word.parse()
+                        .map_err(|_| format!("expected a value, found `{}`", word))?,
+                ),
+            },
+            token => return Err(format!("expected a value, found `{}`", token)),
+        };
+        Ok(DynCondition::Compare {
+            variable,
+            comparison,
+            value,
+        })
+    }
+}
+
+/// Read a graph from the text format written by [`write`](fn.write.html).
+///
+/// Nodes and edges must be listed in order of index, and nodes before the
+/// edges between them. Blank lines and comments starting with `#` are
+/// ignored.
+pub fn read(text: &str) -> Result<DialogueGraph<DynCondition>, TextError> {
+    let mut graph = DialogueGraph::new();
+    for (index, line) in text.lines().enumerate() {
+        read_line(&mut graph, line).map_err(|message| TextError {
+            line: index + 1,
+            message,
+        })?;
+    }
+    Ok(graph)
+}
+
+/// Read a line of the text format into the graph.
+fn read_line(graph: &mut DialogueGraph<DynCondition>, line: &str) -> Result<(), String> {
+    let mut parser = Parser {
+        tokens: tokenize(line)?,
+        position: 0,
+    };
+    let keyword = match parser.peek() {
+        None => return Ok(()),
+        Some(_) => parser.next()?,
+    };
+    match keyword {
+        Token::Word(word) if word == "version" => {
+            graph.version = parser.number()?;
+        }
+        Token::Word(word) if word == "node" => {
+            let index: usize = parser.number()?;
+            if index != graph.data.node_count() {
+                return Err(format!(
+                    "expected node {}, found node {}",
+                    graph.data.node_count(),
+                    index
+                ));
+            }
+            parser.expect(":")?;
+            let mut node = Node::new(&parser.string()?);
+            while let Some(token) = parser.peek().cloned() {
+                parser.position += 1;
+                let slot = match &token {
+                    Token::Word(word) if word == "id" => &mut node.id,
+                    Token::Word(word) if word == "speaker" => &mut node.speaker,
+                    Token::Word(word) if word == "key" => &mut node.key,
+                    Token::Word(word) if word == "link" => &mut node.link,
+                    token => return Err(format!("unknown node attribute `{}`", token)),
+                };
+                parser.expect("=")?;
+                *slot = Some(parser.string()?);
+            }
+            let _ = graph.data.add_node(node);
+        }
+        Token::Word(word) if word == "edge" => {
+            let index: usize = parser.number()?;
+            if index != graph.data.edge_count() {
+                return Err(format!(
+                    "expected edge {}, found edge {}",
+                    graph.data.edge_count(),
+                    index
+                ));
+            }
+            parser.expect(":")?;
+            let mut endpoints = [NodeIndex::end(); 2];
+            for (position, endpoint) in endpoints.iter_mut().enumerate() {
+                if position == 1 {
+                    parser.expect("->")?;
+                }
+                let node: usize = parser.number()?;
+                if node >= graph.data.node_count() {
+                    return Err(format!("node {} is not in the graph", node));
+                }
+                *endpoint = NodeIndex::new(node);
+            }
+            let mut edge = Edge::new(DynCondition::True);
+            while let Some(token) = parser.peek().cloned() {
+                parser.position += 1;
+                match &token {
+                    Token::Word(word) if word == "auto" => edge.kind = EdgeKind::Auto,
+                    Token::Word(word) if word == "once" => edge.once = true,
+                    Token::Word(word) if word == "max_uses" => {
+                        parser.expect("=")?;
+                        edge.max_uses = Some(parser.number()?);
+                    }
+                    Token::Word(word) if word == "cooldown" => {
+                        parser.expect("=")?;
+                        edge.cooldown = Some(parser.number()?);
+                    }
+                    Token::Word(word) if word == "call" || word == "text" || word == "key" => {
+                        parser.expect("=")?;
+                        let value = Some(parser.string()?);
+                        match word.as_str() {
+                            "call" => edge.call = value,
+                            "text" => edge.text = value,
+                            _ => edge.key = value,
+                        }
+                    }
+                    Token::Word(word) if word == "if" => {
+                        edge.condition = parser.or()?;
+                        parser.end()?;
+                    }
+                    token => return Err(format!("unknown edge attribute `{}`", token)),
+                }
+            }
+            let _ = graph.data.add_edge(endpoints[0], endpoints[1], edge);
+        }
+        token => {
+            return Err(format!(
+                "expected `version`, `node` or `edge`, found `{}`",
+                token
+            ))
+        }
+    }
+    parser.end()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::condition::{Compare, Condition};
+
+    #[test]
+    fn test_round_trip() {
+        let mut graph = DialogueGraph::new();
+        graph.version = 2;
+        let a = graph.data.add_node(
+            Node::new("Say \"hi\"\n# not a comment")
+                .with_id("a")
+                .with_speaker("Guard")
+                .with_key("greeting"),
+        );
+        let b = graph.data.add_node(Node::link("other::b"));
+        let condition = DynCondition::and(
+            DynCondition::or(
+                Compare::new("and", Comparison::NotEqual, "x y").to_dynamic(),
+                DynCondition::VisitCount { node: b, count: 2 },
+            ),
+            DynCondition::not(DynCondition::and(
+                Compare::new("gold", Comparison::GreaterOrEqual, -3).to_dynamic(),
+                DynCondition::and(
+                    DynCondition::EdgeTaken {
+                        edge: EdgeIndex::new(0),
+                    },
+                    Compare::new("flag", Comparison::Equal, false).to_dynamic(),
+                ),
+            )),
+        );
+        let _ = graph.data.add_edge(
+            a,
+            b,
+            Edge::new(condition)
+                .auto()
+                .once()
+                .with_max_uses(3)
+                .with_cooldown(1)
+                .with_call("shop")
+                .with_text("Go")
+                .with_key("go"),
+        );
+        let _ = graph.data.add_edge(b, a, Edge::new(DynCondition::True));
+
+        let (written, losses) = write(&graph);
+        assert!(losses.is_empty());
+        assert!(written.contains(
+            "if (\"and\" != \"x y\" or visited(1, 2)) and not (gold >= -3 and (taken(0) and flag == false))"
+        ));
+        assert_eq!(read(&written).unwrap(), graph);
+    }
+
+    #[test]
+    fn test_write_opaque() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let _ = graph
+            .data
+            .add_edge(a, a, Edge::new(DynCondition::opaque("weather")));
+
+        let (written, losses) = write(&graph);
+
+        assert!(written.ends_with("edge 0: 0 -> 0 if false\n"));
+        assert_eq!(
+            losses,
+            vec![Loss::new(
+                "edge 0: the opaque condition \"str\" is written as false".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let error = |text: &str| read(text).unwrap_err();
+
+        assert_eq!(
+            error("node 1: \"A\"").message,
+            "expected node 0, found node 1"
+        );
+        assert_eq!(error("\n\nnode 0: \"A").line, 3);
+        assert_eq!(
+            error("node 0: \"A\"\nedge 0: 0 -> 1").message,
+            "node 1 is not in the graph"
+        );
+        assert_eq!(
+            error("node 0: \"A\"\nedge 0: 0 -> 0 if gold >").message,
+            "unexpected end of line"
+        );
+        assert_eq!(
+            error("node 0: \"A\" colour=\"red\"").message,
+            "unknown node attribute `colour`"
+        );
+    }
+}
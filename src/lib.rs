@@ -15,31 +15,186 @@
     variant_size_differences
 )]
 
-use condition::Condition;
+use condition::{Condition, Context};
 use petgraph::{
-    graph::{Edges, Graph, NodeIndex},
+    graph::{EdgeIndex, Edges, Graph, NodeIndex},
+    visit::EdgeRef,
     Directed,
 };
 #[allow(unused_imports)]
 use rand::seq::IteratorRandom;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 /// Conditions
 pub mod condition;
+/// Conversations
+pub mod conversation;
+/// Path enumeration and test coverage
+pub mod coverage;
+/// Differences between graphs
+pub mod diff;
+/// Observers of conversation events
+pub mod events;
+/// Text and DOT formats of graphs
+pub mod format;
+/// Libraries of named graphs
+pub mod library;
+/// Localization of dialogue text
+pub mod localization;
+/// Markup of dialogue text
+pub mod markup;
+/// Three-way merging of graphs
+pub mod merge;
+/// Format versioning and migrations
+pub mod migration;
+/// Reachability under variable constraints
+pub mod reachability;
+/// JSON Schema generation
+pub mod schema;
 mod serde_impls;
+/// Randomized playthroughs
+pub mod simulation;
+/// Templates of dialogue text
+pub mod template;
+/// Scripted playthroughs
+pub mod transcript;
+/// Validation of dialogue content
+pub mod validation;
+/// Variables
+pub mod variable;
 
 /// A single unit of dialogue which makes up the `DialogueGraph`.
-pub type Node = String;
+///
+/// Nodes with only text are serialized as a plain string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    /// The identifier other graphs refer to the node by, if it has one.
+    pub id: Option<String>,
+    /// The name of the character who says the text, if any.
+    pub speaker: Option<String>,
+    /// The dialogue text, in the source language.
+    pub text: String,
+    /// The key of the text in a
+    /// [`StringTable`](localization/struct.StringTable.html), if it differs
+    /// from the node's id.
+    pub key: Option<String>,
+    /// The id of the node this node stands in for, if it is a link.
+    ///
+    /// Links are resolved when graphs are merged with
+    /// [`Library::merge`](library/struct.Library.html#method.merge): edges
+    /// leading to the link lead to the node it refers to instead.
+    pub link: Option<String>,
+}
+
+impl Node {
+    /// Create a node with the given text.
+    pub fn new(text: &str) -> Self {
+        Self {
+            id: None,
+            speaker: None,
+            text: text.to_owned(),
+            key: None,
+            link: None,
+        }
+    }
+
+    /// Create a link to the node with the given id, which may be qualified
+    /// with the namespace of another graph, as in `"blacksmith::farewell"`.
+    pub fn link(reference: &str) -> Self {
+        Self {
+            id: None,
+            speaker: None,
+            text: String::new(),
+            key: None,
+            link: Some(reference.to_owned()),
+        }
+    }
+
+    /// Give the node an identifier.
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_owned());
+        self
+    }
+
+    /// Give the node a speaker.
+    pub fn with_speaker(mut self, speaker: &str) -> Self {
+        self.speaker = Some(speaker.to_owned());
+        self
+    }
+
+    /// Look the node's text up in string tables by the given key.
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_owned());
+        self
+    }
+
+    /// Returns the key of the node's text in string tables: its key, or
+    /// else its id, or else the text itself.
+    pub fn text_key(&self) -> &str {
+        self.key
+            .as_deref()
+            .or(self.id.as_deref())
+            .unwrap_or(&self.text)
+    }
+}
+
+impl From<&str> for Node {
+    fn from(text: &str) -> Self {
+        Node::new(text)
+    }
+}
+
+impl From<String> for Node {
+    fn from(text: String) -> Self {
+        Self {
+            id: None,
+            speaker: None,
+            text,
+            key: None,
+            link: None,
+        }
+    }
+}
+
+/// How an edge is traversed.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// The edge is presented to the player as a choice.
+    #[default]
+    Choice,
+    /// The edge is followed automatically, without player input.
+    Auto,
+}
 
 /// A directed connection between two `Node` objects, with a condition that
 /// predicates the ability to traverse it.
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Edge<T>
 where
     T: Condition,
 {
     /// The condition for traversing.
     pub condition: T,
+    /// How the edge is traversed.
+    pub kind: EdgeKind,
+    /// Whether the edge closes once it has been traversed.
+    pub once: bool,
+    /// The number of times the edge can be traversed, if limited.
+    pub max_uses: Option<u32>,
+    /// The number of other traversals that must happen before the edge can be
+    /// traversed again, if any.
+    pub cooldown: Option<u32>,
+    /// The name of a graph in the conversation's
+    /// [`Library`](library/struct.Library.html) to run once the edge is
+    /// traversed, before continuing from the node it leads to.
+    pub call: Option<String>,
+    /// The text presented for the choice, in the source language, if any.
+    pub text: Option<String>,
+    /// The key of the choice's text in a
+    /// [`StringTable`](localization/struct.StringTable.html), if it differs
+    /// from the text itself.
+    pub key: Option<String>,
 }
 
 impl<T> Edge<T>
@@ -48,10 +203,127 @@ where
 {
     /// Create a new edge.
     pub fn new(condition: T) -> Self {
-        Self { condition }
+        Self {
+            condition,
+            kind: EdgeKind::Choice,
+            once: false,
+            max_uses: None,
+            cooldown: None,
+            call: None,
+            text: None,
+            key: None,
+        }
+    }
+
+    /// Follow the edge automatically instead of presenting it as a choice.
+    pub fn auto(mut self) -> Self {
+        self.kind = EdgeKind::Auto;
+        self
+    }
+
+    /// Close the edge once it has been traversed.
+    pub fn once(mut self) -> Self {
+        self.once = true;
+        self
+    }
+
+    /// Limit the number of times the edge can be traversed.
+    pub fn with_max_uses(mut self, max_uses: u32) -> Self {
+        self.max_uses = Some(max_uses);
+        self
+    }
+
+    /// Require a number of other traversals before the edge can be traversed
+    /// again.
+    pub fn with_cooldown(mut self, cooldown: u32) -> Self {
+        self.cooldown = Some(cooldown);
+        self
+    }
+
+    /// Run the named graph once the edge is traversed, continuing from the
+    /// node it leads to when the graph is finished.
+    pub fn with_call(mut self, graph: &str) -> Self {
+        self.call = Some(graph.to_owned());
+        self
+    }
+
+    /// Present the choice with the given text.
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_owned());
+        self
+    }
+
+    /// Look the choice's text up in string tables by the given key.
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_owned());
+        self
+    }
+
+    /// Returns the key of the choice's text in string tables, if it has
+    /// text: its key, or else the text itself.
+    pub fn text_key(&self) -> Option<&str> {
+        let text = self.text.as_deref()?;
+        Some(self.key.as_deref().unwrap_or(text))
+    }
+
+    /// Returns whether the other edge has the same kind, usage limits, call
+    /// and text, whatever its condition.
+    pub(crate) fn same_options(&self, other: &Edge<T>) -> bool {
+        self.kind == other.kind
+            && self.once == other.once
+            && self.max_uses == other.max_uses
+            && self.cooldown == other.cooldown
+            && self.call == other.call
+            && self.text == other.text
+            && self.key == other.key
+    }
+
+    /// Returns whether the edge's usage limits allow traversing it, given the
+    /// number of times it has been traversed and the number of traversals
+    /// since it was last traversed.
+    pub fn within_limits(&self, uses: u32, since_last: Option<usize>) -> bool {
+        let max_uses = if self.once {
+            Some(self.max_uses.unwrap_or(1).min(1))
+        } else {
+            self.max_uses
+        };
+        if let Some(max_uses) = max_uses {
+            if uses >= max_uses {
+                return false;
+            }
+        }
+        match (self.cooldown, since_last) {
+            (Some(cooldown), Some(since_last)) => since_last >= cooldown as usize,
+            _ => true,
+        }
+    }
+
+    /// Returns whether the edge with the given index can be traversed in the
+    /// given context, checking both its usage limits and its condition.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::{Context, True}, conversation::History, Edge};
+    /// # use petgraph::graph::EdgeIndex;
+    /// let id = EdgeIndex::new(0);
+    /// let edge = Edge::new(True::new()).once();
+    /// let mut history = History::new();
+    ///
+    /// assert!(edge.is_open(id, &Context::new().with_history(&history)));
+    ///
+    /// history.record_traversal(id);
+    /// assert!(!edge.is_open(id, &Context::new().with_history(&history)));
+    /// ```
+    pub fn is_open(&self, id: EdgeIndex, context: &Context<'_>) -> bool {
+        self.within_limits(context.traversal_count(id), context.traversals_since(id))
+            && self.condition.evaluate_with(context)
     }
 }
 
+/// The version of the content format written by this crate, which new graphs
+/// are stamped with.
+pub const FORMAT_VERSION: u32 = 1;
+
 /// A dialogue graph consisting of dialogue nodes connected by conditional
 /// edges.
 #[derive(Serialize, Debug)]
@@ -61,13 +333,26 @@ where
 {
     /// The underlying graph.
     pub data: Graph<Node, Edge<T>, Directed, u32>,
+    /// The version of the content format, used by
+    /// [`Migrations`](migration/struct.Migrations.html) to upgrade older
+    /// serialized graphs. Graphs serialized before versioning are version 0,
+    /// and new graphs are [`FORMAT_VERSION`](constant.FORMAT_VERSION.html).
+    /// Content which registers migrations of its own past that version
+    /// should stamp the graphs it creates with `Migrations::latest`.
+    ///
+    /// The version is serialized after `data`, so graphs serialized before
+    /// versioning still deserialize, except from formats which don't
+    /// describe their own layout, such as bincode, which need a `u32`
+    /// version of 0 appended to them first.
+    pub version: u32,
 }
 
 impl<T> PartialEq for DialogueGraph<T>
 where
     T: Condition + PartialEq,
 {
-    /// Compare the two graphs' nodes and edges.
+    /// Compare the two graphs' nodes and edges in the order they are stored.
+    /// See [`equivalent`](#method.equivalent) to ignore the order.
     ///
     /// Algorithm taken from [this comment on GitHub](https://github.com/petgraph/petgraph/issues/199#issuecomment-484077775).
     fn eq(&self, other: &DialogueGraph<T>) -> bool {
@@ -95,6 +380,7 @@ where
     pub fn new() -> Self {
         Self {
             data: Graph::<Node, Edge<T>>::new(),
+            version: FORMAT_VERSION,
         }
     }
 
@@ -109,11 +395,38 @@ where
         !DialogueGraph::open(edge)
     }
 
+    /// Returns the entry point of the dialogue, which is the first node
+    /// added to the graph.
+    pub fn entry(&self) -> Option<NodeIndex> {
+        self.data.node_indices().next()
+    }
+
+    /// Returns the index of the node with the given id.
+    pub fn find(&self, id: &str) -> Option<NodeIndex> {
+        self.data
+            .node_indices()
+            .find(|node| self.data[*node].id.as_deref() == Some(id))
+    }
+
     /// Returns an iterator over the open edges connected to the given node
     /// index.
     pub fn open_edges<'a, 'de>(&'a self, node: NodeIndex) -> OpenEdges<'a, T> {
+        self.open_edges_with(node, Context::new())
+    }
+
+    /// Returns an iterator over the edges connected to the given node index
+    /// which are open in the given context.
+    ///
+    /// If the context has a conversation history, edges which have used up
+    /// their traversals are closed as well.
+    pub fn open_edges_with<'a>(
+        &'a self,
+        node: NodeIndex,
+        context: Context<'a>,
+    ) -> OpenEdges<'a, T> {
         OpenEdges {
             edges: self.data.edges(node),
+            context,
         }
     }
 }
@@ -125,6 +438,7 @@ where
     T: Condition,
 {
     edges: Edges<'a, Edge<T>, Directed>,
+    context: Context<'a>,
 }
 
 impl<'a, T> Iterator for OpenEdges<'a, T>
@@ -135,7 +449,7 @@ where
     fn next(&mut self) -> Option<&'a Edge<T>> {
         let mut result = None;
         while let Some(edge) = self.edges.next() {
-            if edge.weight().condition.evaluate() {
+            if edge.weight().is_open(edge.id(), &self.context) {
                 result = Some(edge.weight());
                 break;
             }
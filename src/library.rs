@@ -0,0 +1,514 @@
+use crate::{condition::Condition, DialogueGraph, Node};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// The separator between a graph's name and a node id in a qualified
+/// reference, as in `"blacksmith::farewell"`.
+pub const SEPARATOR: &str = "::";
+
+/// Qualify an id with the graph's name, unless it is already qualified.
+fn qualify(namespace: &str, id: &str) -> String {
+    if id.contains(SEPARATOR) {
+        id.to_owned()
+    } else {
+        format!("{}{}{}", namespace, SEPARATOR, id)
+    }
+}
+
+/// A problem found while merging the graphs of a `Library`.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MergeIssue {
+    /// More than one node has the qualified id. Links to it refer to the
+    /// first.
+    DuplicateId {
+        /// The qualified id.
+        id: String,
+    },
+    /// A link refers to an id no node has. The link is kept in the merged
+    /// graph.
+    UnresolvedReference {
+        /// The index of the link in the merged graph.
+        node: NodeIndex,
+        /// The qualified id it refers to.
+        reference: String,
+    },
+    /// The edge's condition could not be rewritten for the merged graph's
+    /// indices, as parts of it are opaque or refer to nodes or edges not in
+    /// its graph, so it might refer to the wrong ones.
+    UnmappedCondition {
+        /// The index of the edge in the merged graph.
+        edge: EdgeIndex,
+    },
+}
+
+impl fmt::Display for MergeIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeIssue::DuplicateId { id } => {
+                write!(f, "more than one node has the id {:?}", id)
+            }
+            MergeIssue::UnresolvedReference { node, reference } => write!(
+                f,
+                "node {} links to {:?}, which no node has as its id",
+                node.index(),
+                reference
+            ),
+            MergeIssue::UnmappedCondition { edge } => write!(
+                f,
+                "edge {} has a condition whose nodes and edges could not be renumbered",
+                edge.index()
+            ),
+        }
+    }
+}
+
+/// The problems found while merging the graphs of a `Library`.
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+pub struct MergeReport {
+    /// The problems, duplicate ids first and conditions last.
+    pub issues: Vec<MergeIssue>,
+}
+
+impl MergeReport {
+    /// Create an empty report.
+    pub fn new() -> Self {
+        Self { issues: Vec::new() }
+    }
+
+    /// Returns whether no problems were found.
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// A collection of named `DialogueGraph` objects, which can call each other
+/// through edges with a [`call`](../struct.Edge.html#structfield.call).
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::True, conversation::Conversation, library::Library, DialogueGraph, Edge};
+/// let mut shop = DialogueGraph::new();
+/// let _ = shop.data.add_node("What'll it be?".into());
+///
+/// let mut village = DialogueGraph::new();
+/// let hello = village.data.add_node("Hello.".into());
+/// let bye = village.data.add_node("Goodbye.".into());
+/// let buy = village.data.add_edge(hello, bye, Edge::new(True::new()).with_call("shop"));
+///
+/// let mut library = Library::new();
+/// library.insert("shop", shop);
+///
+/// let mut conversation = Conversation::new(&village, hello).with_library(&library);
+/// conversation.choose(buy).unwrap();
+/// assert_eq!(conversation.node().text, "What'll it be?");
+///
+/// conversation.advance().unwrap();
+/// assert_eq!(conversation.current(), bye);
+/// ```
+#[derive(Serialize, Debug)]
+#[serde(transparent)]
+pub struct Library<T>
+where
+    T: Condition,
+{
+    pub(crate) graphs: BTreeMap<String, DialogueGraph<T>>,
+}
+
+impl<T> Library<T>
+where
+    T: Condition,
+{
+    /// Create an empty library.
+    pub fn new() -> Self {
+        Self {
+            graphs: BTreeMap::new(),
+        }
+    }
+
+    /// Add a graph under the given name, returning the graph it replaces.
+    pub fn insert(&mut self, name: &str, graph: DialogueGraph<T>) -> Option<DialogueGraph<T>> {
+        self.graphs.insert(name.to_owned(), graph)
+    }
+
+    /// Remove the graph with the given name, returning it.
+    pub fn remove(&mut self, name: &str) -> Option<DialogueGraph<T>> {
+        self.graphs.remove(name)
+    }
+
+    /// Returns the graph with the given name.
+    pub fn get(&self, name: &str) -> Option<&DialogueGraph<T>> {
+        self.graphs.get(name)
+    }
+
+    /// Returns an iterator over the graphs and their names, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &DialogueGraph<T>)> {
+        self.graphs
+            .iter()
+            .map(|(name, graph)| (name.as_str(), graph))
+    }
+
+    /// Merge the library's graphs into one, using each graph's name as the
+    /// namespace of its node ids.
+    ///
+    /// Ids and links without a namespace are qualified with the name of the
+    /// graph they are in, so `"farewell"` in the graph named `"blacksmith"`
+    /// becomes `"blacksmith::farewell"`. Links are then resolved: edges
+    /// leading to or from a link are moved to the node it refers to, and the
+    /// link is removed.
+    ///
+    /// Graphs are added in order of name, so the merged graph's entry node is
+    /// the entry node of the first graph by name; see
+    /// [`merge_with_entry`](#method.merge_with_entry) to choose it. Calls
+    /// between graphs are kept as they are, and conditions are rewritten to
+    /// refer to the merged graph's nodes and edges.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::True, library::Library, DialogueGraph, Edge, Node};
+    /// let mut blacksmith = DialogueGraph::new();
+    /// blacksmith.data.add_node(Node::new("Safe travels.").with_id("farewell"));
+    ///
+    /// let mut village = DialogueGraph::new();
+    /// let hello = village.data.add_node(Node::new("Hello.").with_id("hello"));
+    /// let link = village.data.add_node(Node::link("blacksmith::farewell"));
+    /// village.data.add_edge(hello, link, Edge::new(True::new()));
+    ///
+    /// let mut library = Library::new();
+    /// library.insert("blacksmith", blacksmith);
+    /// library.insert("village", village);
+    /// let (merged, report) = library.merge();
+    ///
+    /// assert!(report.is_empty());
+    /// let farewell = merged.find("blacksmith::farewell").unwrap();
+    /// let hello = merged.find("village::hello").unwrap();
+    /// assert!(merged.data.contains_edge(hello, farewell));
+    /// ```
+    pub fn merge(self) -> (DialogueGraph<T>, MergeReport) {
+        merge(self.graphs.into_iter().collect())
+    }
+
+    /// Merge the library's graphs into one as [`merge`](#method.merge) does,
+    /// adding the graph with the given name first so that its entry node is
+    /// the merged graph's entry node. The other graphs follow in order of
+    /// name, as they all do if no graph has the name.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::True, library::Library, DialogueGraph, Node};
+    /// let mut village = DialogueGraph::<True>::new();
+    /// village.data.add_node(Node::new("Hello.").with_id("hello"));
+    /// let mut blacksmith = DialogueGraph::new();
+    /// blacksmith.data.add_node(Node::new("Safe travels.").with_id("farewell"));
+    ///
+    /// let mut library = Library::new();
+    /// library.insert("blacksmith", blacksmith);
+    /// library.insert("village", village);
+    /// let (merged, _) = library.merge_with_entry("village");
+    ///
+    /// assert_eq!(merged.entry(), merged.find("village::hello"));
+    /// ```
+    pub fn merge_with_entry(self, entry: &str) -> (DialogueGraph<T>, MergeReport) {
+        let mut graphs: Vec<(String, DialogueGraph<T>)> = self.graphs.into_iter().collect();
+        if let Some(position) = graphs.iter().position(|(name, _)| name == entry) {
+            let graph = graphs.remove(position);
+            graphs.insert(0, graph);
+        }
+        merge(graphs)
+    }
+}
+
+/// Merge named graphs into one in the order given.
+fn merge<T>(graphs: Vec<(String, DialogueGraph<T>)>) -> (DialogueGraph<T>, MergeReport)
+where
+    T: Condition,
+{
+    let mut report = MergeReport::new();
+    let mut version = 0;
+    let mut nodes: Vec<Node> = Vec::new();
+    let mut edges = Vec::new();
+    // The first node and edge of each graph and how many it has.
+    let mut ranges = Vec::new();
+    for (namespace, graph) in graphs {
+        version = version.max(graph.version);
+        let offset = nodes.len();
+        ranges.push((
+            offset,
+            graph.data.node_count(),
+            edges.len(),
+            graph.data.edge_count(),
+        ));
+        let (graph_nodes, graph_edges) = graph.data.into_nodes_edges();
+        for node in graph_nodes {
+            let mut node = node.weight;
+            node.id = node.id.map(|id| qualify(&namespace, &id));
+            node.link = node.link.map(|link| qualify(&namespace, &link));
+            nodes.push(node);
+        }
+        for edge in graph_edges {
+            edges.push((
+                ranges.len() - 1,
+                offset + edge.source().index(),
+                offset + edge.target().index(),
+                edge.weight,
+            ));
+        }
+    }
+
+    let mut ids: BTreeMap<String, usize> = BTreeMap::new();
+    let mut duplicates = BTreeSet::new();
+    for (index, node) in nodes.iter().enumerate() {
+        if let Some(id) = &node.id {
+            if ids.contains_key(id) {
+                duplicates.insert(id.clone());
+            } else {
+                ids.insert(id.clone(), index);
+            }
+        }
+    }
+    for id in duplicates {
+        report.issues.push(MergeIssue::DuplicateId { id });
+    }
+
+    // Follow each link to the node it refers to, giving up on links
+    // which are missing or lead back to themselves.
+    let resolved: Vec<Option<usize>> = (0..nodes.len())
+        .map(|start| {
+            let mut current = start;
+            for _ in 0..nodes.len() {
+                match &nodes[current].link {
+                    None => return Some(current),
+                    Some(reference) => current = *ids.get(reference)?,
+                }
+            }
+            None
+        })
+        .collect();
+
+    let mut merged = DialogueGraph::new();
+    merged.version = version;
+    let mut indices = vec![NodeIndex::end(); nodes.len()];
+    let mut unresolved = Vec::new();
+    for (index, node) in nodes.into_iter().enumerate() {
+        let is_link = node.link.is_some();
+        if is_link && resolved[index].is_some() {
+            continue;
+        }
+        if is_link {
+            unresolved.push((index, node.link.clone().unwrap_or_default()));
+        }
+        indices[index] = merged.data.add_node(node);
+    }
+    for (index, target) in resolved.iter().enumerate() {
+        if let Some(target) = target {
+            indices[index] = indices[*target];
+        }
+    }
+    for (index, reference) in unresolved {
+        report.issues.push(MergeIssue::UnresolvedReference {
+            node: indices[index],
+            reference,
+        });
+    }
+
+    for (graph, source, target, mut edge) in edges {
+        let (node_offset, node_count, edge_offset, edge_count) = ranges[graph];
+        let renumber_node = |node: NodeIndex| {
+            Some(node)
+                .filter(|node| node.index() < node_count)
+                .map(|node| indices[node_offset + node.index()])
+        };
+        let renumber_edge = |edge: EdgeIndex| {
+            Some(edge)
+                .filter(|edge| edge.index() < edge_count)
+                .map(|edge| EdgeIndex::new(edge_offset + edge.index()))
+        };
+        let mapped = edge.condition.renumber(&renumber_node, &renumber_edge);
+        let edge = merged.data.add_edge(indices[source], indices[target], edge);
+        if !mapped {
+            report.issues.push(MergeIssue::UnmappedCondition { edge });
+        }
+    }
+    (merged, report)
+}
+
+impl<T> Default for Library<T>
+where
+    T: Condition,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> PartialEq for Library<T>
+where
+    T: Condition + PartialEq,
+{
+    fn eq(&self, other: &Library<T>) -> bool {
+        self.graphs == other.graphs
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        condition::{dynamic::DynCondition, True},
+        Edge,
+    };
+    use serde::Deserialize;
+
+    #[test]
+    fn test_merge_qualifies_ids() {
+        let mut shop = DialogueGraph::new();
+        let welcome = shop.data.add_node(Node::new("Welcome.").with_id("welcome"));
+        let back = shop.data.add_node(Node::link("welcome"));
+        let _ = shop.data.add_edge(welcome, back, Edge::new(True::new()));
+
+        let mut library = Library::new();
+        let _ = library.insert("shop", shop);
+        let (merged, report) = library.merge();
+
+        assert!(report.is_empty());
+        assert_eq!(merged.data.node_count(), 1);
+        let welcome = merged.find("shop::welcome").unwrap();
+        assert!(merged.data.contains_edge(welcome, welcome));
+    }
+
+    #[test]
+    fn test_merge_reports_issues() {
+        let mut a = DialogueGraph::<True>::new();
+        let _ = a.data.add_node(Node::new("A").with_id("b::same"));
+        let _ = a.data.add_node(Node::link("b::missing"));
+        let mut b = DialogueGraph::new();
+        let _ = b.data.add_node(Node::new("B").with_id("same"));
+        let _ = b.data.add_node(Node::link("loop"));
+        let _ = b.data.add_node(Node::link("loop").with_id("loop"));
+
+        let mut library = Library::new();
+        let _ = library.insert("x", a);
+        let _ = library.insert("b", b);
+        let (merged, report) = library.merge();
+
+        assert_eq!(
+            report.issues,
+            vec![
+                MergeIssue::DuplicateId {
+                    id: "b::same".to_owned()
+                },
+                MergeIssue::UnresolvedReference {
+                    node: NodeIndex::new(1),
+                    reference: "b::loop".to_owned()
+                },
+                MergeIssue::UnresolvedReference {
+                    node: NodeIndex::new(2),
+                    reference: "b::loop".to_owned()
+                },
+                MergeIssue::UnresolvedReference {
+                    node: NodeIndex::new(4),
+                    reference: "b::missing".to_owned()
+                },
+            ]
+        );
+        assert_eq!(merged.data.node_count(), 5);
+        assert_eq!(merged.data[NodeIndex::new(4)], Node::link("b::missing"));
+    }
+
+    #[test]
+    fn test_merge_renumbers_conditions() {
+        let mut inn = DialogueGraph::new();
+        let welcome = inn.data.add_node(Node::new("Welcome.").with_id("welcome"));
+        let bar = inn.data.add_node("Drinks?".into());
+        let _ = inn
+            .data
+            .add_edge(welcome, bar, Edge::new(DynCondition::True));
+
+        let mut village = DialogueGraph::new();
+        let square = village.data.add_node("Square".into());
+        let gate = village.data.add_node("Gate".into());
+        let link = village.data.add_node(Node::link("inn::welcome"));
+        let to_gate = village.data.add_edge(
+            square,
+            gate,
+            Edge::new(DynCondition::Visited { node: gate }),
+        );
+        let _ = village.data.add_edge(
+            square,
+            link,
+            Edge::new(DynCondition::and(
+                DynCondition::EdgeTaken { edge: to_gate },
+                DynCondition::VisitCount {
+                    node: link,
+                    count: 2,
+                },
+            )),
+        );
+        let _ = village
+            .data
+            .add_edge(gate, square, Edge::new(DynCondition::opaque("weather")));
+
+        let mut library = Library::new();
+        let _ = library.insert("inn", inn);
+        let _ = library.insert("village", village);
+        let (merged, report) = library.merge();
+
+        let conditions: Vec<&DynCondition> = merged
+            .data
+            .raw_edges()
+            .iter()
+            .map(|edge| &edge.weight.condition)
+            .collect();
+        assert_eq!(
+            conditions[1],
+            &DynCondition::Visited {
+                node: NodeIndex::new(3)
+            }
+        );
+        assert_eq!(
+            conditions[2],
+            &DynCondition::and(
+                DynCondition::EdgeTaken {
+                    edge: EdgeIndex::new(1)
+                },
+                DynCondition::VisitCount {
+                    node: NodeIndex::new(0),
+                    count: 2
+                },
+            )
+        );
+        assert_eq!(
+            report.issues,
+            vec![MergeIssue::UnmappedCondition {
+                edge: EdgeIndex::new(3)
+            }]
+        );
+    }
+
+    #[test]
+    fn test_merge_custom_conditions() {
+        #[derive(Serialize, Deserialize)]
+        struct Raining;
+
+        impl Condition for Raining {
+            fn evaluate(&self) -> bool {
+                false
+            }
+        }
+
+        let mut village = DialogueGraph::new();
+        let square = village.data.add_node("Square".into());
+        let gate = village.data.add_node("Gate".into());
+        let _ = village.data.add_edge(square, gate, Edge::new(Raining));
+        let mut library = Library::new();
+        let _ = library.insert("village", village);
+
+        let (merged, report) = library.merge();
+        assert_eq!(merged.data.edge_count(), 1);
+        assert!(report.issues.is_empty());
+    }
+}
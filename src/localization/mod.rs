@@ -0,0 +1,448 @@
+use crate::{condition::Condition, DialogueGraph, Edge, Node};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error, fmt};
+
+/// Gettext PO files
+pub mod po;
+/// XLIFF 1.2 files
+pub mod xliff;
+
+/// The translations of dialogue text into one locale, by key.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::localization::StringTable;
+/// let table: StringTable = serde_json::from_str(r#"{
+///     "locale": "pt",
+///     "strings": { "greeting": "Olá." }
+/// }"#).unwrap();
+///
+/// assert_eq!(table.get("greeting"), Some("Olá."));
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StringTable {
+    /// The locale, such as `"pt-BR"`.
+    pub locale: String,
+    strings: BTreeMap<String, String>,
+}
+
+impl StringTable {
+    /// Create an empty table for the given locale.
+    pub fn new(locale: &str) -> Self {
+        Self {
+            locale: locale.to_owned(),
+            strings: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the text with the given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+
+    /// Set the text with the given key, returning its previous text.
+    pub fn insert(&mut self, key: &str, text: &str) -> Option<String> {
+        self.strings.insert(key.to_owned(), text.to_owned())
+    }
+
+    /// Remove the text with the given key, returning it.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.strings.remove(key)
+    }
+
+    /// Returns the number of strings in the table.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns whether the table has no strings.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Returns an iterator over the keys and their text, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.strings
+            .iter()
+            .map(|(key, text)| (key.as_str(), text.as_str()))
+    }
+}
+
+/// Returns the locales to look text up in for the given locale, from the most
+/// to the least specific, ending with the default locale.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::localization::fallback_chain;
+/// assert_eq!(fallback_chain("pt-BR", "en"), vec!["pt-BR", "pt", "en"]);
+/// assert_eq!(fallback_chain("en-GB", "en"), vec!["en-GB", "en"]);
+/// ```
+pub fn fallback_chain(locale: &str, default_locale: &str) -> Vec<String> {
+    let mut chain = Vec::new();
+    let mut locale = locale;
+    while !locale.is_empty() {
+        chain.push(locale.to_owned());
+        locale = match locale.rfind(['-', '_']) {
+            Some(end) => &locale[..end],
+            None => "",
+        };
+    }
+    if !chain.iter().any(|locale| locale == default_locale) {
+        chain.push(default_locale.to_owned());
+    }
+    chain
+}
+
+/// String tables for several locales, with one of them active.
+///
+/// Text missing from the active locale's table is looked up along its
+/// [fallback chain](fn.fallback_chain.html), and then taken from the graph
+/// itself.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{localization::{Localization, StringTable}, Node};
+/// let mut pt = StringTable::new("pt");
+/// pt.insert("greeting", "Olá.");
+/// let mut localization = Localization::new("en");
+/// localization.insert(pt);
+/// localization.set_locale("pt-BR");
+///
+/// let node = Node::new("Hello.").with_id("greeting");
+/// assert_eq!(localization.node_text(&node), "Olá.");
+///
+/// let untranslated = Node::new("Goodbye.");
+/// assert_eq!(localization.node_text(&untranslated), "Goodbye.");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Localization {
+    tables: BTreeMap<String, StringTable>,
+    default_locale: String,
+    locale: String,
+}
+
+impl Localization {
+    /// Create a localization without string tables, whose default locale is
+    /// active.
+    pub fn new(default_locale: &str) -> Self {
+        Self {
+            tables: BTreeMap::new(),
+            default_locale: default_locale.to_owned(),
+            locale: default_locale.to_owned(),
+        }
+    }
+
+    /// Add a string table, returning the table for the same locale it
+    /// replaces.
+    pub fn insert(&mut self, table: StringTable) -> Option<StringTable> {
+        self.tables.insert(table.locale.clone(), table)
+    }
+
+    /// Returns the string table for the given locale.
+    pub fn table(&self, locale: &str) -> Option<&StringTable> {
+        self.tables.get(locale)
+    }
+
+    /// Returns the default locale.
+    pub fn default_locale(&self) -> &str {
+        &self.default_locale
+    }
+
+    /// Returns the active locale.
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Make the given locale active.
+    pub fn set_locale(&mut self, locale: &str) {
+        self.locale = locale.to_owned();
+    }
+
+    /// Returns the text with the given key in the active locale, following
+    /// its fallback chain.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        fallback_chain(&self.locale, &self.default_locale)
+            .iter()
+            .filter_map(|locale| self.tables.get(locale))
+            .find_map(|table| table.get(key))
+    }
+
+    /// Returns the node's text in the active locale.
+    pub fn node_text<'a>(&'a self, node: &'a Node) -> &'a str {
+        self.get(node.text_key()).unwrap_or(&node.text)
+    }
+
+    /// Returns the choice's text in the active locale, if it has text.
+    pub fn choice_text<'a, T>(&'a self, edge: &'a Edge<T>) -> Option<&'a str>
+    where
+        T: Condition,
+    {
+        let key = edge.text_key()?;
+        self.get(key).or(edge.text.as_deref())
+    }
+}
+
+/// Where a translatable string comes from.
+#[derive(Copy, Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", content = "index", rename_all = "snake_case")]
+pub enum Source {
+    /// The text of a node.
+    Node(NodeIndex),
+    /// The text of a choice.
+    Choice(EdgeIndex),
+}
+
+/// A translatable string of a `DialogueGraph`.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct Entry {
+    /// The key of the string in string tables.
+    pub key: String,
+    /// The string in the source language.
+    pub text: String,
+    /// The speaker of the node's text. Choices have no speaker.
+    pub speaker: Option<String>,
+    /// The id of the node, or for a choice the id of the node it leads from,
+    /// if it has one.
+    pub node: Option<String>,
+    /// Where the string comes from.
+    pub source: Source,
+}
+
+/// The strings of a `DialogueGraph` which share a key, as written to
+/// translation files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Message {
+    pub(crate) key: String,
+    pub(crate) text: String,
+    pub(crate) speakers: Vec<String>,
+    pub(crate) nodes: Vec<String>,
+    pub(crate) sources: Vec<Source>,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Node(node) => write!(f, "node:{}", node.index()),
+            Source::Choice(edge) => write!(f, "choice:{}", edge.index()),
+        }
+    }
+}
+
+/// An error raised when a translation file cannot be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImportError {
+    /// A PO file is malformed or uses a feature which is not supported.
+    Syntax {
+        /// The line of the problem, counting from 1.
+        line: usize,
+        /// What is wrong.
+        message: String,
+    },
+    /// An XLIFF file is malformed.
+    Xml {
+        /// The byte offset of the problem.
+        position: u64,
+        /// What is wrong.
+        message: String,
+    },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            ImportError::Xml { position, message } => {
+                write!(f, "byte {}: {}", position, message)
+            }
+        }
+    }
+}
+
+impl Error for ImportError {}
+
+/// The result of importing a translation file into a string table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportReport {
+    /// The translations of the graph's current strings.
+    pub table: StringTable,
+    /// The keys of the graph's strings which have no translation, ordered by
+    /// key.
+    pub missing: Vec<String>,
+    /// The keys of translations which were left out of the table because the
+    /// graph no longer has the key or its source text has changed, in the
+    /// order of the file.
+    pub stale: Vec<String>,
+}
+
+impl ImportReport {
+    /// Build a report from the translations read from a file, as keys with
+    /// their source text and translated text. Empty translations are
+    /// treated as missing.
+    pub(crate) fn new<T, I>(graph: &DialogueGraph<T>, locale: &str, translations: I) -> Self
+    where
+        T: Condition,
+        I: IntoIterator<Item = (String, String, String)>,
+    {
+        let sources = graph.string_table(locale);
+        let mut table = StringTable::new(locale);
+        let mut stale = Vec::new();
+        for (key, source, translation) in translations {
+            if sources.get(&key) != Some(source.as_str()) {
+                stale.push(key);
+            } else if !translation.is_empty() {
+                let _ = table.insert(&key, &translation);
+            }
+        }
+        let missing = sources
+            .iter()
+            .filter(|(key, _)| table.get(key).is_none())
+            .map(|(key, _)| key.to_owned())
+            .collect();
+        Self {
+            table,
+            missing,
+            stale,
+        }
+    }
+
+    /// Returns whether every string has a translation and none are stale.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.stale.is_empty()
+    }
+}
+
+impl<T> DialogueGraph<T>
+where
+    T: Condition,
+{
+    /// Returns the translatable strings of the graph: the text of every node
+    /// which is not a link and of every choice with text, in order of index.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::True, DialogueGraph, Edge, Node};
+    /// let mut graph = DialogueGraph::new();
+    /// let hello = graph.data.add_node(Node::new("Hello.").with_id("hello"));
+    /// let bye = graph.data.add_node("Goodbye.".into());
+    /// graph.data.add_edge(hello, bye, Edge::new(True::new()).with_text("Leave"));
+    ///
+    /// let keys: Vec<_> = graph.strings().into_iter().map(|entry| entry.key).collect();
+    /// assert_eq!(keys, vec!["hello", "Goodbye.", "Leave"]);
+    /// ```
+    pub fn strings(&self) -> Vec<Entry> {
+        let nodes = self.data.node_indices().filter_map(|index| {
+            let node = &self.data[index];
+            if node.link.is_some() {
+                return None;
+            }
+            Some(Entry {
+                key: node.text_key().to_owned(),
+                text: node.text.clone(),
+                speaker: node.speaker.clone(),
+                node: node.id.clone(),
+                source: Source::Node(index),
+            })
+        });
+        let choices = self.data.edge_indices().filter_map(|index| {
+            let edge = &self.data[index];
+            let (from, _) = self.data.edge_endpoints(index)?;
+            Some(Entry {
+                key: edge.text_key()?.to_owned(),
+                text: edge.text.clone()?,
+                speaker: None,
+                node: self.data[from].id.clone(),
+                source: Source::Choice(index),
+            })
+        });
+        nodes.chain(choices).collect()
+    }
+
+    /// Returns a string table for the given locale with the graph's
+    /// translatable strings, for use as the source of translations.
+    ///
+    /// Where strings share a key, the first is kept.
+    pub fn string_table(&self, locale: &str) -> StringTable {
+        let mut table = StringTable::new(locale);
+        for entry in self.strings() {
+            table.strings.entry(entry.key).or_insert(entry.text);
+        }
+        table
+    }
+
+    /// Returns the graph's translatable strings grouped by key, in order of
+    /// each key's first string, keeping the text of the first.
+    pub(crate) fn messages(&self) -> Vec<Message> {
+        let mut messages: Vec<Message> = Vec::new();
+        let mut positions = BTreeMap::new();
+        for entry in self.strings() {
+            let position = *positions.entry(entry.key.clone()).or_insert_with(|| {
+                messages.push(Message {
+                    key: entry.key.clone(),
+                    text: entry.text.clone(),
+                    speakers: Vec::new(),
+                    nodes: Vec::new(),
+                    sources: Vec::new(),
+                });
+                messages.len() - 1
+            });
+            let message = &mut messages[position];
+            if let Some(speaker) = entry.speaker {
+                if !message.speakers.contains(&speaker) {
+                    message.speakers.push(speaker);
+                }
+            }
+            if let Some(node) = entry.node {
+                if !message.nodes.contains(&node) {
+                    message.nodes.push(node);
+                }
+            }
+            message.sources.push(entry.source);
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::condition::True;
+
+    #[test]
+    fn test_fallback_chain() {
+        assert_eq!(fallback_chain("en", "en"), vec!["en"]);
+        assert_eq!(
+            fallback_chain("zh_Hant_TW", "en"),
+            vec!["zh_Hant_TW", "zh_Hant", "zh", "en"]
+        );
+    }
+
+    #[test]
+    fn test_choice_text() {
+        let edge = Edge::new(True::new()).with_text("Leave").with_key("leave");
+        let mut en = StringTable::new("en");
+        let _ = en.insert("leave", "Go away");
+        let mut pt = StringTable::new("pt");
+        let _ = pt.insert("other", "Outro");
+
+        let mut localization = Localization::new("en");
+        let _ = localization.insert(en);
+        let _ = localization.insert(pt);
+        localization.set_locale("pt");
+
+        assert_eq!(localization.choice_text(&edge), Some("Go away"));
+        assert_eq!(localization.choice_text(&Edge::new(True::new())), None);
+    }
+
+    #[test]
+    fn test_string_table() {
+        let mut graph = DialogueGraph::<True>::new();
+        let _ = graph.data.add_node("Yes.".into());
+        let _ = graph.data.add_node("Yes.".into());
+        let _ = graph.data.add_node(Node::link("elsewhere"));
+
+        let table = graph.string_table("en");
+
+        assert_eq!(table.iter().collect::<Vec<_>>(), vec![("Yes.", "Yes.")]);
+    }
+}
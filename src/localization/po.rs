@@ -0,0 +1,338 @@
+use super::{ImportError, ImportReport, StringTable};
+use crate::{condition::Condition, DialogueGraph};
+use std::fmt::Write;
+
+/// Quote a string, escaping it as in C.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Write a keyword and its string, splitting the string after each newline
+/// but the last.
+fn write_string(po: &mut String, keyword: &str, text: &str) {
+    let lines: Vec<_> = text.split_inclusive('\n').collect();
+    if lines.len() > 1 {
+        let _ = writeln!(po, "{} \"\"", keyword);
+        for line in lines {
+            let _ = writeln!(po, "{}", quote(line));
+        }
+    } else {
+        let _ = writeln!(po, "{} {}", keyword, quote(text));
+    }
+}
+
+/// Write the strings of a graph as a gettext PO file, with the translations
+/// in the given table.
+///
+/// Each key is written once, as the message's context, so message ids stay
+/// stable when the source text is edited. Comments give the speakers and ids
+/// of the nodes the string belongs to, and references give the index of each
+/// node or choice. Strings the table has no translation for are left empty,
+/// so an empty table gives a template.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::True, localization::{po, StringTable}, DialogueGraph, Node};
+/// let mut graph = DialogueGraph::<True>::new();
+/// graph.data.add_node(Node::new("Halt!").with_id("halt").with_speaker("Guard"));
+/// let mut pt = StringTable::new("pt");
+/// pt.insert("halt", "Alto!");
+///
+/// let file = po::export(&graph, &pt);
+/// assert!(file.ends_with(
+///     "#. speaker: Guard\n#. node: halt\n#: node:0\nmsgctxt \"halt\"\nmsgid \"Halt!\"\nmsgstr \"Alto!\"\n"
+/// ));
+/// ```
+pub fn export<T>(graph: &DialogueGraph<T>, translations: &StringTable) -> String
+where
+    T: Condition,
+{
+    let mut po = String::new();
+    let _ = writeln!(po, "msgid \"\"");
+    let _ = writeln!(po, "msgstr \"\"");
+    let _ = writeln!(
+        po,
+        "{}",
+        quote(&format!("Language: {}\n", translations.locale))
+    );
+    let _ = writeln!(po, "{}", quote("MIME-Version: 1.0\n"));
+    let _ = writeln!(po, "{}", quote("Content-Type: text/plain; charset=UTF-8\n"));
+    let _ = writeln!(po, "{}", quote("Content-Transfer-Encoding: 8bit\n"));
+    for message in graph.messages() {
+        let _ = writeln!(po);
+        for speaker in &message.speakers {
+            let _ = writeln!(po, "#. speaker: {}", speaker);
+        }
+        for node in &message.nodes {
+            let _ = writeln!(po, "#. node: {}", node);
+        }
+        let sources: Vec<_> = message.sources.iter().map(|s| s.to_string()).collect();
+        let _ = writeln!(po, "#: {}", sources.join(" "));
+        write_string(&mut po, "msgctxt", &message.key);
+        write_string(&mut po, "msgid", &message.text);
+        write_string(
+            &mut po,
+            "msgstr",
+            translations.get(&message.key).unwrap_or_default(),
+        );
+    }
+    po
+}
+
+/// The part of a PO entry a string belongs to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Field {
+    Context,
+    Id,
+    String,
+}
+
+/// A PO entry being read.
+#[derive(Clone, Debug, Default)]
+struct PoEntry {
+    context: Option<String>,
+    id: Option<String>,
+    string: Option<String>,
+    fuzzy: bool,
+    obsolete: bool,
+}
+
+impl PoEntry {
+    /// Returns the entry as a key with its source and translated text, unless
+    /// it is the header or obsolete.
+    fn into_translation(self) -> Option<(String, String, String)> {
+        let id = self.id?;
+        if self.obsolete || (id.is_empty() && self.context.is_none()) {
+            return None;
+        }
+        let string = if self.fuzzy {
+            String::new()
+        } else {
+            self.string.unwrap_or_default()
+        };
+        Some((self.context.unwrap_or_else(|| id.clone()), id, string))
+    }
+}
+
+/// Read a quoted string, undoing its escapes.
+fn unquote(text: &str, line: usize) -> Result<String, ImportError> {
+    let syntax = |message: &str| ImportError::Syntax {
+        line,
+        message: message.to_owned(),
+    };
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .filter(|_| text.len() >= 2)
+        .ok_or_else(|| syntax("expected a quoted string"))?;
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unquoted.push('\\'),
+            Some('"') => unquoted.push('"'),
+            Some('n') => unquoted.push('\n'),
+            Some('t') => unquoted.push('\t'),
+            Some('r') => unquoted.push('\r'),
+            _ => return Err(syntax("unsupported escape sequence")),
+        }
+    }
+    Ok(unquoted)
+}
+
+/// Read the translations in a gettext PO file into a string table for the
+/// given locale, checking them against the graph's strings.
+///
+/// An entry's context is its key; entries without one use their message id,
+/// as do strings without a key or id in the graph. Fuzzy entries count as
+/// untranslated, and obsolete entries are ignored. Plural forms are not
+/// supported.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::True, localization::po, DialogueGraph, Node};
+/// let mut graph = DialogueGraph::<True>::new();
+/// graph.data.add_node(Node::new("Halt!").with_id("halt"));
+/// graph.data.add_node("Who goes there?".into());
+///
+/// let file = r#"
+/// msgctxt "halt"
+/// msgid "Halt!"
+/// msgstr "Alto!"
+///
+/// msgctxt "gone"
+/// msgid "Removed."
+/// msgstr "Removido."
+/// "#;
+/// let report = po::import(&graph, "pt", file).unwrap();
+///
+/// assert_eq!(report.table.get("halt"), Some("Alto!"));
+/// assert_eq!(report.missing, vec!["Who goes there?"]);
+/// assert_eq!(report.stale, vec!["gone"]);
+/// ```
+pub fn import<T>(
+    graph: &DialogueGraph<T>,
+    locale: &str,
+    input: &str,
+) -> Result<ImportReport, ImportError>
+where
+    T: Condition,
+{
+    let mut translations = Vec::new();
+    let mut entry = PoEntry::default();
+    let mut field = None;
+    for (index, line) in input.lines().enumerate() {
+        let number = index + 1;
+        let syntax = |message: &str| ImportError::Syntax {
+            line: number,
+            message: message.to_owned(),
+        };
+        let mut line = line.trim();
+        let obsolete = match line.strip_prefix("#~") {
+            Some(rest) => {
+                line = rest.trim_start();
+                true
+            }
+            None => false,
+        };
+        // Anything but a continuation after a translation ends the entry.
+        if field == Some(Field::String) && !line.starts_with('"') {
+            translations.extend(std::mem::take(&mut entry).into_translation());
+            field = None;
+        }
+        entry.obsolete |= obsolete;
+        if let Some(flags) = line.strip_prefix("#,") {
+            if flags.split(',').any(|flag| flag.trim() == "fuzzy") {
+                entry.fuzzy = true;
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('"') {
+            let text = unquote(line, number)?;
+            let target = match field {
+                Some(Field::Context) => &mut entry.context,
+                Some(Field::Id) => &mut entry.id,
+                Some(Field::String) => &mut entry.string,
+                None => return Err(syntax("string outside of an entry")),
+            };
+            target.get_or_insert_with(String::new).push_str(&text);
+            continue;
+        }
+
+        let (keyword, rest) = match line.find(char::is_whitespace) {
+            Some(end) => (&line[..end], line[end..].trim_start()),
+            None => (line, ""),
+        };
+        let next = match keyword {
+            "msgctxt" => Field::Context,
+            "msgid" => Field::Id,
+            "msgstr" => Field::String,
+            "msgid_plural" => return Err(syntax("plural forms are not supported")),
+            _ if keyword.starts_with("msgstr[") => {
+                return Err(syntax("plural forms are not supported"))
+            }
+            _ => return Err(syntax(&format!("unknown keyword {:?}", keyword))),
+        };
+        if next == Field::String && entry.id.is_none() {
+            return Err(syntax("msgstr without msgid"));
+        }
+        let target = match next {
+            Field::Context => &mut entry.context,
+            Field::Id => &mut entry.id,
+            Field::String => &mut entry.string,
+        };
+        if target.is_some() {
+            return Err(syntax(&format!("duplicate {}", keyword)));
+        }
+        *target = Some(unquote(rest, number)?);
+        field = Some(next);
+    }
+    translations.extend(entry.into_translation());
+    Ok(ImportReport::new(graph, locale, translations))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{condition::True, Edge, Node};
+
+    fn graph() -> DialogueGraph<True> {
+        let mut graph = DialogueGraph::new();
+        let halt = graph
+            .data
+            .add_node(Node::new("Halt!\nWho goes there?").with_id("halt"));
+        let friend = graph.data.add_node(Node::new("Pass, \"friend\"."));
+        let _ = graph
+            .data
+            .add_edge(halt, friend, Edge::new(True::new()).with_text("A friend"));
+        graph
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let graph = graph();
+        let mut pt = StringTable::new("pt");
+        let _ = pt.insert("halt", "Alto!\nQuem vem lá?");
+        let _ = pt.insert("Pass, \"friend\".", "Passe, \"amigo\".");
+
+        let file = export(&graph, &pt);
+        let report = import(&graph, "pt", &file).unwrap();
+
+        assert_eq!(report.table, pt);
+        assert_eq!(report.missing, vec!["A friend"]);
+        assert!(report.stale.is_empty());
+        assert!(file.contains("#. node: halt\n#: choice:0\nmsgctxt \"A friend\"\n"));
+    }
+
+    #[test]
+    fn test_import_flags_and_errors() {
+        let graph = graph();
+        let file = "#, fuzzy\n\
+                    msgctxt \"halt\"\n\
+                    msgid \"Halt!\\n\"\n\
+                    \"Who goes there?\"\n\
+                    msgstr \"Alto!\"\n\
+                    \n\
+                    #~ msgctxt \"old\"\n\
+                    #~ msgid \"Old\"\n\
+                    #~ msgstr \"Velho\"\n\
+                    msgctxt \"A friend\"\n\
+                    msgid \"A stranger\"\n\
+                    msgstr \"Um estranho\"\n";
+        let report = import(&graph, "pt", file).unwrap();
+
+        assert!(report.table.is_empty());
+        assert_eq!(report.stale, vec!["A friend"]);
+        assert_eq!(report.missing.len(), 3);
+
+        let plural = "msgid \"day\"\nmsgid_plural \"days\"\n";
+        assert_eq!(
+            import(&graph, "pt", plural),
+            Err(ImportError::Syntax {
+                line: 2,
+                message: "plural forms are not supported".to_owned()
+            })
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use super::{ImportError, ImportReport, StringTable};
+use crate::{condition::Condition, DialogueGraph};
+use quick_xml::{
+    escape::{escape, resolve_xml_entity},
+    events::Event,
+    Reader,
+};
+use std::fmt::Write;
+
+/// Write the strings of a graph as an XLIFF 1.2 file translating from the
+/// source locale into the locale of the given table.
+///
+/// Each key is written once, as the id of a translation unit, so ids stay
+/// stable when the source text is edited. Notes give the speakers and ids of
+/// the nodes the string belongs to, and the index of each node or choice.
+/// Strings the table has no translation for have no target.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::True, localization::{xliff, StringTable}, DialogueGraph, Node};
+/// let mut graph = DialogueGraph::<True>::new();
+/// graph.data.add_node(Node::new("Halt!").with_id("halt").with_speaker("Guard"));
+/// let mut pt = StringTable::new("pt");
+/// pt.insert("halt", "Alto!");
+///
+/// let file = xliff::export(&graph, "en", &pt);
+/// assert!(file.contains(r#"<trans-unit id="halt">"#));
+/// assert!(file.contains("<target>Alto!</target>"));
+/// assert!(file.contains(r#"<note from="speaker">Guard</note>"#));
+/// ```
+pub fn export<T>(
+    graph: &DialogueGraph<T>,
+    source_locale: &str,
+    translations: &StringTable,
+) -> String
+where
+    T: Condition,
+{
+    let mut xliff = String::new();
+    let _ = writeln!(xliff, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        xliff,
+        r#"<xliff version="1.2" xmlns="urn:oasis:names:tc:xliff:document:1.2">"#
+    );
+    let _ = writeln!(
+        xliff,
+        r#"  <file original="dialogue" datatype="plaintext" source-language="{}" target-language="{}">"#,
+        escape(source_locale),
+        escape(translations.locale.as_str())
+    );
+    let _ = writeln!(xliff, "    <body>");
+    for message in graph.messages() {
+        let _ = writeln!(
+            xliff,
+            r#"      <trans-unit id="{}">"#,
+            escape(message.key.as_str())
+        );
+        let _ = writeln!(
+            xliff,
+            "        <source>{}</source>",
+            escape(message.text.as_str())
+        );
+        if let Some(target) = translations.get(&message.key) {
+            let _ = writeln!(xliff, "        <target>{}</target>", escape(target));
+        }
+        for speaker in &message.speakers {
+            let _ = writeln!(
+                xliff,
+                r#"        <note from="speaker">{}</note>"#,
+                escape(speaker.as_str())
+            );
+        }
+        for node in &message.nodes {
+            let _ = writeln!(
+                xliff,
+                r#"        <note from="node">{}</note>"#,
+                escape(node.as_str())
+            );
+        }
+        for source in &message.sources {
+            let _ = writeln!(xliff, r#"        <note from="source">{}</note>"#, source);
+        }
+        let _ = writeln!(xliff, "      </trans-unit>");
+    }
+    let _ = writeln!(xliff, "    </body>");
+    let _ = writeln!(xliff, "  </file>");
+    let _ = writeln!(xliff, "</xliff>");
+    xliff
+}
+
+/// The element of a translation unit whose text is being read.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Element {
+    Source,
+    Target,
+}
+
+/// A translation unit being read.
+#[derive(Clone, Debug, Default)]
+struct Unit {
+    id: String,
+    source: String,
+    target: String,
+}
+
+/// Read the translations in an XLIFF 1.2 file into a string table for the
+/// given locale, checking them against the graph's strings.
+///
+/// The id of a translation unit is its key. Units without a target count as
+/// untranslated, and inline elements within a source or target are ignored
+/// but their text is kept.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::True, localization::xliff, DialogueGraph, Node};
+/// let mut graph = DialogueGraph::<True>::new();
+/// graph.data.add_node(Node::new("Halt & wait!").with_id("halt"));
+/// graph.data.add_node("Who goes there?".into());
+///
+/// let file = r#"<xliff version="1.2"><file><body>
+///   <trans-unit id="halt">
+///     <source>Halt &amp; wait!</source>
+///     <target>Alto &amp; espere!</target>
+///   </trans-unit>
+/// </body></file></xliff>"#;
+/// let report = xliff::import(&graph, "pt", file).unwrap();
+///
+/// assert_eq!(report.table.get("halt"), Some("Alto & espere!"));
+/// assert_eq!(report.missing, vec!["Who goes there?"]);
+/// assert!(report.stale.is_empty());
+/// ```
+pub fn import<T>(
+    graph: &DialogueGraph<T>,
+    locale: &str,
+    input: &str,
+) -> Result<ImportReport, ImportError>
+where
+    T: Condition,
+{
+    let mut reader = Reader::from_str(input);
+    let error = |reader: &Reader<&[u8]>, message: String| ImportError::Xml {
+        position: reader.error_position().max(reader.buffer_position()),
+        message,
+    };
+    let mut translations = Vec::new();
+    let mut unit: Option<Unit> = None;
+    let mut element = None;
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| error(&reader, e.to_string()))?;
+        let text = match event {
+            Event::Start(start) => {
+                match start.local_name().as_ref() {
+                    b"trans-unit" => {
+                        let id = start
+                            .try_get_attribute("id")
+                            .map_err(|e| error(&reader, e.to_string()))?
+                            .ok_or_else(|| error(&reader, "trans-unit without an id".to_owned()))?
+                            .unescape_value()
+                            .map_err(|e| error(&reader, e.to_string()))?;
+                        unit = Some(Unit {
+                            id: id.into_owned(),
+                            ..Unit::default()
+                        });
+                    }
+                    b"source" if unit.is_some() => element = Some(Element::Source),
+                    b"target" if unit.is_some() => element = Some(Element::Target),
+                    _ => {}
+                }
+                continue;
+            }
+            Event::End(end) => {
+                match end.local_name().as_ref() {
+                    b"trans-unit" => {
+                        if let Some(unit) = unit.take() {
+                            translations.push((unit.id, unit.source, unit.target));
+                        }
+                    }
+                    b"source" | b"target" => element = None,
+                    _ => {}
+                }
+                continue;
+            }
+            Event::Text(text) => text
+                .xml_content()
+                .map_err(|e| error(&reader, e.to_string()))?
+                .into_owned(),
+            Event::CData(text) => text
+                .decode()
+                .map_err(|e| error(&reader, e.to_string()))?
+                .into_owned(),
+            Event::GeneralRef(reference) => {
+                let name = reference
+                    .decode()
+                    .map_err(|e| error(&reader, e.to_string()))?;
+                match reference
+                    .resolve_char_ref()
+                    .map_err(|e| error(&reader, e.to_string()))?
+                {
+                    Some(c) => c.to_string(),
+                    None => resolve_xml_entity(&name)
+                        .ok_or_else(|| error(&reader, format!("unknown entity &{};", name)))?
+                        .to_owned(),
+                }
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        if let (Some(unit), Some(element)) = (&mut unit, element) {
+            match element {
+                Element::Source => unit.source.push_str(&text),
+                Element::Target => unit.target.push_str(&text),
+            }
+        }
+    }
+    Ok(ImportReport::new(graph, locale, translations))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{condition::True, Edge, Node};
+
+    #[test]
+    fn test_round_trip() {
+        let mut graph = DialogueGraph::<True>::new();
+        let halt = graph.data.add_node(
+            Node::new("Halt! <Who> goes \"there\"?")
+                .with_id("halt")
+                .with_speaker("Guard"),
+        );
+        let friend = graph.data.add_node("Pass.".into());
+        let _ = graph
+            .data
+            .add_edge(halt, friend, Edge::new(True::new()).with_text("A friend"));
+        let mut pt = StringTable::new("pt");
+        let _ = pt.insert("halt", "Alto! <Quem> vem \"lá\"?");
+        let _ = pt.insert("A friend", "Um amigo");
+        let _ = pt.insert("removed", "Removido");
+
+        let file = export(&graph, "en", &pt);
+        let report = import(&graph, "pt", &file).unwrap();
+
+        let _ = pt.remove("removed");
+        assert_eq!(report.table, pt);
+        assert_eq!(report.missing, vec!["Pass."]);
+        assert!(report.stale.is_empty());
+        assert!(file.contains(r#"<note from="node">halt</note>"#));
+        assert!(file.contains(r#"<note from="source">choice:0</note>"#));
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let graph = DialogueGraph::<True>::new();
+
+        assert!(import(&graph, "pt", "<xliff><trans-unit></trans-unit></xliff>").is_err());
+        let entity = r#"<trans-unit id="a"><target>&nbsp;</target></trans-unit>"#;
+        assert_eq!(
+            import(&graph, "pt", entity),
+            Err(ImportError::Xml {
+                position: 33,
+                message: "unknown entity &nbsp;".to_owned()
+            })
+        );
+        assert!(import(&graph, "pt", "<xliff></file>").is_err());
+    }
+}
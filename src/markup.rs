@@ -0,0 +1,313 @@
+use serde::Serialize;
+use std::{error::Error, fmt, str::FromStr};
+
+/// A style applied to a span of text, such as `[b]` or `[color=red]`.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct Style {
+    /// The name of the tag, such as `"b"`.
+    pub name: String,
+    /// The value given to the tag, such as `"red"` in `[color=red]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// A part of marked up text.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Element {
+    /// Text with the styles of the tags enclosing it, outermost first.
+    Span {
+        /// The text, without markup.
+        text: String,
+        /// The styles applied to the text.
+        styles: Vec<Style>,
+    },
+    /// A pause in the text, from `[wait=0.5]`.
+    Wait {
+        /// The length of the pause in seconds.
+        seconds: f64,
+    },
+    /// An event to fire at this point in the text, from `[event=shake]`.
+    Event {
+        /// The name of the event.
+        name: String,
+    },
+}
+
+/// An error raised when markup is malformed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarkupError {
+    /// The byte offset of the problem in the text.
+    pub position: usize,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at byte {}: {}", self.position, self.message)
+    }
+}
+
+impl Error for MarkupError {}
+
+/// Text marked up with tags in square brackets.
+///
+/// - `[name]…[/name]` or `[name=value]…[/name]` applies a style to the text
+///   between the tags, as in `[b]bold[/b]` or `[color=red]red[/color]`.
+///   Styles nest, and must be closed in the reverse order they were opened.
+/// - `[wait=0.5]` pauses for a number of seconds.
+/// - `[event=shake]` fires a named event.
+/// - `[[` is a literal `[`.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::markup::{Element, Markup, Style};
+/// let markup: Markup = "Stop![wait=0.5] [b]Now[/b].[event=shake]".parse().unwrap();
+/// let bold = Style { name: "b".to_owned(), value: None };
+///
+/// assert_eq!(
+///     markup.elements(),
+///     &[
+///         Element::Span { text: "Stop!".to_owned(), styles: vec![] },
+///         Element::Wait { seconds: 0.5 },
+///         Element::Span { text: " ".to_owned(), styles: vec![] },
+///         Element::Span { text: "Now".to_owned(), styles: vec![bold] },
+///         Element::Span { text: ".".to_owned(), styles: vec![] },
+///         Element::Event { name: "shake".to_owned() },
+///     ]
+/// );
+/// assert_eq!(markup.plain_text(), "Stop! Now.");
+/// ```
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct Markup {
+    elements: Vec<Element>,
+}
+
+impl Markup {
+    /// Parse marked up text.
+    pub fn parse(text: &str) -> Result<Self, MarkupError> {
+        Self::parse_with(text, true)
+    }
+
+    /// Parse marked up text, ignoring malformed tags when not strict.
+    fn parse_with(text: &str, strict: bool) -> Result<Self, MarkupError> {
+        let error = |position: usize, message: String| MarkupError { position, message };
+        let mut markup = Self {
+            elements: Vec::new(),
+        };
+        let mut styles: Vec<(Style, usize)> = Vec::new();
+        let mut position = 0;
+        while position < text.len() {
+            let rest = &text[position..];
+            if rest.starts_with("[[") {
+                markup.push_text("[", &styles);
+                position += 2;
+                continue;
+            }
+            if !rest.starts_with('[') {
+                let end = rest.find('[').unwrap_or(rest.len());
+                markup.push_text(&rest[..end], &styles);
+                position += end;
+                continue;
+            }
+
+            let end = match rest.find(']') {
+                Some(end) if !rest[1..end].contains('[') => end,
+                _ if strict => return Err(error(position, "unclosed `[`".to_owned())),
+                _ => {
+                    markup.push_text("[", &styles);
+                    position += 1;
+                    continue;
+                }
+            };
+            let tag = rest[1..end].trim();
+            let tag_position = position;
+            position += end + 1;
+
+            if let Some(name) = tag.strip_prefix('/') {
+                let name = name.trim();
+                match styles.iter().rposition(|(style, _)| style.name == name) {
+                    Some(open) if open + 1 == styles.len() || !strict => {
+                        let _ = styles.remove(open);
+                    }
+                    Some(_) => {
+                        let (innermost, _) = &styles[styles.len() - 1];
+                        let message = format!("`[/{}]` closes `[{}]` first", name, innermost.name);
+                        return Err(error(tag_position, message));
+                    }
+                    None if strict => {
+                        let message = format!("`[/{}]` without `[{}]`", name, name);
+                        return Err(error(tag_position, message));
+                    }
+                    None => {}
+                }
+                continue;
+            }
+
+            let (name, value) = match tag.find('=') {
+                Some(end) => (tag[..end].trim(), Some(tag[end + 1..].trim())),
+                None => (tag, None),
+            };
+            let valid_name = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+            let element = match (name, value) {
+                _ if !valid_name => Err(format!("invalid tag name {:?}", name)),
+                ("wait", Some(value)) => match value.parse::<f64>() {
+                    Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+                        Ok(Some(Element::Wait { seconds }))
+                    }
+                    _ => Err(format!("invalid number of seconds {:?}", value)),
+                },
+                ("event", Some(value)) if !value.is_empty() => Ok(Some(Element::Event {
+                    name: value.to_owned(),
+                })),
+                ("wait", _) | ("event", _) => Err(format!("`[{}]` needs a value", name)),
+                _ => {
+                    let style = Style {
+                        name: name.to_owned(),
+                        value: value.map(str::to_owned),
+                    };
+                    styles.push((style, tag_position));
+                    Ok(None)
+                }
+            };
+            match element {
+                Ok(element) => markup.elements.extend(element),
+                Err(message) if strict => return Err(error(tag_position, message)),
+                Err(_) => {}
+            }
+        }
+
+        match styles.last() {
+            Some((style, position)) if strict => {
+                let message = format!("`[{}]` without `[/{}]`", style.name, style.name);
+                Err(error(*position, message))
+            }
+            _ => Ok(markup),
+        }
+    }
+
+    /// Add text with the given styles, joining it to the last span if that
+    /// has the same styles.
+    fn push_text(&mut self, text: &str, styles: &[(Style, usize)]) {
+        if let Some(Element::Span {
+            text: last,
+            styles: last_styles,
+        }) = self.elements.last_mut()
+        {
+            if last_styles.len() == styles.len()
+                && last_styles
+                    .iter()
+                    .zip(styles)
+                    .all(|(last, (style, _))| last == style)
+            {
+                last.push_str(text);
+                return;
+            }
+        }
+        self.elements.push(Element::Span {
+            text: text.to_owned(),
+            styles: styles.iter().map(|(style, _)| style.clone()).collect(),
+        });
+    }
+
+    /// Returns the parts of the text, in order.
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    /// Returns the text without markup.
+    pub fn plain_text(&self) -> String {
+        self.elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::Span { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Markup {
+    type Err = MarkupError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+/// Returns the text without markup, for plain-text contexts such as logs.
+///
+/// Unlike [`Markup::parse`](struct.Markup.html#method.parse), this never
+/// fails: malformed tags are dropped, and a `[` without a `]` is kept.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::markup::strip;
+/// assert_eq!(strip("[b]Run![/b][event=shake]"), "Run!");
+/// assert_eq!(strip("[i]Unclosed [[sic] and [/b] stray"), "Unclosed [sic] and  stray");
+/// ```
+pub fn strip(text: &str) -> String {
+    Markup::parse_with(text, false)
+        .map(|markup| markup.plain_text())
+        .unwrap_or_else(|_| text.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn style(name: &str, value: Option<&str>) -> Style {
+        Style {
+            name: name.to_owned(),
+            value: value.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn test_nested_styles() {
+        let markup = Markup::parse("[color = red]a[b]b[/b][[c[/color]").unwrap();
+
+        assert_eq!(
+            markup.elements(),
+            &[
+                Element::Span {
+                    text: "a".to_owned(),
+                    styles: vec![style("color", Some("red"))]
+                },
+                Element::Span {
+                    text: "b".to_owned(),
+                    styles: vec![style("color", Some("red")), style("b", None)]
+                },
+                Element::Span {
+                    text: "[c".to_owned(),
+                    styles: vec![style("color", Some("red"))]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let position = |text: &str| Markup::parse(text).err().map(|error| error.position);
+
+        assert_eq!(position("a [b"), Some(2));
+        assert_eq!(position("a [b] c"), Some(2));
+        assert_eq!(position("[b][i][/b][/i]"), Some(6));
+        assert_eq!(position("x[/b]"), Some(1));
+        assert_eq!(position("[wait=soon]"), Some(0));
+        assert_eq!(position("[wait=-1]"), Some(0));
+        assert_eq!(position("[event]"), Some(0));
+        assert_eq!(position("[a b]"), Some(0));
+    }
+
+    #[test]
+    fn test_strip() {
+        assert_eq!(strip("[b][i]x[/b][wait=no]y[/i]"), "xy");
+        assert_eq!(strip("a [b c] ["), "a  [");
+    }
+}
@@ -0,0 +1,652 @@
+use crate::{condition::Condition, DialogueGraph, Edge};
+use petgraph::graph::{EdgeIndex, GraphIndex, NodeIndex};
+use serde::Serialize;
+use std::fmt;
+
+/// One of the two versions of a graph being merged.
+#[derive(Copy, Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    /// Our version.
+    Ours,
+    /// Their version.
+    Theirs,
+}
+
+/// A change which could not be merged automatically.
+///
+/// Conflicting changes are resolved in favour of our version, and changes
+/// are kept over removals, so that no content is lost.
+#[derive(Copy, Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Conflict {
+    /// Both versions changed the node differently.
+    Node {
+        /// The index of the node in the base graph.
+        base: NodeIndex,
+        /// The index of the node in the merged graph.
+        merged: NodeIndex,
+    },
+    /// One version changed the node and the other removed it.
+    RemovedNode {
+        /// The version which removed the node.
+        removed_by: Side,
+        /// The index of the node in the base graph.
+        base: NodeIndex,
+        /// The index of the node in the merged graph.
+        merged: NodeIndex,
+    },
+    /// Both versions added a node with the same id but different text or
+    /// link.
+    AddedNode {
+        /// The index of the node in the merged graph.
+        merged: NodeIndex,
+    },
+    /// Both versions changed the edge's condition differently.
+    Condition {
+        /// The index of the edge in the base graph.
+        base: EdgeIndex,
+        /// The index of the edge in the merged graph.
+        merged: EdgeIndex,
+    },
+    /// Both versions changed the edge's kind, usage limits, call or text
+    /// differently.
+    Edge {
+        /// The index of the edge in the base graph.
+        base: EdgeIndex,
+        /// The index of the edge in the merged graph.
+        merged: EdgeIndex,
+    },
+    /// One version changed the edge and the other removed it, or removed a
+    /// node it connects.
+    RemovedEdge {
+        /// The version which removed the edge.
+        removed_by: Side,
+        /// The index of the edge in the base graph.
+        base: EdgeIndex,
+        /// The index of the edge in the merged graph, if both of the nodes it
+        /// connects are still there.
+        merged: Option<EdgeIndex>,
+    },
+    /// One version added an edge to or from a node the other removed. The
+    /// edge is left out.
+    DanglingEdge {
+        /// The version which added the edge.
+        added_by: Side,
+        /// The index of the edge in that version.
+        edge: EdgeIndex,
+    },
+    /// The edge's condition could not be rewritten for the merged graph's
+    /// indices, as parts of it are opaque or refer to nodes or edges the
+    /// merge left out, so it might refer to the wrong ones.
+    UnmappedCondition {
+        /// The index of the edge in the merged graph.
+        merged: EdgeIndex,
+    },
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Ours => write!(f, "ours"),
+            Side::Theirs => write!(f, "theirs"),
+        }
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::Node { base, .. } => {
+                write!(f, "node {} was changed differently by both", base.index())
+            }
+            Conflict::RemovedNode {
+                removed_by, base, ..
+            } => write!(
+                f,
+                "node {} was removed by {} but changed by the other",
+                base.index(),
+                removed_by
+            ),
+            Conflict::AddedNode { merged } => {
+                write!(f, "node {} was added differently by both", merged.index())
+            }
+            Conflict::Condition { base, .. } => write!(
+                f,
+                "the condition of edge {} was changed differently by both",
+                base.index()
+            ),
+            Conflict::Edge { base, .. } => {
+                write!(f, "edge {} was changed differently by both", base.index())
+            }
+            Conflict::RemovedEdge {
+                removed_by, base, ..
+            } => write!(
+                f,
+                "edge {} was removed by {} but changed by the other",
+                base.index(),
+                removed_by
+            ),
+            Conflict::DanglingEdge { added_by, edge } => write!(
+                f,
+                "edge {} added by {} connects a node removed by the other",
+                edge.index(),
+                added_by
+            ),
+            Conflict::UnmappedCondition { merged } => write!(
+                f,
+                "the condition of edge {} could not be renumbered",
+                merged.index()
+            ),
+        }
+    }
+}
+
+/// The result of a three-way merge.
+#[derive(Debug)]
+pub struct ThreeWayMerge<T>
+where
+    T: Condition,
+{
+    /// The merged graph.
+    pub graph: DialogueGraph<T>,
+    /// The changes which could not be merged automatically.
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Returns the version of a value both sides agree on, or `None` if they
+/// changed it differently.
+fn pick<'a, V>(base: &V, ours: &'a V, theirs: &'a V) -> Option<&'a V>
+where
+    V: PartialEq,
+{
+    if ours == base {
+        Some(theirs)
+    } else if theirs == base || ours == theirs {
+        Some(ours)
+    } else {
+        None
+    }
+}
+
+/// Merge the changes both versions made to an edge, returning the merged
+/// edge, the version its condition was taken from and whether its condition
+/// and its other options conflicted.
+fn merge_edge<T>(base: &Edge<T>, ours: &Edge<T>, theirs: &Edge<T>) -> (Edge<T>, Side, bool, bool)
+where
+    T: Condition + Clone + PartialEq,
+{
+    let (condition, side, condition_conflict) = if ours.condition == base.condition {
+        (&theirs.condition, Side::Theirs, false)
+    } else if theirs.condition == base.condition || ours.condition == theirs.condition {
+        (&ours.condition, Side::Ours, false)
+    } else {
+        (&ours.condition, Side::Ours, true)
+    };
+    let (options, options_conflict) = if ours.same_options(base) {
+        (theirs, false)
+    } else if theirs.same_options(base) || ours.same_options(theirs) {
+        (ours, false)
+    } else {
+        (ours, true)
+    };
+    let mut merged = options.clone();
+    merged.condition = condition.clone();
+    (merged, side, condition_conflict, options_conflict)
+}
+
+/// Returns, for each index of the base graph, the matching index in the
+/// other.
+fn matches<I>(pairs: &[(I, I)], len: usize) -> Vec<Option<I>>
+where
+    I: Copy + GraphIndex,
+{
+    let mut matches = vec![None; len];
+    for (base, other) in pairs {
+        matches[base.index()] = Some(*other);
+    }
+    matches
+}
+
+/// Returns whether every index of a version is the same in the merged graph.
+fn is_identity<I>(indices: &[Option<I>]) -> bool
+where
+    I: Copy + GraphIndex,
+{
+    indices
+        .iter()
+        .enumerate()
+        .all(|(index, merged)| merged.map(|merged| merged.index()) == Some(index))
+}
+
+/// Merge the changes two versions made to a base graph.
+///
+/// Nodes and edges are matched as in
+/// [`DialogueGraph::diff`](../struct.DialogueGraph.html#method.diff). Nodes
+/// and edges of the base graph come first in the merged graph, followed by
+/// those we added and then those they added. Conditions are rewritten to
+/// refer to the merged graph's nodes and edges.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::True, merge, DialogueGraph, Edge, Node};
+/// let mut base = DialogueGraph::new();
+/// let hello = base.data.add_node(Node::new("Hello.").with_id("hello"));
+///
+/// let mut ours = DialogueGraph::new();
+/// ours.data.add_node(Node::new("Hello there.").with_id("hello"));
+///
+/// let mut theirs = DialogueGraph::new();
+/// let their_hello = theirs.data.add_node(Node::new("Hello.").with_id("hello"));
+/// let bye = theirs.data.add_node("Goodbye.".into());
+/// theirs.data.add_edge(their_hello, bye, Edge::new(True::new()));
+///
+/// let merged = merge::three_way(&base, &ours, &theirs);
+/// assert!(merged.conflicts.is_empty());
+/// assert_eq!(merged.graph.data[hello].text, "Hello there.");
+/// assert_eq!(merged.graph.data.edge_count(), 1);
+/// ```
+pub fn three_way<T>(
+    base: &DialogueGraph<T>,
+    ours: &DialogueGraph<T>,
+    theirs: &DialogueGraph<T>,
+) -> ThreeWayMerge<T>
+where
+    T: Condition + Clone + PartialEq,
+{
+    let our_diff = base.diff(ours);
+    let their_diff = base.diff(theirs);
+    let our_nodes = matches(&our_diff.nodes, base.data.node_count());
+    let their_nodes = matches(&their_diff.nodes, base.data.node_count());
+    let our_edges = matches(&our_diff.edges, base.data.edge_count());
+    let their_edges = matches(&their_diff.edges, base.data.edge_count());
+
+    let mut graph = DialogueGraph::new();
+    graph.version = base.version.max(ours.version).max(theirs.version);
+    let mut conflicts = Vec::new();
+
+    // The index in the merged graph of each node of each version.
+    let mut from_base = vec![None; base.data.node_count()];
+    let mut from_ours = vec![None; ours.data.node_count()];
+    let mut from_theirs = vec![None; theirs.data.node_count()];
+
+    for node in base.data.node_indices() {
+        let weight = &base.data[node];
+        let (merged, conflict) = match (our_nodes[node.index()], their_nodes[node.index()]) {
+            (Some(our), Some(their)) => match pick(weight, &ours.data[our], &theirs.data[their]) {
+                Some(merged) => (merged, false),
+                None => (&ours.data[our], true),
+            },
+            (Some(our), None) if ours.data[our] != *weight => (&ours.data[our], true),
+            (None, Some(their)) if theirs.data[their] != *weight => (&theirs.data[their], true),
+            _ => continue,
+        };
+
+        let index = graph.data.add_node(merged.clone());
+        from_base[node.index()] = Some(index);
+        if let Some(our) = our_nodes[node.index()] {
+            from_ours[our.index()] = Some(index);
+        }
+        if let Some(their) = their_nodes[node.index()] {
+            from_theirs[their.index()] = Some(index);
+        }
+        if conflict {
+            conflicts.push(match (our_nodes[node.index()], their_nodes[node.index()]) {
+                (Some(_), Some(_)) => Conflict::Node {
+                    base: node,
+                    merged: index,
+                },
+                (_, None) => Conflict::RemovedNode {
+                    removed_by: Side::Theirs,
+                    base: node,
+                    merged: index,
+                },
+                (None, _) => Conflict::RemovedNode {
+                    removed_by: Side::Ours,
+                    base: node,
+                    merged: index,
+                },
+            });
+        }
+    }
+
+    let mut added = Vec::new();
+    for node in ours.data.node_indices() {
+        if our_diff.nodes.iter().all(|(_, our)| *our != node) {
+            let index = graph.data.add_node(ours.data[node].clone());
+            from_ours[node.index()] = Some(index);
+            added.push(index);
+        }
+    }
+    for node in theirs.data.node_indices() {
+        if their_diff.nodes.iter().any(|(_, their)| *their == node) {
+            continue;
+        }
+        let weight = &theirs.data[node];
+        let same = added.iter().position(|index| {
+            let ours = &graph.data[*index];
+            match (&ours.id, &weight.id) {
+                (Some(our), Some(their)) => our == their,
+                _ => ours == weight,
+            }
+        });
+        let index = match same {
+            Some(position) => {
+                let index = added.remove(position);
+                if graph.data[index] != *weight {
+                    conflicts.push(Conflict::AddedNode { merged: index });
+                }
+                index
+            }
+            None => graph.data.add_node(weight.clone()),
+        };
+        from_theirs[node.index()] = Some(index);
+    }
+
+    // The index in the merged graph of each edge of each version, and the
+    // version each merged edge's condition was taken from.
+    let mut from_our_edges = vec![None; ours.data.edge_count()];
+    let mut from_their_edges = vec![None; theirs.data.edge_count()];
+    let mut origins = Vec::new();
+
+    for edge in base.data.edge_indices() {
+        let weight = &base.data[edge];
+        let (merged, side, condition_conflict, options_conflict, removed_by) =
+            match (our_edges[edge.index()], their_edges[edge.index()]) {
+                (Some(our), Some(their)) => {
+                    let (merged, side, condition_conflict, options_conflict) =
+                        merge_edge(weight, &ours.data[our], &theirs.data[their]);
+                    (merged, side, condition_conflict, options_conflict, None)
+                }
+                (Some(our), None) if ours.data[our] != *weight => (
+                    ours.data[our].clone(),
+                    Side::Ours,
+                    false,
+                    false,
+                    Some(Side::Theirs),
+                ),
+                (None, Some(their)) if theirs.data[their] != *weight => (
+                    theirs.data[their].clone(),
+                    Side::Theirs,
+                    false,
+                    false,
+                    Some(Side::Ours),
+                ),
+                _ => continue,
+            };
+
+        // Only an edge one version removed can lose one of its nodes.
+        let index = base
+            .data
+            .edge_endpoints(edge)
+            .and_then(|(source, target)| {
+                Some((from_base[source.index()]?, from_base[target.index()]?))
+            })
+            .map(|(source, target)| graph.data.add_edge(source, target, merged));
+        if let Some(removed_by) = removed_by {
+            conflicts.push(Conflict::RemovedEdge {
+                removed_by,
+                base: edge,
+                merged: index,
+            });
+        }
+        if let Some(index) = index {
+            if let Some(our) = our_edges[edge.index()] {
+                from_our_edges[our.index()] = Some(index);
+            }
+            if let Some(their) = their_edges[edge.index()] {
+                from_their_edges[their.index()] = Some(index);
+            }
+            origins.push((index, side));
+            if condition_conflict {
+                conflicts.push(Conflict::Condition {
+                    base: edge,
+                    merged: index,
+                });
+            }
+            if options_conflict {
+                conflicts.push(Conflict::Edge {
+                    base: edge,
+                    merged: index,
+                });
+            }
+        }
+    }
+
+    let mut added = Vec::new();
+    for (side, other, diff, from) in &[
+        (Side::Ours, ours, &our_diff, &from_ours),
+        (Side::Theirs, theirs, &their_diff, &from_theirs),
+    ] {
+        let from_edges = match side {
+            Side::Ours => &mut from_our_edges,
+            Side::Theirs => &mut from_their_edges,
+        };
+        for edge in other.data.edge_indices() {
+            if diff.edges.iter().any(|(_, other)| *other == edge) {
+                continue;
+            }
+            let endpoints = other
+                .data
+                .edge_endpoints(edge)
+                .and_then(|(source, target)| Some((from[source.index()]?, from[target.index()]?)));
+            let (source, target) = match endpoints {
+                Some(endpoints) => endpoints,
+                None => {
+                    conflicts.push(Conflict::DanglingEdge {
+                        added_by: *side,
+                        edge,
+                    });
+                    continue;
+                }
+            };
+
+            let weight = &other.data[edge];
+            // Both versions adding the same edge only adds it once.
+            let same = added.iter().position(|index| {
+                graph.data.edge_endpoints(*index) == Some((source, target))
+                    && graph.data[*index] == *weight
+            });
+            match same {
+                Some(position) if *side == Side::Theirs => {
+                    from_edges[edge.index()] = Some(added.remove(position));
+                }
+                _ => {
+                    let index = graph.data.add_edge(source, target, weight.clone());
+                    from_edges[edge.index()] = Some(index);
+                    origins.push((index, *side));
+                    if *side == Side::Ours {
+                        added.push(index);
+                    }
+                }
+            }
+        }
+    }
+
+    for (index, side) in origins {
+        let (nodes, edges) = match side {
+            Side::Ours => (&from_ours, &from_our_edges),
+            Side::Theirs => (&from_theirs, &from_their_edges),
+        };
+        // Conditions of a version whose indices are all kept need no
+        // rewriting, even where they are opaque.
+        if is_identity(nodes) && is_identity(edges) {
+            continue;
+        }
+        let renumber_node = |node: NodeIndex| nodes.get(node.index()).copied().flatten();
+        let renumber_edge = |edge: EdgeIndex| edges.get(edge.index()).copied().flatten();
+        if !graph.data[index]
+            .condition
+            .renumber(&renumber_node, &renumber_edge)
+        {
+            conflicts.push(Conflict::UnmappedCondition { merged: index });
+        }
+    }
+
+    ThreeWayMerge { graph, conflicts }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{condition::dynamic::DynCondition, Node};
+
+    fn original() -> DialogueGraph<DynCondition> {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node(Node::new("A").with_id("a"));
+        let b = graph.data.add_node(Node::new("B").with_id("b"));
+        let _ = graph.data.add_edge(a, b, Edge::new(DynCondition::True));
+        graph
+    }
+
+    #[test]
+    fn test_three_way_merges_both_sides() {
+        let base = original();
+        let mut ours = original();
+        ours.data[NodeIndex::new(0)].text = "A!".to_owned();
+        ours.data[EdgeIndex::new(0)].once = true;
+        let c = ours.data.add_node("C".into());
+        let _ = ours
+            .data
+            .add_edge(NodeIndex::new(1), c, Edge::new(DynCondition::True));
+
+        let mut theirs = original();
+        theirs.data[EdgeIndex::new(0)].condition = DynCondition::False;
+        let c = theirs.data.add_node("C".into());
+        let _ = theirs
+            .data
+            .add_edge(NodeIndex::new(1), c, Edge::new(DynCondition::True));
+
+        let merged = three_way(&base, &ours, &theirs);
+
+        assert!(merged.conflicts.is_empty());
+        let graph = merged.graph;
+        assert_eq!(graph.data.node_count(), 3);
+        assert_eq!(graph.data.edge_count(), 2);
+        assert_eq!(graph.data[NodeIndex::new(0)].text, "A!");
+        let edge = &graph.data[EdgeIndex::new(0)];
+        assert!(edge.once);
+        assert_eq!(edge.condition, DynCondition::False);
+    }
+
+    #[test]
+    fn test_three_way_conflicts() {
+        let base = original();
+        let mut ours = original();
+        ours.data[NodeIndex::new(0)].text = "Ours".to_owned();
+        ours.data[EdgeIndex::new(0)].condition = DynCondition::False;
+        let _ = ours.data.add_node(Node::new("Ours").with_id("new"));
+
+        let mut theirs = original();
+        theirs.data[NodeIndex::new(0)].text = "Theirs".to_owned();
+        let _ = theirs.data.remove_edge(EdgeIndex::new(0));
+        let _ = theirs.data.add_node(Node::new("Theirs").with_id("new"));
+
+        let merged = three_way(&base, &ours, &theirs);
+
+        assert_eq!(
+            merged.conflicts,
+            vec![
+                Conflict::Node {
+                    base: NodeIndex::new(0),
+                    merged: NodeIndex::new(0)
+                },
+                Conflict::AddedNode {
+                    merged: NodeIndex::new(2)
+                },
+                Conflict::RemovedEdge {
+                    removed_by: Side::Theirs,
+                    base: EdgeIndex::new(0),
+                    merged: Some(EdgeIndex::new(0))
+                },
+            ]
+        );
+        assert_eq!(merged.graph.data[NodeIndex::new(0)].text, "Ours");
+        assert_eq!(merged.graph.data.node_count(), 3);
+    }
+
+    #[test]
+    fn test_three_way_dangling_edge() {
+        let base = original();
+        let mut ours = original();
+        let _ = ours.data.add_edge(
+            NodeIndex::new(1),
+            NodeIndex::new(1),
+            Edge::new(DynCondition::True),
+        );
+        let mut theirs = original();
+        let _ = theirs.data.remove_node(NodeIndex::new(1));
+
+        let merged = three_way(&base, &ours, &theirs);
+
+        assert_eq!(
+            merged.conflicts,
+            vec![Conflict::DanglingEdge {
+                added_by: Side::Ours,
+                edge: EdgeIndex::new(1)
+            }]
+        );
+        assert_eq!(merged.graph.data.node_count(), 1);
+        assert_eq!(merged.graph.data.edge_count(), 0);
+    }
+
+    #[test]
+    fn test_three_way_renumbers_conditions() {
+        let graph = || {
+            let mut graph = DialogueGraph::new();
+            let a = graph.data.add_node(Node::new("A").with_id("a"));
+            let b = graph.data.add_node(Node::new("B").with_id("b"));
+            let c = graph.data.add_node(Node::new("C").with_id("c"));
+            let _ = graph.data.add_edge(a, b, Edge::new(DynCondition::True));
+            let _ = graph
+                .data
+                .add_edge(a, c, Edge::new(DynCondition::Visited { node: c }));
+            (graph, a, b, c)
+        };
+        let (base, ..) = graph();
+
+        let (mut ours, a, _, c) = graph();
+        let _ = ours.data.add_edge(
+            c,
+            a,
+            Edge::new(DynCondition::EdgeTaken {
+                edge: EdgeIndex::new(1),
+            }),
+        );
+
+        // Removing B moves C to its index, and the edge to C to index 0.
+        let (mut theirs, _, b, _) = graph();
+        let _ = theirs.data.remove_node(b);
+        let their_c = NodeIndex::new(1);
+        theirs.data[EdgeIndex::new(0)].condition = DynCondition::VisitCount {
+            node: their_c,
+            count: 2,
+        };
+        let d = theirs.data.add_node("D".into());
+        let _ = theirs
+            .data
+            .add_edge(their_c, d, Edge::new(DynCondition::opaque("weather")));
+
+        let merged = three_way(&base, &ours, &theirs);
+
+        let graph = &merged.graph;
+        assert_eq!(graph.data.node_count(), 3);
+        let c = graph.find("c").unwrap();
+        assert_eq!(
+            graph.data[EdgeIndex::new(0)].condition,
+            DynCondition::VisitCount { node: c, count: 2 }
+        );
+        assert_eq!(
+            graph.data[EdgeIndex::new(1)].condition,
+            DynCondition::EdgeTaken {
+                edge: EdgeIndex::new(0)
+            }
+        );
+        assert_eq!(
+            merged.conflicts,
+            vec![Conflict::UnmappedCondition {
+                merged: EdgeIndex::new(2)
+            }]
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::{condition::Condition, DialogueGraph, FORMAT_VERSION};
+use serde_json::Value;
+use std::{collections::BTreeMap, convert::TryFrom, error::Error, fmt};
+
+/// An upgrade step which rewrites a serialized graph from one version to the
+/// next.
+///
+/// The step receives the whole serialized `DialogueGraph` as a JSON value and
+/// does not need to update its `version` field.
+pub type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// A pipeline of upgrade steps run on serialized graphs before they are
+/// deserialized.
+///
+/// The pipeline upgrades graphs to at least
+/// [`FORMAT_VERSION`](../constant.FORMAT_VERSION.html), and past it to one
+/// past the last step registered.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::True, migration::Migrations, DialogueGraph};
+/// # use serde_json::json;
+/// let migrations = Migrations::new().register(1, |_graph| Ok(()));
+/// let old = json!({
+///     "data": {
+///         "nodes": ["Hello"],
+///         "node_holes": [],
+///         "edge_property": "directed",
+///         "edges": []
+///     }
+/// });
+///
+/// let graph: DialogueGraph<True> = migrations.deserialize(old).unwrap();
+/// assert_eq!(graph.version, 2);
+/// ```
+#[derive(Debug)]
+pub struct Migrations {
+    steps: BTreeMap<u32, Migration>,
+}
+
+impl Default for Migrations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Migrations {
+    /// Create a pipeline with only the steps upgrading graphs to
+    /// `FORMAT_VERSION`.
+    pub fn new() -> Self {
+        // Versioning only added the `version` field itself.
+        Self {
+            steps: BTreeMap::new(),
+        }
+        .register(0, |_graph| Ok(()))
+    }
+
+    /// Register the upgrade from version `from` to version `from + 1`.
+    pub fn register(mut self, from: u32, migration: Migration) -> Self {
+        let _ = self.steps.insert(from, migration);
+        self
+    }
+
+    /// The version that serialized graphs are upgraded to, one past the last
+    /// registered step, or `FORMAT_VERSION` if that is later.
+    pub fn latest(&self) -> u32 {
+        self.steps
+            .keys()
+            .next_back()
+            .map_or(FORMAT_VERSION, |from| (from + 1).max(FORMAT_VERSION))
+    }
+
+    /// Upgrade a serialized graph in place to the latest version, returning
+    /// the version it started at.
+    pub fn migrate(&self, value: &mut Value) -> Result<u32, MigrationError> {
+        let object = value.as_object_mut().ok_or(MigrationError::NotAnObject)?;
+        let initial = match object.get("version") {
+            None => 0,
+            Some(version) => version
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or(MigrationError::InvalidVersion)?,
+        };
+
+        let latest = self.latest();
+        if initial > latest {
+            return Err(MigrationError::UnsupportedVersion {
+                version: initial,
+                latest,
+            });
+        }
+
+        for from in initial..latest {
+            let step = self
+                .steps
+                .get(&from)
+                .ok_or(MigrationError::MissingMigration { from })?;
+            step(value).map_err(|message| MigrationError::Step { from, message })?;
+
+            let object = value.as_object_mut().ok_or(MigrationError::NotAnObject)?;
+            let _ = object.insert("version".to_owned(), Value::from(from + 1));
+        }
+
+        Ok(initial)
+    }
+
+    /// Upgrade a serialized graph to the latest version and deserialize it.
+    pub fn deserialize<T>(&self, mut value: Value) -> Result<DialogueGraph<T>, MigrationError>
+    where
+        T: Condition,
+    {
+        let _ = self.migrate(&mut value)?;
+        serde_json::from_value(value).map_err(MigrationError::Deserialize)
+    }
+
+    /// Parse a serialized graph from a JSON string, upgrade it to the latest
+    /// version and deserialize it.
+    pub fn from_str<T>(&self, json: &str) -> Result<DialogueGraph<T>, MigrationError>
+    where
+        T: Condition,
+    {
+        let value = serde_json::from_str(json).map_err(MigrationError::Deserialize)?;
+        self.deserialize(value)
+    }
+}
+
+/// An error raised while upgrading a serialized graph.
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The serialized graph is not a JSON object.
+    NotAnObject,
+    /// The `version` field is not a valid version number.
+    InvalidVersion,
+    /// The graph is newer than the latest registered version.
+    UnsupportedVersion {
+        /// The version of the serialized graph.
+        version: u32,
+        /// The latest version known to the pipeline.
+        latest: u32,
+    },
+    /// No step is registered to upgrade from the given version.
+    MissingMigration {
+        /// The version without an upgrade step.
+        from: u32,
+    },
+    /// An upgrade step failed.
+    Step {
+        /// The version the failing step upgrades from.
+        from: u32,
+        /// The message returned by the step.
+        message: String,
+    },
+    /// The upgraded graph could not be deserialized.
+    Deserialize(serde_json::Error),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::NotAnObject => f.write_str("serialized graph is not an object"),
+            MigrationError::InvalidVersion => f.write_str("invalid `version` field"),
+            MigrationError::UnsupportedVersion { version, latest } => write!(
+                f,
+                "graph version {} is newer than the latest supported version {}",
+                version, latest
+            ),
+            MigrationError::MissingMigration { from } => {
+                write!(f, "no migration registered from version {}", from)
+            }
+            MigrationError::Step { from, message } => {
+                write!(f, "migration from version {} failed: {}", from, message)
+            }
+            MigrationError::Deserialize(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for MigrationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MigrationError::Deserialize(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::condition::True;
+    use serde_json::json;
+
+    fn graph(version: Option<u32>, nodes: Value) -> Value {
+        let mut graph = json!({
+            "data": {
+                "nodes": nodes,
+                "node_holes": [],
+                "edge_property": "directed",
+                "edges": []
+            }
+        });
+        if let Some(version) = version {
+            graph["version"] = Value::from(version);
+        }
+        graph
+    }
+
+    fn rename_nodes(graph: &mut Value) -> Result<(), String> {
+        let nodes = graph["data"]["nodes"]
+            .as_array_mut()
+            .ok_or_else(|| "missing nodes".to_owned())?;
+        for node in nodes {
+            *node = Value::from(format!("{}!", node.as_str().unwrap_or("")));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_graphs_are_latest() {
+        let migrations = Migrations::new();
+        let value = serde_json::to_value(DialogueGraph::<True>::new()).unwrap();
+        let mut migrated = value.clone();
+
+        assert_eq!(migrations.latest(), FORMAT_VERSION);
+        assert_eq!(migrations.migrate(&mut migrated).unwrap(), FORMAT_VERSION);
+        assert_eq!(migrated, value);
+    }
+
+    #[test]
+    fn test_migrate_runs_steps_in_order() {
+        let migrations = Migrations::new()
+            .register(0, |_| Ok(()))
+            .register(1, rename_nodes);
+        let graph: DialogueGraph<True> = migrations
+            .deserialize(graph(None, json!(["Hello"])))
+            .unwrap();
+
+        assert_eq!(graph.version, 2);
+        assert_eq!(graph.data.raw_nodes()[0].weight.text, "Hello!");
+    }
+
+    #[test]
+    fn test_migrate_skips_applied_steps() {
+        let migrations = Migrations::new()
+            .register(0, rename_nodes)
+            .register(1, |_| Ok(()));
+        let graph: DialogueGraph<True> = migrations
+            .deserialize(graph(Some(1), json!(["Hello"])))
+            .unwrap();
+
+        assert_eq!(graph.data.raw_nodes()[0].weight.text, "Hello");
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_version() {
+        let migrations = Migrations::new().register(0, |_| Ok(()));
+        let error = migrations
+            .deserialize::<True>(graph(Some(3), json!([])))
+            .unwrap_err();
+
+        match error {
+            MigrationError::UnsupportedVersion { version, latest } => {
+                assert_eq!((version, latest), (3, 1));
+            }
+            _ => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn test_migrate_reports_missing_step() {
+        let migrations = Migrations::new().register(2, |_| Ok(()));
+        let error = migrations.migrate(&mut graph(None, json!([]))).unwrap_err();
+
+        match error {
+            MigrationError::MissingMigration { from } => assert_eq!(from, 1),
+            _ => panic!("unexpected error: {}", error),
+        }
+    }
+}
@@ -0,0 +1,496 @@
+use crate::{
+    condition::{dynamic::DynCondition, Condition, Context},
+    conversation::History,
+    variable::{Value, Variables},
+    DialogueGraph, EdgeKind,
+};
+use petgraph::{
+    graph::{EdgeIndex, NodeIndex},
+    visit::EdgeRef,
+};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// The most valuations of the variables explored by default.
+pub const DEFAULT_MAX_VALUATIONS: usize = 4096;
+
+/// The most states explored by default, over every valuation.
+pub const DEFAULT_MAX_STATES: usize = 100_000;
+
+/// A variable assignment and path under which a node is reached.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Witness {
+    /// The variables the conversation has set.
+    pub variables: Variables,
+    /// The edges traversed from the start to the node, in order.
+    pub path: Vec<EdgeIndex>,
+}
+
+/// The results of exploring the states of a conversation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReachabilityReport {
+    /// The nodes reached, each with the first way found to reach it, which
+    /// takes as few traversals as possible under its variables.
+    pub reachable: BTreeMap<NodeIndex, Witness>,
+    /// The nodes not reached under any valuation explored.
+    pub unreachable: Vec<NodeIndex>,
+    /// The number of valuations explored.
+    pub valuations: usize,
+    /// The number of distinct states explored.
+    pub states: usize,
+    /// Whether every valuation and state was explored. If not, a limit was
+    /// hit and nodes reported unreachable might be reachable.
+    pub complete: bool,
+}
+
+/// What a conversation's history can matter for: the visit counts and
+/// traversal counts its conditions and limits tell apart.
+#[derive(Default)]
+struct Thresholds {
+    /// The highest visit count conditions check for each node.
+    visits: BTreeMap<NodeIndex, u32>,
+    /// The highest traversal count conditions and limits check for each
+    /// edge.
+    traversals: BTreeMap<EdgeIndex, u32>,
+    /// The cooldown of each edge which has one.
+    cooldowns: BTreeMap<EdgeIndex, u32>,
+}
+
+/// A state as far as conditions and limits can tell: the current node and
+/// the history, with counts capped at the thresholds that can tell them
+/// apart.
+type Key = (NodeIndex, Vec<u32>, Vec<u32>, Vec<Option<usize>>);
+
+impl Thresholds {
+    fn new<T>(graph: &DialogueGraph<T>) -> Self
+    where
+        T: Condition,
+    {
+        let mut thresholds = Self::default();
+        for edge in graph.data.edge_references() {
+            let weight = edge.weight();
+            thresholds.condition(&weight.condition.to_dynamic());
+            let uses = if weight.once {
+                Some(1)
+            } else {
+                weight.max_uses
+            };
+            if let Some(uses) = uses {
+                thresholds.traversal(edge.id(), uses);
+            }
+            if let Some(cooldown) = weight.cooldown {
+                let _ = thresholds.cooldowns.insert(edge.id(), cooldown);
+            }
+        }
+        thresholds
+    }
+
+    fn traversal(&mut self, edge: EdgeIndex, count: u32) {
+        let threshold = self.traversals.entry(edge).or_insert(0);
+        *threshold = (*threshold).max(count);
+    }
+
+    fn condition(&mut self, condition: &DynCondition) {
+        match condition {
+            DynCondition::Not { condition } => self.condition(condition),
+            DynCondition::And { left, right } | DynCondition::Or { left, right } => {
+                self.condition(left);
+                self.condition(right);
+            }
+            DynCondition::Visited { node } => {
+                let threshold = self.visits.entry(*node).or_insert(0);
+                *threshold = (*threshold).max(1);
+            }
+            DynCondition::VisitCount { node, count } => {
+                let threshold = self.visits.entry(*node).or_insert(0);
+                *threshold = (*threshold).max(*count);
+            }
+            DynCondition::EdgeTaken { edge } => self.traversal(*edge, 1),
+            _ => {}
+        }
+    }
+
+    fn key(&self, node: NodeIndex, history: &History) -> Key {
+        (
+            node,
+            self.visits
+                .iter()
+                .map(|(node, threshold)| history.visit_count(*node).min(*threshold))
+                .collect(),
+            self.traversals
+                .iter()
+                .map(|(edge, threshold)| history.traversal_count(*edge).min(*threshold))
+                .collect(),
+            self.cooldowns
+                .iter()
+                .map(|(edge, cooldown)| {
+                    history
+                        .traversals_since(*edge)
+                        .map(|since| since.min(*cooldown as usize))
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Returns the values of each variable conditions compare, which between
+/// them make every comparison of integers and every equality of strings come
+/// out every way it can, along with the variable being unset.
+fn inferred_domains<T>(graph: &DialogueGraph<T>) -> BTreeMap<String, Vec<Option<Value>>>
+where
+    T: Condition,
+{
+    fn collect(condition: &DynCondition, values: &mut BTreeMap<String, Vec<Value>>) {
+        match condition {
+            DynCondition::Not { condition } => collect(condition, values),
+            DynCondition::And { left, right } | DynCondition::Or { left, right } => {
+                collect(left, values);
+                collect(right, values);
+            }
+            DynCondition::Compare {
+                variable, value, ..
+            } => {
+                let domain = values.entry(variable.clone()).or_default();
+                let candidates = match value {
+                    Value::Bool(_) => vec![Value::Bool(false), Value::Bool(true)],
+                    Value::Int(value) => vec![
+                        Value::Int(value.saturating_sub(1)),
+                        Value::Int(*value),
+                        Value::Int(value.saturating_add(1)),
+                    ],
+                    Value::Str(_) => vec![value.clone()],
+                };
+                for candidate in candidates {
+                    if !domain.contains(&candidate) {
+                        domain.push(candidate);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut values = BTreeMap::new();
+    for edge in graph.data.raw_edges() {
+        collect(&edge.weight.condition.to_dynamic(), &mut values);
+    }
+    values
+        .into_iter()
+        .map(|(name, values)| {
+            // A string longer than any compared to differs from all of them.
+            let strings: Vec<&str> = values
+                .iter()
+                .filter_map(|value| match value {
+                    Value::Str(value) => Some(value.as_str()),
+                    _ => None,
+                })
+                .collect();
+            let mut domain = vec![None];
+            if !strings.is_empty() {
+                domain.push(Some(Value::Str(strings.concat() + "~")));
+            }
+            domain.extend(values.into_iter().map(Some));
+            (name, domain)
+        })
+        .collect()
+}
+
+/// Explores every state a conversation in a graph can reach, under every
+/// valuation of its variables from bounded domains, to find the nodes which
+/// can never be reached.
+///
+/// Unlike [`validate_structure`], which only follows edges, conditions and
+/// usage limits are evaluated, so a node behind `gold >= 10 and gold < 5` is
+/// unreachable. Variables are assumed not to change while the conversation
+/// runs. Automatic edges are followed as a conversation would, and an edge
+/// calling a graph is assumed to return from it, leading on to its target.
+///
+/// The domain of each variable conditions compare is inferred from the
+/// values they compare it to, which covers every outcome of comparisons of
+/// flags and integers, unless one is given. The exploration stops at the
+/// limits on valuations and states, and the report says whether it did.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::{And, Compare, Comparison, Condition}, reachability::Reachability, DialogueGraph, Edge};
+/// let mut graph = DialogueGraph::new();
+/// let shop = graph.data.add_node("What will it be?".into());
+/// let sword = graph.data.add_node("A fine sword.".into());
+/// let cheap = graph.data.add_node("A bargain!".into());
+/// graph.data.add_edge(shop, sword, Edge::new(Compare::new("gold", Comparison::GreaterOrEqual, 10).to_dynamic()));
+/// graph.data.add_edge(shop, cheap, Edge::new(And::new(
+///     Compare::new("gold", Comparison::GreaterOrEqual, 10),
+///     Compare::new("gold", Comparison::Less, 5),
+/// ).to_dynamic()));
+///
+/// let report = Reachability::new(&graph).run();
+/// assert_eq!(report.unreachable, vec![cheap]);
+/// assert!(report.complete);
+///
+/// let witness = &report.reachable[&sword];
+/// assert_eq!(witness.path.len(), 1);
+/// assert!(witness.variables.get("gold").is_some());
+/// ```
+///
+/// [`validate_structure`]: ../struct.DialogueGraph.html#method.validate_structure
+#[allow(missing_debug_implementations)]
+pub struct Reachability<'a, T>
+where
+    T: Condition,
+{
+    graph: &'a DialogueGraph<T>,
+    start: Option<NodeIndex>,
+    domains: BTreeMap<String, Vec<Option<Value>>>,
+    max_valuations: usize,
+    max_states: usize,
+}
+
+impl<'a, T> Reachability<'a, T>
+where
+    T: Condition,
+{
+    /// Create an analysis of conversations from the graph's entry node, with
+    /// variable domains inferred from its conditions.
+    pub fn new(graph: &'a DialogueGraph<T>) -> Self {
+        Self {
+            graph,
+            start: None,
+            domains: inferred_domains(graph),
+            max_valuations: DEFAULT_MAX_VALUATIONS,
+            max_states: DEFAULT_MAX_STATES,
+        }
+    }
+
+    /// Start conversations at the given node instead of the entry node.
+    pub fn with_start(mut self, start: NodeIndex) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Give a variable one of the given values, instead of the values
+    /// inferred from conditions. A variable given no values is left unset.
+    pub fn with_domain(mut self, name: &str, values: Vec<Value>) -> Self {
+        let domain = if values.is_empty() {
+            vec![None]
+        } else {
+            values.into_iter().map(Some).collect()
+        };
+        let _ = self.domains.insert(name.to_owned(), domain);
+        self
+    }
+
+    /// Explore at most the given number of valuations.
+    pub fn with_max_valuations(mut self, max_valuations: usize) -> Self {
+        self.max_valuations = max_valuations;
+        self
+    }
+
+    /// Explore at most the given number of states, over every valuation.
+    pub fn with_max_states(mut self, max_states: usize) -> Self {
+        self.max_states = max_states;
+        self
+    }
+
+    /// Returns the valuations of the variables to explore, up to the limit,
+    /// and whether there are more.
+    fn valuations(&self) -> (Vec<Variables>, bool) {
+        let mut valuations = vec![Variables::new()];
+        for (name, domain) in &self.domains {
+            let mut extended = Vec::new();
+            for variables in &valuations {
+                for value in domain {
+                    if extended.len() == self.max_valuations {
+                        return (extended, false);
+                    }
+                    let mut variables = variables.clone();
+                    if let Some(value) = value {
+                        let _ = variables.set(name, value.clone());
+                    }
+                    extended.push(variables);
+                }
+            }
+            valuations = extended;
+        }
+        (valuations, true)
+    }
+
+    /// Returns the edges a conversation can traverse from a node: the first
+    /// open automatic edge if there is one, or else every open choice. Edges
+    /// calling a graph are included, taking the called graph to return.
+    fn transitions(&self, node: NodeIndex, context: &Context<'_>) -> Vec<EdgeIndex> {
+        let mut edges: Vec<EdgeIndex> = self
+            .graph
+            .data
+            .edges(node)
+            .filter(|edge| edge.weight().is_open(edge.id(), context))
+            .map(|edge| edge.id())
+            .collect();
+        edges.sort();
+        match edges
+            .iter()
+            .find(|edge| self.graph.data[**edge].kind == EdgeKind::Auto)
+        {
+            Some(auto) => vec![*auto],
+            None => edges,
+        }
+    }
+
+    /// Explore the states under every valuation, returning the results.
+    pub fn run(&self) -> ReachabilityReport {
+        let mut report = ReachabilityReport::default();
+        let start = match self.start.or_else(|| self.graph.entry()) {
+            Some(start) => start,
+            None => {
+                report.complete = true;
+                return report;
+            }
+        };
+        let thresholds = Thresholds::new(self.graph);
+        let (valuations, mut complete) = self.valuations();
+
+        'valuations: for variables in valuations {
+            if report.reachable.len() == self.graph.data.node_count() {
+                break;
+            }
+            report.valuations += 1;
+            let mut history = History::new().with_path();
+            history.record_visit(start);
+            let mut seen = BTreeSet::new();
+            let _ = seen.insert(thresholds.key(start, &history));
+            let mut queue = VecDeque::new();
+            queue.push_back((start, history));
+
+            while let Some((node, history)) = queue.pop_front() {
+                report.states += 1;
+                let _ = report.reachable.entry(node).or_insert_with(|| Witness {
+                    variables: variables.clone(),
+                    path: history.path().to_vec(),
+                });
+                let context = Context::new()
+                    .with_variables(&variables)
+                    .with_history(&history);
+                for edge in self.transitions(node, &context) {
+                    let target = match self.graph.data.edge_endpoints(edge) {
+                        Some((_, target)) => target,
+                        None => continue,
+                    };
+                    let mut next = history.clone();
+                    next.record_traversal(edge);
+                    next.record_visit(target);
+                    if seen.insert(thresholds.key(target, &next)) {
+                        if report.states + queue.len() >= self.max_states {
+                            complete = false;
+                            break 'valuations;
+                        }
+                        queue.push_back((target, next));
+                    }
+                }
+            }
+        }
+
+        report.unreachable = self
+            .graph
+            .data
+            .node_indices()
+            .filter(|node| !report.reachable.contains_key(node))
+            .collect();
+        report.complete = complete || report.unreachable.is_empty();
+        report
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        condition::{Compare, Comparison, Not, VisitCount, Visited},
+        Edge,
+    };
+
+    #[test]
+    fn test_history() {
+        let mut graph = DialogueGraph::new();
+        let hub = graph.data.add_node("Hub".into());
+        let story = graph.data.add_node("Story".into());
+        let secret = graph.data.add_node("Secret".into());
+        let never = graph.data.add_node("Never".into());
+        let _ = graph
+            .data
+            .add_edge(hub, story, Edge::new(DynCondition::True));
+        let _ = graph
+            .data
+            .add_edge(story, hub, Edge::new(DynCondition::True).auto());
+        let _ = graph.data.add_edge(
+            hub,
+            secret,
+            Edge::new(VisitCount::new(story, 3).to_dynamic()),
+        );
+        let _ = graph.data.add_edge(
+            hub,
+            never,
+            Edge::new(Not::new(Visited::new(hub)).to_dynamic()),
+        );
+
+        let report = Reachability::new(&graph).run();
+
+        assert!(report.complete);
+        assert_eq!(report.unreachable, vec![never]);
+        assert_eq!(report.reachable[&secret].path.len(), 7);
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let c = graph.data.add_node("C".into());
+        let flag = Compare::new("flag", Comparison::Equal, true);
+        let _ = graph
+            .data
+            .add_edge(a, b, Edge::new(flag.to_dynamic()).once());
+        let _ = graph.data.add_edge(b, a, Edge::new(DynCondition::True));
+        let _ = graph.data.add_edge(
+            a,
+            c,
+            Edge::new(Compare::new("name", Comparison::NotEqual, "Ada").to_dynamic()),
+        );
+
+        let report = Reachability::new(&graph).run();
+        assert!(report.unreachable.is_empty());
+        assert_eq!(
+            report.reachable[&b].variables.get("flag"),
+            Some(&Value::Bool(true))
+        );
+        assert_ne!(
+            report.reachable[&c].variables.get("name"),
+            Some(&Value::from("Ada"))
+        );
+
+        let report = Reachability::new(&graph)
+            .with_domain("flag", vec![false.into()])
+            .run();
+        assert_eq!(report.unreachable, vec![b]);
+
+        let report = Reachability::new(&graph).with_domain("flag", vec![]).run();
+        assert_eq!(report.unreachable, vec![b]);
+        assert_eq!(report.reachable[&a].variables.get("flag"), None);
+
+        let report = Reachability::new(&graph).with_max_states(1).run();
+        assert!(!report.complete);
+    }
+
+    #[test]
+    fn test_calls() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let ab = graph
+            .data
+            .add_edge(a, b, Edge::new(DynCondition::True).with_call("inn"));
+
+        let report = Reachability::new(&graph).run();
+
+        assert!(report.complete);
+        assert!(report.unreachable.is_empty());
+        assert_eq!(report.reachable[&b].path, vec![ab]);
+    }
+}
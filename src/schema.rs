@@ -0,0 +1,493 @@
+use crate::{
+    condition::{
+        dynamic::DynCondition, And, Compare, Comparison, Condition, EdgeTaken, Not, Or, True,
+        VisitCount, Visited,
+    },
+    library::Library,
+    DialogueGraph, Edge, Node,
+};
+use serde_json::{json, Value};
+
+/// The JSON Schema dialect used by generated schemas.
+pub const DIALECT: &str = "http://json-schema.org/draft-07/schema#";
+
+/// A type that can describe its serialized form as a JSON Schema.
+///
+/// Implement this for your own condition types so that the schema of a
+/// `DialogueGraph` using them includes their sub-schema.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::Condition, schema::JsonSchema};
+/// # use serde::{Deserialize, Serialize};
+/// # use serde_json::{json, Value};
+/// #[derive(Serialize, Deserialize)]
+/// struct HasItem {
+///     item: String,
+/// }
+///
+/// # impl Condition for HasItem {
+/// #     fn evaluate(&self) -> bool {
+/// #         true
+/// #     }
+/// # }
+/// impl JsonSchema for HasItem {
+///     fn json_schema() -> Value {
+///         json!({
+///             "type": "object",
+///             "properties": { "item": { "type": "string" } },
+///             "required": ["item"],
+///             "additionalProperties": false
+///         })
+///     }
+/// }
+/// ```
+pub trait JsonSchema {
+    /// Returns the schema describing the serialized type.
+    fn json_schema() -> Value;
+}
+
+/// Returns a standalone schema document for the given type, suitable for
+/// saving to a file and referencing from an editor.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::True, schema, DialogueGraph};
+/// let document = schema::root::<DialogueGraph<True>>();
+///
+/// assert_eq!(document["$schema"], schema::DIALECT);
+/// ```
+pub fn root<S>() -> Value
+where
+    S: JsonSchema,
+{
+    let mut schema = S::json_schema();
+    if let Some(object) = schema.as_object_mut() {
+        let _ = object.insert("$schema".to_owned(), Value::from(DIALECT));
+    }
+    schema
+}
+
+/// Returns the schema of a struct with the given required fields.
+fn object(properties: Vec<(&str, Value)>) -> Value {
+    let required: Vec<&str> = properties.iter().map(|(name, _)| *name).collect();
+    let properties: serde_json::Map<String, Value> = properties
+        .into_iter()
+        .map(|(name, schema)| (name.to_owned(), schema))
+        .collect();
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false
+    })
+}
+
+/// Returns the schema of a node or edge index.
+fn index() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+
+/// Returns the fields of a `Compare` condition.
+fn compare_fields() -> Vec<(&'static str, Value)> {
+    let comparisons = [
+        Comparison::Equal,
+        Comparison::NotEqual,
+        Comparison::Less,
+        Comparison::LessOrEqual,
+        Comparison::Greater,
+        Comparison::GreaterOrEqual,
+    ];
+    let comparisons: Vec<Value> = comparisons
+        .iter()
+        .filter_map(|comparison| serde_json::to_value(comparison).ok())
+        .collect();
+
+    vec![
+        ("variable", json!({ "type": "string" })),
+        ("comparison", json!({ "enum": comparisons })),
+        ("value", json!({ "type": ["boolean", "integer", "string"] })),
+    ]
+}
+
+impl JsonSchema for True {
+    fn json_schema() -> Value {
+        object(vec![])
+    }
+}
+
+impl<T> JsonSchema for Not<T>
+where
+    T: Condition + JsonSchema,
+{
+    fn json_schema() -> Value {
+        object(vec![("condition", T::json_schema())])
+    }
+}
+
+impl<T, U> JsonSchema for And<T, U>
+where
+    T: Condition + JsonSchema,
+    U: Condition + JsonSchema,
+{
+    fn json_schema() -> Value {
+        object(vec![
+            ("left", T::json_schema()),
+            ("right", U::json_schema()),
+        ])
+    }
+}
+
+impl<T, U> JsonSchema for Or<T, U>
+where
+    T: Condition + JsonSchema,
+    U: Condition + JsonSchema,
+{
+    fn json_schema() -> Value {
+        object(vec![
+            ("left", T::json_schema()),
+            ("right", U::json_schema()),
+        ])
+    }
+}
+
+impl JsonSchema for Compare {
+    fn json_schema() -> Value {
+        object(compare_fields())
+    }
+}
+
+impl JsonSchema for Visited {
+    fn json_schema() -> Value {
+        object(vec![("node", index())])
+    }
+}
+
+impl JsonSchema for VisitCount {
+    fn json_schema() -> Value {
+        object(vec![
+            ("node", index()),
+            ("count", json!({ "type": "integer", "minimum": 0 })),
+        ])
+    }
+}
+
+impl JsonSchema for EdgeTaken {
+    fn json_schema() -> Value {
+        object(vec![("edge", index())])
+    }
+}
+
+impl JsonSchema for DynCondition {
+    /// Returns the schema of every variant, tagged by `type`. The schema is
+    /// identified as `#condition`, which nested conditions refer to.
+    fn json_schema() -> Value {
+        let condition = json!({ "$ref": "#condition" });
+        let variants = vec![
+            ("true", vec![]),
+            ("false", vec![]),
+            ("not", vec![("condition", condition.clone())]),
+            (
+                "and",
+                vec![("left", condition.clone()), ("right", condition.clone())],
+            ),
+            (
+                "or",
+                vec![("left", condition.clone()), ("right", condition)],
+            ),
+            ("compare", compare_fields()),
+            ("visited", vec![("node", index())]),
+            (
+                "visit_count",
+                vec![
+                    ("node", index()),
+                    ("count", json!({ "type": "integer", "minimum": 0 })),
+                ],
+            ),
+            ("edge_taken", vec![("edge", index())]),
+            (
+                "opaque",
+                vec![("name", json!({ "type": "string" })), ("value", json!({}))],
+            ),
+        ];
+        let variants: Vec<Value> = variants
+            .into_iter()
+            .map(|(tag, mut fields)| {
+                fields.insert(0, ("type", json!({ "const": tag })));
+                object(fields)
+            })
+            .collect();
+
+        json!({ "$id": "#condition", "oneOf": variants })
+    }
+}
+
+impl JsonSchema for Node {
+    fn json_schema() -> Value {
+        let string = json!({ "type": "string" });
+        let mut full = object(vec![
+            ("id", string.clone()),
+            ("speaker", string.clone()),
+            ("text", string.clone()),
+            ("key", string.clone()),
+            ("link", string),
+        ]);
+        full["required"] = json!([]);
+        json!({ "oneOf": [{ "type": "string" }, full] })
+    }
+}
+
+impl<T> JsonSchema for Edge<T>
+where
+    T: Condition + JsonSchema,
+{
+    fn json_schema() -> Value {
+        let count = json!({ "type": ["integer", "null"], "minimum": 0 });
+        let mut schema = object(vec![
+            ("condition", T::json_schema()),
+            ("kind", json!({ "enum": ["choice", "auto"] })),
+            ("once", json!({ "type": "boolean" })),
+            ("max_uses", count.clone()),
+            ("cooldown", count),
+            ("call", json!({ "type": ["string", "null"] })),
+            ("text", json!({ "type": ["string", "null"] })),
+            ("key", json!({ "type": ["string", "null"] })),
+        ]);
+        schema["required"] = json!(["condition"]);
+        schema
+    }
+}
+
+impl<T> JsonSchema for DialogueGraph<T>
+where
+    T: Condition + JsonSchema,
+{
+    fn json_schema() -> Value {
+        let index = index();
+        let edge = json!({
+            "oneOf": [
+                { "type": "null" },
+                {
+                    "type": "array",
+                    "items": [index, index, Edge::<T>::json_schema()],
+                    "minItems": 3,
+                    "maxItems": 3
+                }
+            ]
+        });
+        let data = object(vec![
+            (
+                "nodes",
+                json!({ "type": "array", "items": Node::json_schema() }),
+            ),
+            ("node_holes", json!({ "type": "array", "items": index })),
+            ("edge_property", json!({ "const": "directed" })),
+            ("edges", json!({ "type": "array", "items": edge })),
+        ]);
+
+        let mut schema = object(vec![("version", index), ("data", data)]);
+        // Graphs serialized before versioning have no `version` field.
+        schema["required"] = json!(["data"]);
+        schema["title"] = Value::from("DialogueGraph");
+        schema
+    }
+}
+
+impl<T> JsonSchema for Library<T>
+where
+    T: Condition + JsonSchema,
+{
+    fn json_schema() -> Value {
+        json!({
+            "title": "Library",
+            "type": "object",
+            "additionalProperties": DialogueGraph::<T>::json_schema()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::format::text;
+
+    /// Returns whether a value is valid against a schema, supporting the
+    /// keywords generated schemas use.
+    fn is_valid(schema: &Value, value: &Value, root: &Value) -> bool {
+        fn find<'a>(schema: &'a Value, id: &str) -> Option<&'a Value> {
+            if schema["$id"] == id {
+                return Some(schema);
+            }
+            match schema {
+                Value::Object(object) => object.values().find_map(|value| find(value, id)),
+                Value::Array(array) => array.iter().find_map(|value| find(value, id)),
+                _ => None,
+            }
+        }
+
+        let object = match schema.as_object() {
+            Some(object) => object,
+            None => return true,
+        };
+        if let Some(id) = object.get("$ref").and_then(Value::as_str) {
+            return find(root, id).map_or(false, |schema| is_valid(schema, value, root));
+        }
+        if let Some(schemas) = object.get("oneOf").and_then(Value::as_array) {
+            let valid = schemas
+                .iter()
+                .filter(|schema| is_valid(schema, value, root))
+                .count();
+            if valid != 1 {
+                return false;
+            }
+        }
+        if let Some(types) = object.get("type") {
+            let kind = match value {
+                Value::Null => "null",
+                Value::Bool(_) => "boolean",
+                Value::Number(number) if number.is_u64() || number.is_i64() => "integer",
+                Value::Number(_) => "number",
+                Value::String(_) => "string",
+                Value::Array(_) => "array",
+                Value::Object(_) => "object",
+            };
+            let types = types
+                .as_array()
+                .cloned()
+                .unwrap_or_else(|| vec![types.clone()]);
+            if !types.iter().any(|name| name == kind) {
+                return false;
+            }
+        }
+        if object
+            .get("const")
+            .map_or(false, |constant| constant != value)
+            || object
+                .get("enum")
+                .and_then(Value::as_array)
+                .map_or(false, |values| !values.contains(value))
+            || object
+                .get("minimum")
+                .and_then(Value::as_i64)
+                .map_or(false, |minimum| {
+                    value.as_i64().map_or(false, |value| value < minimum)
+                })
+        {
+            return false;
+        }
+        if let Some(fields) = value.as_object() {
+            let properties = &schema["properties"];
+            let required = schema["required"].as_array().cloned().unwrap_or_default();
+            if required
+                .iter()
+                .any(|name| !fields.contains_key(name.as_str().unwrap_or("")))
+            {
+                return false;
+            }
+            for (name, field) in fields {
+                let valid = match properties.get(name) {
+                    Some(property) => is_valid(property, field, root),
+                    None => match object.get("additionalProperties") {
+                        Some(Value::Bool(allowed)) => *allowed,
+                        Some(additional) => is_valid(additional, field, root),
+                        None => true,
+                    },
+                };
+                if !valid {
+                    return false;
+                }
+            }
+        }
+        if let Some(items) = value.as_array() {
+            let length = items.len() as u64;
+            if schema["minItems"]
+                .as_u64()
+                .map_or(false, |min| length < min)
+                || schema["maxItems"]
+                    .as_u64()
+                    .map_or(false, |max| length > max)
+            {
+                return false;
+            }
+            let valid = match &schema["items"] {
+                Value::Array(schemas) => items
+                    .iter()
+                    .zip(schemas)
+                    .all(|(item, schema)| is_valid(schema, item, root)),
+                Value::Null => true,
+                schema => items.iter().all(|item| is_valid(schema, item, root)),
+            };
+            if !valid {
+                return false;
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn test_schema_nests_conditions() {
+        let schema = Not::<And<True, True>>::json_schema();
+
+        assert_eq!(
+            schema["properties"]["condition"]["required"],
+            json!(["left", "right"])
+        );
+        assert_eq!(
+            schema["properties"]["condition"]["properties"]["left"],
+            True::json_schema()
+        );
+    }
+
+    #[test]
+    fn test_schema_compare() {
+        let schema = Compare::json_schema();
+
+        assert_eq!(
+            schema["properties"]["comparison"]["enum"][5],
+            "greater_or_equal"
+        );
+    }
+
+    #[test]
+    fn test_schema_dialogue_graph() {
+        let schema = root::<DialogueGraph<Or<True, True>>>();
+        let edge = &schema["properties"]["data"]["properties"]["edges"]["items"]["oneOf"][1];
+
+        assert_eq!(schema["$schema"], DIALECT);
+        assert_eq!(schema["required"], json!(["data"]));
+        assert_eq!(edge["items"][2], Edge::<Or<True, True>>::json_schema());
+    }
+
+    #[test]
+    fn test_schema_validates_cli_files() {
+        let schema = root::<DialogueGraph<DynCondition>>();
+        let graph = text::read(
+            "version 1\n\
+             node 0: \"Halt!\" id=\"halt\" speaker=\"Guard\"\n\
+             node 1: \"\" link=\"gate::open\"\n\
+             edge 0: 0 -> 1 once text=\"Pay\" if gold >= 10 and not visited(1)\n\
+             edge 1: 0 -> 0 auto max_uses=2 if visited(0, 2) or taken(0) or name == \"Ana\"\n",
+        )
+        .unwrap();
+        let mut file = serde_json::to_value(&graph).unwrap();
+
+        assert!(is_valid(&schema, &file, &schema));
+
+        file["data"]["edges"][0][2]["condition"] = json!({
+            "type": "opaque",
+            "name": "Weather",
+            "value": { "sunny": true }
+        });
+        assert!(is_valid(&schema, &file, &schema));
+
+        file["data"]["edges"][0][2]["condition"] = json!({
+            "type": "not",
+            "condition": { "type": "visited", "node": -1 }
+        });
+        assert!(!is_valid(&schema, &file, &schema));
+
+        file["data"]["edges"][0][2]["condition"] = json!({ "type": "maybe" });
+        assert!(!is_valid(&schema, &file, &schema));
+    }
+}
@@ -1,14 +1,275 @@
 use crate::{
     condition::{And, Condition, Function, Not, Or},
-    DialogueGraph, Edge,
+    library::Library,
+    DialogueGraph, Edge, EdgeKind, Node,
 };
 use serde::{
     de::{self, MapAccess, SeqAccess, Visitor},
-    Deserialize, Deserializer, Serialize,
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
 };
+use std::collections::BTreeMap;
 use std::fmt;
 use std::marker::PhantomData;
 
+impl Serialize for Node {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if !serializer.is_human_readable() {
+            // Formats which don't describe their own layout read every field
+            // by position.
+            let mut state = serializer.serialize_struct("Node", 5)?;
+            state.serialize_field("id", &self.id)?;
+            state.serialize_field("speaker", &self.speaker)?;
+            state.serialize_field("text", &self.text)?;
+            state.serialize_field("key", &self.key)?;
+            state.serialize_field("link", &self.link)?;
+            return state.end();
+        }
+        if self.id.is_none() && self.speaker.is_none() && self.key.is_none() && self.link.is_none()
+        {
+            return serializer.serialize_str(&self.text);
+        }
+
+        let len = 1
+            + self.id.is_some() as usize
+            + self.speaker.is_some() as usize
+            + self.key.is_some() as usize
+            + self.link.is_some() as usize;
+        let mut state = serializer.serialize_struct("Node", len)?;
+        if let Some(id) = &self.id {
+            state.serialize_field("id", id)?;
+        }
+        if let Some(speaker) = &self.speaker {
+            state.serialize_field("speaker", speaker)?;
+        }
+        state.serialize_field("text", &self.text)?;
+        if let Some(key) = &self.key {
+            state.serialize_field("key", key)?;
+        }
+        if let Some(link) = &self.link {
+            state.serialize_field("link", link)?;
+        }
+        state.end()
+    }
+}
+
+impl<T> Serialize for Edge<T>
+where
+    T: Condition,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Formats which don't describe their own layout read fields by
+        // position, so only those which describe it can leave out defaults.
+        let all = !serializer.is_human_readable();
+        let kind = all || self.kind != EdgeKind::Choice;
+        let once = all || self.once;
+        let max_uses = all || self.max_uses.is_some();
+        let cooldown = all || self.cooldown.is_some();
+        let call = all || self.call.is_some();
+        let text = all || self.text.is_some();
+        let key = all || self.key.is_some();
+
+        let len = 1
+            + kind as usize
+            + once as usize
+            + max_uses as usize
+            + cooldown as usize
+            + call as usize
+            + text as usize
+            + key as usize;
+        let mut state = serializer.serialize_struct("Edge", len)?;
+        state.serialize_field("condition", &self.condition)?;
+        if kind {
+            state.serialize_field("kind", &self.kind)?;
+        }
+        if once {
+            state.serialize_field("once", &self.once)?;
+        }
+        if max_uses {
+            state.serialize_field("max_uses", &self.max_uses)?;
+        }
+        if cooldown {
+            state.serialize_field("cooldown", &self.cooldown)?;
+        }
+        if call {
+            state.serialize_field("call", &self.call)?;
+        }
+        if text {
+            state.serialize_field("text", &self.text)?;
+        }
+        if key {
+            state.serialize_field("key", &self.key)?;
+        }
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        enum Field {
+            Id,
+            Speaker,
+            Text,
+            Key,
+            Link,
+        };
+
+        impl<'de> Deserialize<'de> for Field {
+            fn deserialize<D>(deserializer: D) -> Result<Field, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                struct FieldVisitor;
+
+                impl Visitor<'_> for FieldVisitor {
+                    type Value = Field;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        formatter.write_str("`id`, `speaker`, `text`, `key` or `link`")
+                    }
+
+                    fn visit_str<E>(self, value: &str) -> Result<Field, E>
+                    where
+                        E: de::Error,
+                    {
+                        match value {
+                            "id" => Ok(Field::Id),
+                            "speaker" => Ok(Field::Speaker),
+                            "text" => Ok(Field::Text),
+                            "key" => Ok(Field::Key),
+                            "link" => Ok(Field::Link),
+                            _ => Err(de::Error::unknown_field(value, FIELDS)),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_identifier(FieldVisitor)
+            }
+        }
+
+        struct NodeVisitor;
+
+        impl<'de> Visitor<'de> for NodeVisitor {
+            type Value = Node;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a string or struct Node")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Node, E>
+            where
+                E: de::Error,
+            {
+                Ok(Node::new(value))
+            }
+
+            fn visit_string<E>(self, value: String) -> Result<Node, E>
+            where
+                E: de::Error,
+            {
+                Ok(Node::from(value))
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Node, V::Error>
+            where
+                V: SeqAccess<'de>,
+            {
+                let id = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let speaker = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let text = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                let key = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+                let link = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(4, &self))?;
+                Ok(Node {
+                    id,
+                    speaker,
+                    text,
+                    key,
+                    link,
+                })
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<Node, V::Error>
+            where
+                V: MapAccess<'de>,
+            {
+                let mut id = None;
+                let mut speaker = None;
+                let mut text = None;
+                let mut string_key = None;
+                let mut link = None;
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        Field::Id => {
+                            if id.is_some() {
+                                return Err(de::Error::duplicate_field("id"));
+                            }
+                            id = Some(map.next_value()?);
+                        }
+                        Field::Speaker => {
+                            if speaker.is_some() {
+                                return Err(de::Error::duplicate_field("speaker"));
+                            }
+                            speaker = Some(map.next_value()?);
+                        }
+                        Field::Text => {
+                            if text.is_some() {
+                                return Err(de::Error::duplicate_field("text"));
+                            }
+                            text = Some(map.next_value()?);
+                        }
+                        Field::Key => {
+                            if string_key.is_some() {
+                                return Err(de::Error::duplicate_field("key"));
+                            }
+                            string_key = Some(map.next_value()?);
+                        }
+                        Field::Link => {
+                            if link.is_some() {
+                                return Err(de::Error::duplicate_field("link"));
+                            }
+                            link = Some(map.next_value()?);
+                        }
+                    }
+                }
+
+                Ok(Node {
+                    id,
+                    speaker,
+                    text: text.unwrap_or_default(),
+                    key: string_key,
+                    link,
+                })
+            }
+        }
+
+        const FIELDS: &[&str] = &["id", "speaker", "text", "key", "link"];
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(NodeVisitor)
+        } else {
+            deserializer.deserialize_struct("Node", FIELDS, NodeVisitor)
+        }
+    }
+}
+
 impl<'de, T> Deserialize<'de> for Edge<T>
 where
     T: Condition,
@@ -19,6 +280,13 @@ where
     {
         enum Field {
             Condition,
+            Kind,
+            Once,
+            MaxUses,
+            Cooldown,
+            Call,
+            Text,
+            Key,
         };
 
         impl<'de> Deserialize<'de> for Field {
@@ -32,7 +300,9 @@ where
                     type Value = Field;
 
                     fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-                        formatter.write_str("`condition`")
+                        formatter.write_str(
+                            "`condition`, `kind`, `once`, `max_uses`, `cooldown`, `call`, `text` or `key`",
+                        )
                     }
 
                     fn visit_str<E>(self, value: &str) -> Result<Field, E>
@@ -41,6 +311,13 @@ where
                     {
                         match value {
                             "condition" => Ok(Field::Condition),
+                            "kind" => Ok(Field::Kind),
+                            "once" => Ok(Field::Once),
+                            "max_uses" => Ok(Field::MaxUses),
+                            "cooldown" => Ok(Field::Cooldown),
+                            "call" => Ok(Field::Call),
+                            "text" => Ok(Field::Text),
+                            "key" => Ok(Field::Key),
                             _ => Err(de::Error::unknown_field(value, FIELDS)),
                         }
                     }
@@ -85,7 +362,15 @@ where
                 let condition = seq
                     .next_element()?
                     .ok_or_else(|| de::Error::invalid_length(0, &self))?;
-                Ok(Edge::new(condition))
+                let mut edge = Edge::new(condition);
+                edge.kind = seq.next_element()?.unwrap_or_default();
+                edge.once = seq.next_element()?.unwrap_or(false);
+                edge.max_uses = seq.next_element()?.unwrap_or(None);
+                edge.cooldown = seq.next_element()?.unwrap_or(None);
+                edge.call = seq.next_element()?.unwrap_or(None);
+                edge.text = seq.next_element()?.unwrap_or(None);
+                edge.key = seq.next_element()?.unwrap_or(None);
+                Ok(edge)
             }
 
             fn visit_map<V>(self, mut map: V) -> Result<Edge<T>, V::Error>
@@ -93,6 +378,13 @@ where
                 V: MapAccess<'de>,
             {
                 let mut condition = None;
+                let mut kind = None;
+                let mut once = None;
+                let mut max_uses = None;
+                let mut cooldown = None;
+                let mut call = None;
+                let mut text = None;
+                let mut string_key = None;
                 while let Some(key) = map.next_key()? {
                     match key {
                         Field::Condition => {
@@ -101,15 +393,74 @@ where
                             }
                             condition = Some(map.next_value()?);
                         }
+                        Field::Kind => {
+                            if kind.is_some() {
+                                return Err(de::Error::duplicate_field("kind"));
+                            }
+                            kind = Some(map.next_value()?);
+                        }
+                        Field::Once => {
+                            if once.is_some() {
+                                return Err(de::Error::duplicate_field("once"));
+                            }
+                            once = Some(map.next_value()?);
+                        }
+                        Field::MaxUses => {
+                            if max_uses.is_some() {
+                                return Err(de::Error::duplicate_field("max_uses"));
+                            }
+                            max_uses = Some(map.next_value()?);
+                        }
+                        Field::Cooldown => {
+                            if cooldown.is_some() {
+                                return Err(de::Error::duplicate_field("cooldown"));
+                            }
+                            cooldown = Some(map.next_value()?);
+                        }
+                        Field::Call => {
+                            if call.is_some() {
+                                return Err(de::Error::duplicate_field("call"));
+                            }
+                            call = Some(map.next_value()?);
+                        }
+                        Field::Text => {
+                            if text.is_some() {
+                                return Err(de::Error::duplicate_field("text"));
+                            }
+                            text = Some(map.next_value()?);
+                        }
+                        Field::Key => {
+                            if string_key.is_some() {
+                                return Err(de::Error::duplicate_field("key"));
+                            }
+                            string_key = Some(map.next_value()?);
+                        }
                     }
                 }
 
                 let condition = condition.ok_or_else(|| de::Error::missing_field("condition"))?;
-                Ok(Edge::new(condition))
+                let mut edge = Edge::new(condition);
+                edge.kind = kind.unwrap_or_default();
+                edge.once = once.unwrap_or(false);
+                edge.max_uses = max_uses.unwrap_or(None);
+                edge.cooldown = cooldown.unwrap_or(None);
+                edge.call = call.unwrap_or(None);
+                edge.text = text.unwrap_or(None);
+                edge.key = string_key.unwrap_or(None);
+                Ok(edge)
             }
         }
 
-        const FIELDS: &[&str] = &["condition"];
+        const FIELDS: &[&str] = &[
+            "condition",
+            "kind",
+            "once",
+            "max_uses",
+            "cooldown",
+            "call",
+            "text",
+            "key",
+        ];
         deserializer.deserialize_struct("Edge", FIELDS, EdgeVisitor::new())
     }
 }
@@ -124,6 +475,7 @@ where
     {
         enum Field {
             Data,
+            Version,
         };
 
         impl<'de> Deserialize<'de> for Field {
@@ -137,7 +489,7 @@ where
                     type Value = Field;
 
                     fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-                        formatter.write_str("`condition`")
+                        formatter.write_str("`data` or `version`")
                     }
 
                     fn visit_str<E>(self, value: &str) -> Result<Field, E>
@@ -146,6 +498,7 @@ where
                     {
                         match value {
                             "data" => Ok(Field::Data),
+                            "version" => Ok(Field::Version),
                             _ => Err(de::Error::unknown_field(value, FIELDS)),
                         }
                     }
@@ -180,7 +533,7 @@ where
             type Value = DialogueGraph<T>;
 
             fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-                formatter.write_str("struct Edge")
+                formatter.write_str("struct DialogueGraph")
             }
 
             fn visit_seq<V>(self, mut seq: V) -> Result<DialogueGraph<T>, V::Error>
@@ -190,16 +543,28 @@ where
                 let data = seq
                     .next_element()?
                     .ok_or_else(|| de::Error::invalid_length(0, &self))?;
-                Ok(DialogueGraph { data })
+                // Graphs serialized before versioning was introduced end
+                // after `data`. Formats which don't describe their own
+                // layout, such as bincode, can't tell where a graph ends, so
+                // old graphs must be read from them with a version appended.
+                let version = seq.next_element()?.unwrap_or(0);
+                Ok(DialogueGraph { version, data })
             }
 
             fn visit_map<V>(self, mut map: V) -> Result<DialogueGraph<T>, V::Error>
             where
                 V: MapAccess<'de>,
             {
+                let mut version = None;
                 let mut data = None;
                 while let Some(key) = map.next_key()? {
                     match key {
+                        Field::Version => {
+                            if version.is_some() {
+                                return Err(de::Error::duplicate_field("version"));
+                            }
+                            version = Some(map.next_value()?);
+                        }
                         Field::Data => {
                             if data.is_some() {
                                 return Err(de::Error::duplicate_field("condition"));
@@ -209,16 +574,31 @@ where
                     }
                 }
 
+                // Graphs serialized before versioning was introduced have no
+                // `version` field.
+                let version = version.unwrap_or(0);
                 let data = data.ok_or_else(|| de::Error::missing_field("condition"))?;
-                Ok(DialogueGraph { data })
+                Ok(DialogueGraph { version, data })
             }
         }
 
-        const FIELDS: &[&str] = &["data"];
+        const FIELDS: &[&str] = &["data", "version"];
         deserializer.deserialize_struct("DialogueGraph", FIELDS, DialogueGraphVisitor::new())
     }
 }
 
+impl<'de, T> Deserialize<'de> for Library<T>
+where
+    T: Condition,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        BTreeMap::deserialize(deserializer).map(|graphs| Library { graphs })
+    }
+}
+
 impl<'de, T> Deserialize<'de> for Not<T>
 where
     T: Condition,
@@ -704,13 +1084,73 @@ where
 mod test {
     use super::*;
     use crate::{condition::True, Node};
-    use serde_test::{assert_tokens, Token};
+    use serde_test::{assert_de_tokens, assert_ser_tokens, assert_tokens, Configure, Token};
 
     #[test]
     fn test_ser_de_node() {
-        let node: Node = "Node".to_owned();
+        let node: Node = "Node".into();
 
-        assert_tokens(&node, &[Token::Str("Node")]);
+        assert_tokens(&node.readable(), &[Token::Str("Node")]);
+    }
+
+    #[test]
+    fn test_ser_de_node_with_id() {
+        let node = Node::new("Bye.").with_id("farewell");
+
+        assert_tokens(
+            &node.readable(),
+            &[
+                Token::Struct {
+                    name: "Node",
+                    len: 2,
+                },
+                Token::Str("id"),
+                Token::Str("farewell"),
+                Token::Str("text"),
+                Token::Str("Bye."),
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_ser_de_node_compact() {
+        let node = Node::new("Bye.").with_speaker("Guard");
+
+        assert_ser_tokens(
+            &node.clone().compact(),
+            &[
+                Token::Struct {
+                    name: "Node",
+                    len: 5,
+                },
+                Token::Str("id"),
+                Token::None,
+                Token::Str("speaker"),
+                Token::Some,
+                Token::Str("Guard"),
+                Token::Str("text"),
+                Token::Str("Bye."),
+                Token::Str("key"),
+                Token::None,
+                Token::Str("link"),
+                Token::None,
+                Token::StructEnd,
+            ],
+        );
+        assert_de_tokens(
+            &node.compact(),
+            &[
+                Token::Seq { len: Some(5) },
+                Token::None,
+                Token::Some,
+                Token::Str("Guard"),
+                Token::Str("Bye."),
+                Token::None,
+                Token::None,
+                Token::SeqEnd,
+            ],
+        );
     }
 
     #[test]
@@ -718,7 +1158,7 @@ mod test {
         let edge = Edge::new(True::new());
 
         assert_tokens(
-            &edge,
+            &edge.readable(),
             &[
                 Token::Struct {
                     name: "Edge",
@@ -735,11 +1175,156 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_ser_de_edge_options() {
+        let edge = Edge::new(True::new())
+            .auto()
+            .once()
+            .with_max_uses(3)
+            .with_cooldown(2)
+            .with_call("shop");
+
+        assert_tokens(
+            &edge.readable(),
+            &[
+                Token::Struct {
+                    name: "Edge",
+                    len: 6,
+                },
+                Token::Str("condition"),
+                Token::Struct {
+                    name: "True",
+                    len: 0,
+                },
+                Token::StructEnd,
+                Token::Str("kind"),
+                Token::UnitVariant {
+                    name: "EdgeKind",
+                    variant: "auto",
+                },
+                Token::Str("once"),
+                Token::Bool(true),
+                Token::Str("max_uses"),
+                Token::Some,
+                Token::U32(3),
+                Token::Str("cooldown"),
+                Token::Some,
+                Token::U32(2),
+                Token::Str("call"),
+                Token::Some,
+                Token::Str("shop"),
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_ser_de_edge_compact() {
+        let edge = Edge::new(True::new()).with_max_uses(3);
+
+        assert_ser_tokens(
+            &edge.clone().compact(),
+            &[
+                Token::Struct {
+                    name: "Edge",
+                    len: 8,
+                },
+                Token::Str("condition"),
+                Token::Struct {
+                    name: "True",
+                    len: 0,
+                },
+                Token::StructEnd,
+                Token::Str("kind"),
+                Token::UnitVariant {
+                    name: "EdgeKind",
+                    variant: "choice",
+                },
+                Token::Str("once"),
+                Token::Bool(false),
+                Token::Str("max_uses"),
+                Token::Some,
+                Token::U32(3),
+                Token::Str("cooldown"),
+                Token::None,
+                Token::Str("call"),
+                Token::None,
+                Token::Str("text"),
+                Token::None,
+                Token::Str("key"),
+                Token::None,
+                Token::StructEnd,
+            ],
+        );
+        assert_de_tokens(
+            &edge.compact(),
+            &[
+                Token::Seq { len: Some(8) },
+                Token::Struct {
+                    name: "True",
+                    len: 0,
+                },
+                Token::StructEnd,
+                Token::UnitVariant {
+                    name: "EdgeKind",
+                    variant: "choice",
+                },
+                Token::Bool(false),
+                Token::Some,
+                Token::U32(3),
+                Token::None,
+                Token::None,
+                Token::None,
+                Token::None,
+                Token::SeqEnd,
+            ],
+        );
+    }
+
     #[test]
     fn test_ser_de_dialogue_graph() {
         let graph = DialogueGraph::<True>::new();
 
         assert_tokens(
+            &graph,
+            &[
+                Token::Struct {
+                    name: "DialogueGraph",
+                    len: 2,
+                },
+                Token::Str("data"),
+                Token::Struct {
+                    name: "Graph",
+                    len: 4,
+                },
+                Token::Str("nodes"),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::Str("node_holes"),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::Str("edge_property"),
+                Token::UnitVariant {
+                    name: "EdgeProperty",
+                    variant: "directed",
+                },
+                Token::Str("edges"),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::StructEnd,
+                Token::Str("version"),
+                Token::U32(1),
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_de_unversioned_dialogue_graph() {
+        let mut graph = DialogueGraph::<True>::new();
+        graph.version = 0;
+
+        assert_de_tokens(
             &graph,
             &[
                 Token::Struct {
@@ -771,6 +1356,39 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_de_unversioned_dialogue_graph_seq() {
+        let mut graph = DialogueGraph::<True>::new();
+        graph.version = 0;
+
+        assert_de_tokens(
+            &graph,
+            &[
+                Token::Seq { len: Some(1) },
+                Token::Struct {
+                    name: "Graph",
+                    len: 4,
+                },
+                Token::Str("nodes"),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::Str("node_holes"),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::Str("edge_property"),
+                Token::UnitVariant {
+                    name: "EdgeProperty",
+                    variant: "directed",
+                },
+                Token::Str("edges"),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::StructEnd,
+                Token::SeqEnd,
+            ],
+        );
+    }
+
     #[test]
     fn test_ser_de_not() {
         let not = Not::new(True::new());
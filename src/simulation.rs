@@ -0,0 +1,361 @@
+use crate::{
+    condition::Condition,
+    conversation::{Conversation, ConversationError, Position},
+    library::Library,
+    variable::{Value, Variables},
+    DialogueGraph,
+};
+use petgraph::graph::NodeIndex;
+use rand::{rngs::StdRng, seq::SliceRandom, RngCore, SeedableRng};
+use std::collections::BTreeMap;
+
+/// The most choices made in a single run by default.
+pub const DEFAULT_MAX_STEPS: usize = 1000;
+
+/// How a randomized run through a graph ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The conversation reached a node no edges lead out of.
+    Finished,
+    /// The conversation stopped at a node which has edges leading out of it,
+    /// none of them open.
+    Stuck {
+        /// The node the conversation stopped at, which may be in a called
+        /// graph.
+        position: Position,
+    },
+    /// The conversation was still going after the most steps allowed, and is
+    /// likely caught in a loop.
+    Loop,
+    /// The conversation could not proceed.
+    Error(ConversationError),
+}
+
+/// A randomized run through a graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Run {
+    /// The seed the run was made with, which
+    /// [`Simulator::run_once`](struct.Simulator.html#method.run_once) takes
+    /// to make it again.
+    pub seed: u64,
+    /// The variables the run started with.
+    pub variables: Variables,
+    /// The number of choices made.
+    pub steps: usize,
+    /// The positions moved to, in order, from the start.
+    pub positions: Vec<Position>,
+    /// How the run ended.
+    pub outcome: Outcome,
+}
+
+/// A problem found by a number of runs, with the first of them as an
+/// example.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Failure {
+    /// How the runs ended.
+    pub outcome: Outcome,
+    /// The number of runs which ended this way.
+    pub count: usize,
+    /// The first run which ended this way.
+    pub example: Run,
+}
+
+/// The results of a number of randomized runs through a graph.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SimulationReport {
+    /// The number of runs made.
+    pub runs: usize,
+    /// The number of runs which finished.
+    pub finished: usize,
+    /// The ways runs failed to finish, in the order they were first found.
+    pub failures: Vec<Failure>,
+    /// The number of times each position was moved to, over every run.
+    pub visits: BTreeMap<Position, u64>,
+}
+
+impl SimulationReport {
+    /// Add a run to the report.
+    fn record(&mut self, run: Run) {
+        self.runs += 1;
+        for position in &run.positions {
+            *self.visits.entry(position.clone()).or_insert(0) += 1;
+        }
+        if run.outcome == Outcome::Finished {
+            self.finished += 1;
+            return;
+        }
+        match self
+            .failures
+            .iter_mut()
+            .find(|failure| failure.outcome == run.outcome)
+        {
+            Some(failure) => failure.count += 1,
+            None => self.failures.push(Failure {
+                outcome: run.outcome.clone(),
+                count: 1,
+                example: run,
+            }),
+        }
+    }
+
+    /// Returns the number of times a node of the graph the runs started in
+    /// was moved to.
+    ///
+    /// Visits to nodes of called graphs are not counted; look them up in
+    /// [`visits`](#structfield.visits) by their position instead.
+    pub fn visit_count(&self, node: NodeIndex) -> u64 {
+        self.visits
+            .get(&Position::new(None, node))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Makes randomized runs through a graph, making random choices and starting
+/// with random variables, to find where conversations get stuck or loop.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::{Compare, Comparison}, conversation::Position, simulation::{Outcome, Simulator}, DialogueGraph, Edge};
+/// let mut graph = DialogueGraph::new();
+/// let gate = graph.data.add_node("The gate is shut.".into());
+/// let town = graph.data.add_node("Welcome to town.".into());
+/// graph.data.add_edge(gate, town, Edge::new(Compare::new("key", Comparison::Equal, true)));
+///
+/// let report = Simulator::new(&graph)
+///     .with_seed(7)
+///     .with_runs(100)
+///     .with_variable("key", vec![true.into(), false.into()])
+///     .run();
+///
+/// assert_eq!(report.runs, 100);
+/// assert!(report.finished > 0);
+/// assert_eq!(report.failures[0].outcome, Outcome::Stuck { position: Position::new(None, gate) });
+/// ```
+#[allow(missing_debug_implementations)]
+pub struct Simulator<'a, T>
+where
+    T: Condition,
+{
+    graph: &'a DialogueGraph<T>,
+    library: Option<&'a Library<T>>,
+    start: Option<NodeIndex>,
+    runs: usize,
+    max_steps: usize,
+    seed: u64,
+    variables: Vec<(String, Vec<Value>)>,
+}
+
+impl<'a, T> Simulator<'a, T>
+where
+    T: Condition,
+{
+    /// Create a simulator making a single run from the graph's entry node.
+    pub fn new(graph: &'a DialogueGraph<T>) -> Self {
+        Self {
+            graph,
+            library: None,
+            start: None,
+            runs: 1,
+            max_steps: DEFAULT_MAX_STEPS,
+            seed: rand::random(),
+            variables: Vec::new(),
+        }
+    }
+
+    /// Use the given library for the graphs edges call.
+    pub fn with_library(mut self, library: &'a Library<T>) -> Self {
+        self.library = Some(library);
+        self
+    }
+
+    /// Start runs at the given node instead of the entry node.
+    pub fn with_start(mut self, start: NodeIndex) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Make the given number of runs.
+    pub fn with_runs(mut self, runs: usize) -> Self {
+        self.runs = runs;
+        self
+    }
+
+    /// End runs as loops after the given number of choices.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Use the given seed, so that the same runs are made each time.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Start each run with a variable set to one of the given values, picked
+    /// at random. Variables are left unset if no values are given.
+    pub fn with_variable(mut self, name: &str, values: Vec<Value>) -> Self {
+        self.variables.push((name.to_owned(), values));
+        self
+    }
+
+    /// Make the runs, returning their results. No runs are made if the graph
+    /// has no nodes.
+    pub fn run(&self) -> SimulationReport {
+        let mut report = SimulationReport::default();
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        for _ in 0..self.runs {
+            match self.run_once(rng.next_u64()) {
+                Some(run) => report.record(run),
+                None => break,
+            }
+        }
+        report
+    }
+
+    /// Make a single run with the given seed, returning `None` if the graph
+    /// has no nodes.
+    pub fn run_once(&self, seed: u64) -> Option<Run> {
+        let start = self.start.or_else(|| self.graph.entry())?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut conversation = Conversation::new(self.graph, start).with_seed(rng.next_u64());
+        if let Some(library) = self.library {
+            conversation = conversation.with_library(library);
+        }
+        for (name, values) in &self.variables {
+            if let Some(value) = values.choose(&mut rng) {
+                let _ = conversation.variables_mut().set(name, value.clone());
+            }
+        }
+
+        let variables = conversation.variables().clone();
+        let mut positions = vec![conversation.position()];
+        let mut steps = 0;
+        let outcome = loop {
+            match conversation.advance() {
+                Ok(moved) => positions.extend(moved),
+                Err(error) => break Outcome::Error(error),
+            }
+            let edge = match conversation.random_choice() {
+                Some(edge) => edge,
+                None => {
+                    let node = conversation.current();
+                    let exit = conversation.graph().data.edges(node).next().is_none();
+                    break if exit {
+                        Outcome::Finished
+                    } else {
+                        Outcome::Stuck {
+                            position: conversation.position(),
+                        }
+                    };
+                }
+            };
+            if steps == self.max_steps {
+                break Outcome::Loop;
+            }
+            if let Err(error) = conversation.choose(edge) {
+                break Outcome::Error(error);
+            }
+            positions.push(conversation.position());
+            steps += 1;
+        };
+        Some(Run {
+            seed,
+            variables,
+            steps,
+            positions,
+            outcome,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{condition::True, Edge};
+
+    #[test]
+    fn test_run() {
+        let mut graph = DialogueGraph::new();
+        let hub = graph.data.add_node("Hub".into());
+        let bye = graph.data.add_node("Bye".into());
+        let _ = graph.data.add_edge(hub, hub, Edge::new(True::new()));
+        let _ = graph.data.add_edge(hub, bye, Edge::new(True::new()));
+
+        let simulator = Simulator::new(&graph).with_seed(3).with_runs(50);
+        let report = simulator.run();
+        assert_eq!(report.runs, 50);
+        assert_eq!(report.finished, 50);
+        assert!(report.failures.is_empty());
+        assert_eq!(report.visit_count(bye), 50);
+        assert!(report.visit_count(hub) >= 50);
+        assert_eq!(simulator.run(), report);
+
+        let run = simulator.run_once(11).unwrap();
+        assert_eq!(simulator.run_once(11).unwrap(), run);
+        assert_eq!(run.positions.len(), run.steps + 1);
+    }
+
+    #[test]
+    fn test_loop() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let _ = graph.data.add_edge(a, b, Edge::new(True::new()));
+        let _ = graph.data.add_edge(b, a, Edge::new(True::new()));
+
+        let report = Simulator::new(&graph).with_max_steps(20).with_runs(3).run();
+
+        assert_eq!(report.finished, 0);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].outcome, Outcome::Loop);
+        assert_eq!(report.failures[0].count, 3);
+        assert_eq!(report.failures[0].example.steps, 20);
+    }
+
+    #[test]
+    fn test_auto_cycle() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let _ = graph.data.add_edge(a, a, Edge::new(True::new()).auto());
+
+        let report = Simulator::new(&graph).run();
+
+        assert_eq!(
+            report.failures[0].outcome,
+            Outcome::Error(ConversationError::AutoAdvanceCycle(a))
+        );
+    }
+
+    #[test]
+    fn test_stuck_after_call() {
+        use crate::condition::dynamic::DynCondition;
+
+        let mut inn = DialogueGraph::new();
+        let door = inn.data.add_node("The door is locked.".into());
+        let mut library = Library::new();
+        let _ = library.insert("inn", inn);
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let c = graph.data.add_node("C".into());
+        let _ = graph
+            .data
+            .add_edge(a, b, Edge::new(DynCondition::True).with_call("inn"));
+        let _ = graph.data.add_edge(b, c, Edge::new(DynCondition::False));
+
+        let report = Simulator::new(&graph).with_library(&library).run();
+
+        assert_eq!(
+            report.failures[0].outcome,
+            Outcome::Stuck {
+                position: Position::new(None, b)
+            }
+        );
+        assert_eq!(
+            report.visits[&Position::new(Some("inn"), door)],
+            report.runs as u64
+        );
+    }
+}
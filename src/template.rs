@@ -0,0 +1,672 @@
+use crate::{
+    condition::{Compare, Comparison, Condition, Context},
+    localization::Source,
+    variable::Value,
+    DialogueGraph, Node,
+};
+use serde::Serialize;
+use std::{collections::BTreeSet, error::Error, fmt, str::FromStr};
+
+/// How the value of a placeholder is written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// The value as it is, with strings unquoted.
+    Plain,
+    /// An integer with its digits grouped by thousands, as in `1,250`.
+    Number,
+    /// One of two forms, depending on whether an integer is one. A `#` in the
+    /// form is replaced by the integer.
+    Plural {
+        /// The form used for one.
+        one: String,
+        /// The form used for any other number.
+        other: String,
+    },
+}
+
+/// The test of a conditional fragment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Test {
+    /// The variable is set to `true`, a non-zero integer or a non-empty
+    /// string, or with `negated`, it is not.
+    Truthy {
+        /// The name of the variable.
+        variable: String,
+        /// Whether the test is negated.
+        negated: bool,
+    },
+    /// The variable is set and compares to a value as given.
+    Compare(Compare),
+}
+
+impl Test {
+    /// Returns whether the test passes in the given context.
+    pub fn evaluate_with(&self, context: &Context<'_>) -> bool {
+        match self {
+            Test::Truthy { variable, negated } => {
+                let truthy = match context.variable(variable) {
+                    None => false,
+                    Some(Value::Bool(value)) => *value,
+                    Some(Value::Int(value)) => *value != 0,
+                    Some(Value::Str(value)) => !value.is_empty(),
+                };
+                truthy != *negated
+            }
+            Test::Compare(compare) => compare.evaluate_with(context),
+        }
+    }
+
+    /// Returns the name of the variable the test reads.
+    pub fn variable(&self) -> &str {
+        match self {
+            Test::Truthy { variable, .. } => variable,
+            Test::Compare(compare) => &compare.variable,
+        }
+    }
+}
+
+/// A part of a `Template`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Segment {
+    /// Literal text.
+    Text(String),
+    /// A placeholder for the value of a variable.
+    Placeholder {
+        /// The name of the variable.
+        variable: String,
+        /// How the value is written.
+        format: Format,
+    },
+    /// A fragment included only when its test passes, with an alternative
+    /// for when it does not.
+    Conditional {
+        /// The test deciding which fragment is included.
+        test: Test,
+        /// The fragment included when the test passes.
+        then: Vec<Segment>,
+        /// The fragment included when the test fails.
+        otherwise: Vec<Segment>,
+    },
+}
+
+/// An error raised when a template cannot be parsed or rendered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    /// The template is malformed.
+    Syntax {
+        /// The byte offset of the problem in the text.
+        position: usize,
+        /// What is wrong.
+        message: String,
+    },
+    /// A variable the template reads is not set.
+    UnsetVariable(String),
+    /// A variable is written with a format its value's kind does not
+    /// support.
+    WrongType {
+        /// The name of the variable.
+        variable: String,
+        /// The kind of value the format needs.
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Syntax { position, message } => {
+                write!(f, "at byte {}: {}", position, message)
+            }
+            TemplateError::UnsetVariable(variable) => {
+                write!(f, "variable {:?} is not set", variable)
+            }
+            TemplateError::WrongType { variable, expected } => {
+                write!(f, "variable {:?} is not {}", variable, expected)
+            }
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+/// Dialogue text with placeholders for the values of variables.
+///
+/// - `{name}` is replaced by the value of the variable `name`.
+/// - `{gold:number}` writes an integer with its digits grouped by thousands.
+/// - `{count:plural(# coin|# coins)}` writes the first form if the integer is
+///   one and the second otherwise, replacing `#` by the integer.
+/// - `{if test}…{else}…{end}` includes a fragment only if the test passes,
+///   where the test is a variable, a negated variable such as `!name`, or a
+///   comparison such as `gold >= 10` or `class == "mage"`. The `{else}`
+///   fragment is optional.
+/// - `{{` and `}}` are literal braces.
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::Context, template::Template, variable::Variables};
+/// let template: Template =
+///     "That'll be {price:plural(# gold|# gold pieces)}, {name}.{if price > 100} A bargain!{end}"
+///         .parse()
+///         .unwrap();
+/// let mut variables = Variables::new();
+/// variables.set("price", 150);
+/// variables.set("name", "traveller");
+/// let context = Context::new().with_variables(&variables);
+///
+/// assert_eq!(
+///     template.render(&context).unwrap(),
+///     "That'll be 150 gold pieces, traveller. A bargain!"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+/// A conditional fragment being parsed.
+struct Frame {
+    test: Test,
+    then: Vec<Segment>,
+    otherwise: Option<Vec<Segment>>,
+    position: usize,
+}
+
+/// Returns whether the text is a valid variable name.
+fn is_name(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Write an integer with its digits grouped by thousands.
+// `usize::is_multiple_of` needs a newer compiler than the crate supports.
+#[allow(unknown_lints, clippy::manual_is_multiple_of)]
+fn group_digits(value: i64) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if value < 0 {
+        grouped.push('-');
+    }
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+impl Template {
+    /// Parse a template.
+    pub fn parse(text: &str) -> Result<Self, TemplateError> {
+        let syntax = |position: usize, message: &str| TemplateError::Syntax {
+            position,
+            message: message.to_owned(),
+        };
+        let mut segments = Vec::new();
+        let mut frames: Vec<Frame> = Vec::new();
+        let mut literal = String::new();
+        let mut position = 0;
+        while position < text.len() {
+            let rest = &text[position..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                literal.push_str(&rest[..1]);
+                position += 2;
+                continue;
+            }
+            if rest.starts_with('}') {
+                return Err(syntax(position, "unmatched `}`"));
+            }
+            if !rest.starts_with('{') {
+                let end = rest.find(['{', '}']).unwrap_or(rest.len());
+                literal.push_str(&rest[..end]);
+                position += end;
+                continue;
+            }
+
+            let end = rest
+                .find('}')
+                .ok_or_else(|| syntax(position, "unclosed `{`"))?;
+            let tag = rest[1..end].trim();
+            if tag.contains('{') {
+                return Err(syntax(position, "unclosed `{`"));
+            }
+            let current = match frames.last_mut() {
+                Some(frame) => frame.otherwise.as_mut().unwrap_or(&mut frame.then),
+                None => &mut segments,
+            };
+            if !literal.is_empty() {
+                current.push(Segment::Text(std::mem::take(&mut literal)));
+            }
+
+            if tag == "if" || tag.starts_with("if ") {
+                let test = Self::parse_test(tag[2..].trim())
+                    .map_err(|message| syntax(position, &message))?;
+                frames.push(Frame {
+                    test,
+                    then: Vec::new(),
+                    otherwise: None,
+                    position,
+                });
+            } else if tag == "else" {
+                match frames.last_mut() {
+                    Some(frame) if frame.otherwise.is_none() => frame.otherwise = Some(Vec::new()),
+                    Some(_) => return Err(syntax(position, "more than one `{else}`")),
+                    None => return Err(syntax(position, "`{else}` outside of `{if}`")),
+                }
+            } else if tag == "end" {
+                let frame = frames
+                    .pop()
+                    .ok_or_else(|| syntax(position, "`{end}` outside of `{if}`"))?;
+                let segment = Segment::Conditional {
+                    test: frame.test,
+                    then: frame.then,
+                    otherwise: frame.otherwise.unwrap_or_default(),
+                };
+                match frames.last_mut() {
+                    Some(frame) => frame.otherwise.as_mut().unwrap_or(&mut frame.then),
+                    None => &mut segments,
+                }
+                .push(segment);
+            } else {
+                let segment =
+                    Self::parse_placeholder(tag).map_err(|message| syntax(position, &message))?;
+                current.push(segment);
+            }
+            position += end + 1;
+        }
+
+        if let Some(frame) = frames.last() {
+            return Err(syntax(frame.position, "`{if}` without `{end}`"));
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Text(literal));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Parse the inside of a placeholder, such as `gold:number`.
+    fn parse_placeholder(tag: &str) -> Result<Segment, String> {
+        let (variable, spec) = match tag.find(':') {
+            Some(end) => (tag[..end].trim(), Some(tag[end + 1..].trim())),
+            None => (tag, None),
+        };
+        if !is_name(variable) {
+            return Err(format!("invalid variable name {:?}", variable));
+        }
+        let format = match spec {
+            None => Format::Plain,
+            Some("number") => Format::Number,
+            Some(spec) => {
+                let forms = spec
+                    .strip_prefix("plural(")
+                    .and_then(|spec| spec.strip_suffix(')'))
+                    .ok_or_else(|| format!("unknown format {:?}", spec))?;
+                let end = forms
+                    .find('|')
+                    .ok_or("plural needs two forms, as in `plural(# coin|# coins)`")?;
+                Format::Plural {
+                    one: forms[..end].to_owned(),
+                    other: forms[end + 1..].to_owned(),
+                }
+            }
+        };
+        Ok(Segment::Placeholder {
+            variable: variable.to_owned(),
+            format,
+        })
+    }
+
+    /// Parse the test of a conditional fragment, such as `gold >= 10`.
+    fn parse_test(test: &str) -> Result<Test, String> {
+        if let Some(variable) = test.strip_prefix('!') {
+            let variable = variable.trim();
+            if !is_name(variable) {
+                return Err(format!("invalid variable name {:?}", variable));
+            }
+            return Ok(Test::Truthy {
+                variable: variable.to_owned(),
+                negated: true,
+            });
+        }
+
+        let end = test.find(['=', '!', '<', '>']).unwrap_or(test.len());
+        let variable = test[..end].trim();
+        if !is_name(variable) {
+            return Err(format!("invalid variable name {:?}", variable));
+        }
+        let rest = &test[end..];
+        if rest.is_empty() {
+            return Ok(Test::Truthy {
+                variable: variable.to_owned(),
+                negated: false,
+            });
+        }
+
+        let operators = [
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+        let (value, comparison) = operators
+            .iter()
+            .find_map(|(operator, comparison)| {
+                Some((rest.strip_prefix(operator)?.trim(), *comparison))
+            })
+            .ok_or_else(|| format!("invalid comparison {:?}", rest))?;
+        let value = match value {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => match value.parse::<i64>() {
+                Ok(value) => Value::Int(value),
+                Err(_) => value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .filter(|_| value.len() >= 2)
+                    .map(Value::from)
+                    .ok_or_else(|| format!("invalid value {:?}", value))?,
+            },
+        };
+        Ok(Test::Compare(Compare::new(variable, comparison, value)))
+    }
+
+    /// Returns the parts of the template.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Returns the names of the variables the template reads, ordered by
+    /// name.
+    pub fn variables(&self) -> BTreeSet<&str> {
+        fn collect<'a>(segments: &'a [Segment], variables: &mut BTreeSet<&'a str>) {
+            for segment in segments {
+                match segment {
+                    Segment::Text(_) => {}
+                    Segment::Placeholder { variable, .. } => {
+                        variables.insert(variable);
+                    }
+                    Segment::Conditional {
+                        test,
+                        then,
+                        otherwise,
+                    } => {
+                        variables.insert(test.variable());
+                        collect(then, variables);
+                        collect(otherwise, variables);
+                    }
+                }
+            }
+        }
+
+        let mut variables = BTreeSet::new();
+        collect(&self.segments, &mut variables);
+        variables
+    }
+
+    /// Render the template with the variables of the given context.
+    ///
+    /// Variables only read by the tests of conditional fragments may be
+    /// unset, in which case the tests fail.
+    pub fn render(&self, context: &Context<'_>) -> Result<String, TemplateError> {
+        let mut text = String::new();
+        Self::render_segments(&self.segments, context, &mut text)?;
+        Ok(text)
+    }
+
+    fn render_segments(
+        segments: &[Segment],
+        context: &Context<'_>,
+        text: &mut String,
+    ) -> Result<(), TemplateError> {
+        for segment in segments {
+            match segment {
+                Segment::Text(literal) => text.push_str(literal),
+                Segment::Placeholder { variable, format } => {
+                    let value = context
+                        .variable(variable)
+                        .ok_or_else(|| TemplateError::UnsetVariable(variable.clone()))?;
+                    let wrong_type = || TemplateError::WrongType {
+                        variable: variable.clone(),
+                        expected: "an integer",
+                    };
+                    match (format, value) {
+                        (Format::Plain, Value::Str(value)) => text.push_str(value),
+                        (Format::Plain, value) => text.push_str(&value.to_string()),
+                        (Format::Number, Value::Int(value)) => text.push_str(&group_digits(*value)),
+                        (Format::Plural { one, other }, Value::Int(value)) => {
+                            let form = if value.unsigned_abs() == 1 {
+                                one
+                            } else {
+                                other
+                            };
+                            text.push_str(&form.replace('#', &value.to_string()));
+                        }
+                        _ => return Err(wrong_type()),
+                    }
+                }
+                Segment::Conditional {
+                    test,
+                    then,
+                    otherwise,
+                } => {
+                    let fragment = if test.evaluate_with(context) {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    Self::render_segments(fragment, context, text)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+impl Node {
+    /// Render the node's text as a [`Template`](template/struct.Template.html)
+    /// with the variables of the given context.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::Context, variable::Variables, Node};
+    /// let mut variables = Variables::new();
+    /// variables.set("name", "Ada");
+    /// let context = Context::new().with_variables(&variables);
+    ///
+    /// let node = Node::new("Welcome back, {name}.");
+    /// assert_eq!(node.render(&context).unwrap(), "Welcome back, Ada.");
+    /// ```
+    pub fn render(&self, context: &Context<'_>) -> Result<String, TemplateError> {
+        Template::parse(&self.text)?.render(context)
+    }
+}
+
+/// A problem found in the templates of a `DialogueGraph`.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TemplateIssue {
+    /// The text is not a valid template.
+    InvalidTemplate {
+        /// Where the text comes from.
+        source: Source,
+        /// What is wrong.
+        message: String,
+    },
+    /// The text reads a variable which is not known.
+    UnknownVariable {
+        /// Where the text comes from.
+        source: Source,
+        /// The name of the variable.
+        variable: String,
+    },
+}
+
+impl fmt::Display for TemplateIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateIssue::InvalidTemplate { source, message } => {
+                write!(f, "{} is not a valid template: {}", source, message)
+            }
+            TemplateIssue::UnknownVariable { source, variable } => {
+                write!(f, "{} reads the unknown variable {:?}", source, variable)
+            }
+        }
+    }
+}
+
+impl<T> DialogueGraph<T>
+where
+    T: Condition,
+{
+    /// Check the text of every node and choice as a template, reporting
+    /// malformed templates and variables which are not among those given.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::True, localization::Source, template::TemplateIssue, DialogueGraph};
+    /// let mut graph = DialogueGraph::<True>::new();
+    /// let node = graph.data.add_node("That'll be {price} gold, {player_name}.".into());
+    ///
+    /// assert_eq!(
+    ///     graph.validate_templates(vec!["price", "name"]),
+    ///     vec![TemplateIssue::UnknownVariable {
+    ///         source: Source::Node(node),
+    ///         variable: "player_name".to_owned(),
+    ///     }]
+    /// );
+    /// ```
+    pub fn validate_templates<'a, I>(&self, known: I) -> Vec<TemplateIssue>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let known: BTreeSet<&str> = known.into_iter().collect();
+        let mut issues = Vec::new();
+        for entry in self.strings() {
+            match Template::parse(&entry.text) {
+                Err(error) => issues.push(TemplateIssue::InvalidTemplate {
+                    source: entry.source,
+                    message: error.to_string(),
+                }),
+                Ok(template) => issues.extend(
+                    template
+                        .variables()
+                        .into_iter()
+                        .filter(|variable| !known.contains(variable))
+                        .map(|variable| TemplateIssue::UnknownVariable {
+                            source: entry.source,
+                            variable: variable.to_owned(),
+                        }),
+                ),
+            }
+        }
+        issues
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{condition::True, variable::Variables, Edge};
+
+    fn render(text: &str, variables: &Variables) -> Result<String, TemplateError> {
+        Template::parse(text)?.render(&Context::new().with_variables(variables))
+    }
+
+    #[test]
+    fn test_render() {
+        let mut variables = Variables::new();
+        let _ = variables.set("gold", -1_234_567);
+        let _ = variables.set("count", 1);
+        let _ = variables.set("armed", false);
+        let _ = variables.set("class", "mage");
+
+        assert_eq!(
+            render(
+                "{gold:number} {{gold}} {count:plural(# coin|# coins)}",
+                &variables
+            ),
+            Ok("-1,234,567 {gold} 1 coin".to_owned())
+        );
+        assert_eq!(
+            render(
+                "{if !armed}Unarmed{if class == \"mage\"} mage{end}{else}Armed{end}.",
+                &variables
+            ),
+            Ok("Unarmed mage.".to_owned())
+        );
+        let _ = variables.set("count", i64::MIN);
+        assert_eq!(
+            render("{count:plural(one|many)} {count:number}", &variables),
+            Ok("many -9,223,372,036,854,775,808".to_owned())
+        );
+        assert_eq!(render("{if missing}x{end}", &variables), Ok(String::new()));
+        assert_eq!(
+            render("{missing}", &variables),
+            Err(TemplateError::UnsetVariable("missing".to_owned()))
+        );
+        assert_eq!(
+            render("{class:number}", &variables),
+            Err(TemplateError::WrongType {
+                variable: "class".to_owned(),
+                expected: "an integer"
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let position = |text: &str| match Template::parse(text) {
+            Err(TemplateError::Syntax { position, .. }) => Some(position),
+            _ => None,
+        };
+
+        assert_eq!(position("a }"), Some(2));
+        assert_eq!(position("a {b"), Some(2));
+        assert_eq!(position("{if a}b{else}c{else}d{end}"), Some(14));
+        assert_eq!(position("x {if a}b"), Some(2));
+        assert_eq!(position("{end}"), Some(0));
+        assert_eq!(position("{a:upper}"), Some(0));
+        assert_eq!(position("{if a <> 1}{end}"), Some(0));
+        assert_eq!(position("{a b}"), Some(0));
+    }
+
+    #[test]
+    fn test_validate_templates() {
+        let mut graph = DialogueGraph::<True>::new();
+        let a = graph.data.add_node("{if gold > 5}{name}{end}".into());
+        let b = graph.data.add_node("{broken".into());
+        let leave = graph
+            .data
+            .add_edge(a, b, Edge::new(True::new()).with_text("Pay {price}"));
+
+        assert_eq!(
+            graph.validate_templates(vec!["gold"]),
+            vec![
+                TemplateIssue::UnknownVariable {
+                    source: Source::Node(a),
+                    variable: "name".to_owned()
+                },
+                TemplateIssue::InvalidTemplate {
+                    source: Source::Node(b),
+                    message: "at byte 0: unclosed `{`".to_owned()
+                },
+                TemplateIssue::UnknownVariable {
+                    source: Source::Choice(leave),
+                    variable: "price".to_owned()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,467 @@
+use crate::{
+    condition::{Condition, Context},
+    conversation::{Conversation, ConversationError, Position},
+    markup,
+    variable::Value,
+    Node,
+};
+use std::{collections::VecDeque, error::Error, fmt, str::FromStr};
+
+/// A step of a scripted playthrough.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Set a variable, written `set <name> = <value>`. Values are `true`,
+    /// `false`, integers and strings quoted as in JSON.
+    Set {
+        /// The name of the variable.
+        name: String,
+        /// The value to set it to.
+        value: Value,
+    },
+    /// Expect the next line of the conversation, as returned by
+    /// [`line`](fn.line.html), written `expect <text>`.
+    Expect(String),
+    /// Choose one of the open choices, counting from 1 in the order they were
+    /// added to the graph, written `choose <number>`.
+    Choose(usize),
+    /// Expect the conversation to have ended, written `end`.
+    End,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Set { name, value } => match value {
+                Value::Str(text) => write!(
+                    f,
+                    "set {} = {}",
+                    name,
+                    serde_json::Value::from(text.as_str())
+                ),
+                value => write!(f, "set {} = {}", name, value),
+            },
+            Step::Expect(text) => {
+                f.write_str("expect")?;
+                if !text.is_empty() {
+                    write!(f, " {}", escape(text))?;
+                }
+                Ok(())
+            }
+            Step::Choose(number) => write!(f, "choose {}", number),
+            Step::End => f.write_str("end"),
+        }
+    }
+}
+
+/// An error raised when a transcript cannot be parsed or does not match the
+/// conversation it is checked against.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranscriptError {
+    /// The transcript is malformed.
+    Syntax {
+        /// The line of the problem, counting from 1.
+        line: usize,
+        /// What is wrong.
+        message: String,
+    },
+    /// The conversation differs from the transcript.
+    Mismatch {
+        /// The line of the step which does not match, counting from 1.
+        line: usize,
+        /// The step in the transcript.
+        expected: String,
+        /// The step the conversation would need instead.
+        found: String,
+    },
+    /// The conversation could not proceed.
+    Conversation {
+        /// The line of the step which failed, counting from 1.
+        line: usize,
+        /// The error raised.
+        error: ConversationError,
+    },
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranscriptError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            TranscriptError::Mismatch {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: the conversation differs from the transcript\n- {}\n+ {}",
+                line, expected, found
+            ),
+            TranscriptError::Conversation { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl Error for TranscriptError {}
+
+/// Escape backslashes and line breaks, so that text fits on one line.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Reverse [`escape`](fn.escape.html).
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                unescaped.push('\n');
+                let _ = chars.next();
+            }
+            ('\\', Some('\\')) => {
+                unescaped.push('\\');
+                let _ = chars.next();
+            }
+            (c, _) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Returns the text of a node as a player sees it: with its template
+/// rendered, or as written if it cannot be, and its markup stripped, after
+/// the name of its speaker.
+pub fn line(node: &Node, context: &Context<'_>) -> String {
+    let text = node.render(context).unwrap_or_else(|_| node.text.clone());
+    let text = markup::strip(&text);
+    match &node.speaker {
+        Some(speaker) => format!("{}: {}", speaker, text),
+        None => text,
+    }
+}
+
+/// A scripted playthrough of a conversation, which can be checked against a
+/// graph to catch changes to its content.
+///
+/// Transcripts are written one step to a line, and blank lines and comments
+/// starting with `#` are ignored:
+///
+/// ```text
+/// set gold = 10
+/// expect Guard: Halt!
+/// choose 2
+/// expect Guard: Thank you kindly.
+/// end
+/// ```
+///
+/// # Example use
+/// ```
+/// # use dialogue_graph::{condition::True, conversation::Conversation, transcript::Transcript, DialogueGraph, Edge, Node};
+/// let mut graph = DialogueGraph::new();
+/// let halt = graph.data.add_node(Node::new("Halt!").with_speaker("Guard"));
+/// let pass = graph.data.add_node(Node::new("Pass, {name}.").with_speaker("Guard"));
+/// graph.data.add_edge(halt, pass, Edge::new(True::new()));
+///
+/// let transcript: Transcript = "
+///     set name = \"Ada\"
+///     expect Guard: Halt!
+///     choose 1
+///     expect Guard: Pass, Ada.
+///     end
+/// ".parse().unwrap();
+/// assert!(transcript.check(Conversation::new(&graph, halt)).is_ok());
+///
+/// let transcript: Transcript = "expect Guard: Stop!".parse().unwrap();
+/// let error = transcript.check(Conversation::new(&graph, halt)).unwrap_err();
+/// assert_eq!(
+///     error.to_string(),
+///     "line 1: the conversation differs from the transcript\n\
+///      - expect Guard: Stop!\n\
+///      + expect Guard: Halt!"
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Transcript {
+    steps: Vec<Step>,
+    lines: Vec<usize>,
+}
+
+impl Transcript {
+    /// Create an empty transcript, to record a playthrough in.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a transcript.
+    pub fn parse(text: &str) -> Result<Self, TranscriptError> {
+        let mut transcript = Self::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim_start();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let syntax = |message: String| TranscriptError::Syntax {
+                line: index + 1,
+                message,
+            };
+            let (keyword, rest) = match line.find(' ') {
+                Some(end) => (&line[..end], &line[end + 1..]),
+                None => (line, ""),
+            };
+            let step = match keyword {
+                "expect" => Step::Expect(unescape(rest)),
+                "choose" => match rest.trim().parse() {
+                    Ok(number) if number > 0 => Step::Choose(number),
+                    _ => {
+                        return Err(syntax(format!(
+                            "expected a choice number, not {:?}",
+                            rest.trim()
+                        )))
+                    }
+                },
+                "end" if rest.trim().is_empty() => Step::End,
+                "set" => {
+                    let equals = rest
+                        .find('=')
+                        .ok_or_else(|| syntax("expected `set <name> = <value>`".to_owned()))?;
+                    let name = rest[..equals].trim();
+                    let value = rest[equals + 1..].trim();
+                    if name.is_empty() {
+                        return Err(syntax("expected a variable name".to_owned()));
+                    }
+                    let value = match value {
+                        "true" => Value::Bool(true),
+                        "false" => Value::Bool(false),
+                        _ if value.starts_with('"') => serde_json::from_str::<String>(value)
+                            .map(Value::Str)
+                            .map_err(|error| syntax(format!("invalid string: {}", error)))?,
+                        _ => value
+                            .parse()
+                            .map(Value::Int)
+                            .map_err(|_| syntax(format!("invalid value {:?}", value)))?,
+                    };
+                    Step::Set {
+                        name: name.to_owned(),
+                        value,
+                    }
+                }
+                _ => return Err(syntax(format!("unknown step {:?}", line))),
+            };
+            transcript.steps.push(step);
+            transcript.lines.push(index + 1);
+        }
+        Ok(transcript)
+    }
+
+    /// Returns the steps of the transcript.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Add a step to the end of the transcript.
+    pub fn push(&mut self, step: Step) {
+        let line = self.lines.last().map_or(1, |line| line + 1);
+        self.steps.push(step);
+        self.lines.push(line);
+    }
+
+    /// Play a conversation as the transcript says, failing at the first step
+    /// which does not match.
+    ///
+    /// Automatic edges are followed as soon as the first step other than
+    /// `set` is reached, so that variables can be set beforehand. Every line
+    /// of the conversation must be expected in turn, up to the end of the
+    /// transcript.
+    pub fn check<T>(&self, mut conversation: Conversation<'_, T>) -> Result<(), TranscriptError>
+    where
+        T: Condition,
+    {
+        let mut pending = VecDeque::new();
+        pending.push_back(conversation.position());
+        let mut started = false;
+        for (step, line) in self.steps.iter().zip(self.lines.iter().copied()) {
+            let failed = |error| TranscriptError::Conversation { line, error };
+            let mismatch = |conversation: &Conversation<'_, T>, pending: &VecDeque<Position>| {
+                TranscriptError::Mismatch {
+                    line,
+                    expected: step.to_string(),
+                    found: next_step(conversation, pending),
+                }
+            };
+            if !started && !matches!(step, Step::Set { .. }) {
+                pending.extend(conversation.advance().map_err(failed)?);
+                started = true;
+            }
+            match step {
+                Step::Set { name, value } => {
+                    let _ = conversation.variables_mut().set(name, value.clone());
+                }
+                Step::Expect(text) => {
+                    let found = pending
+                        .front()
+                        .and_then(|position| conversation.node_at(position))
+                        .map(|node| line_of(node, &conversation));
+                    if found.as_ref() != Some(text) {
+                        return Err(mismatch(&conversation, &pending));
+                    }
+                    let _ = pending.pop_front();
+                }
+                Step::Choose(number) => {
+                    let choices = conversation.choices();
+                    if !pending.is_empty() || *number > choices.len() {
+                        return Err(mismatch(&conversation, &pending));
+                    }
+                    let _ = conversation.choose(choices[number - 1]).map_err(failed)?;
+                    pending.push_back(conversation.position());
+                    pending.extend(conversation.advance().map_err(failed)?);
+                }
+                Step::End => {
+                    if !pending.is_empty() || !conversation.choices().is_empty() {
+                        return Err(mismatch(&conversation, &pending));
+                    }
+                }
+            }
+        }
+        if !pending.is_empty() {
+            return Err(TranscriptError::Mismatch {
+                line: self.lines.last().map_or(1, |line| line + 1),
+                expected: "the end of the transcript".to_owned(),
+                found: next_step(&conversation, &pending),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Returns the line of a node in a conversation.
+fn line_of<T>(node: &Node, conversation: &Conversation<'_, T>) -> String
+where
+    T: Condition,
+{
+    line(node, &conversation.context())
+}
+
+/// Describe the step a conversation is ready for, given the positions moved
+/// to whose lines are yet to be expected.
+fn next_step<T>(conversation: &Conversation<'_, T>, pending: &VecDeque<Position>) -> String
+where
+    T: Condition,
+{
+    if let Some(node) = pending
+        .front()
+        .and_then(|position| conversation.node_at(position))
+    {
+        return Step::Expect(line_of(node, conversation)).to_string();
+    }
+    match conversation.choices().len() {
+        0 => Step::End.to_string(),
+        1 => Step::Choose(1).to_string(),
+        count => format!("choose 1 to {}", count),
+    }
+}
+
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Transcript {
+    type Err = TranscriptError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{condition::True, DialogueGraph, Edge};
+
+    fn graph() -> DialogueGraph<True> {
+        let mut graph = DialogueGraph::new();
+        let a = graph
+            .data
+            .add_node(Node::new("Hello,\n[b]{name}[/b].").with_speaker("Guard"));
+        let b = graph.data.add_node("Left".into());
+        let c = graph.data.add_node("Right".into());
+        let d = graph.data.add_node("Done".into());
+        let _ = graph.data.add_edge(a, b, Edge::new(True::new()));
+        let _ = graph.data.add_edge(a, c, Edge::new(True::new()));
+        let _ = graph.data.add_edge(c, d, Edge::new(True::new()).auto());
+        graph
+    }
+
+    fn check(text: &str) -> Result<(), TranscriptError> {
+        let graph = graph();
+        let start = graph.entry().unwrap();
+        Transcript::parse(text)
+            .unwrap()
+            .check(Conversation::new(&graph, start))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut transcript = Transcript::new();
+        transcript.push(Step::Set {
+            name: "name".to_owned(),
+            value: Value::from("A \"B\""),
+        });
+        transcript.push(Step::Set {
+            name: "gold".to_owned(),
+            value: Value::Int(-2),
+        });
+        transcript.push(Step::Expect("one\\two\nthree".to_owned()));
+        transcript.push(Step::Expect(String::new()));
+        transcript.push(Step::Choose(2));
+        transcript.push(Step::End);
+
+        let written = transcript.to_string();
+        assert_eq!(
+            written,
+            "set name = \"A \\\"B\\\"\"\nset gold = -2\nexpect one\\\\two\\nthree\nexpect\nchoose 2\nend\n"
+        );
+        assert_eq!(Transcript::parse(&written).unwrap(), transcript);
+        assert_eq!(
+            Transcript::parse("\n# comment\nchoose 0").unwrap_err(),
+            TranscriptError::Syntax {
+                line: 3,
+                message: "expected a choice number, not \"0\"".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let script = "set name = \"Ada\"\nexpect Guard: Hello,\\nAda.\nchoose 2\nexpect Right\nexpect Done\nend";
+        assert_eq!(check(script), Ok(()));
+
+        assert_eq!(
+            check("expect Guard: Hello,\\n{name}.\nchoose 3"),
+            Err(TranscriptError::Mismatch {
+                line: 2,
+                expected: "choose 3".to_owned(),
+                found: "choose 1 to 2".to_owned(),
+            })
+        );
+        assert_eq!(
+            check("set name = \"Ada\"\nexpect Guard: Hello,\\nAda.\nchoose 2\nexpect Right\nend"),
+            Err(TranscriptError::Mismatch {
+                line: 5,
+                expected: "end".to_owned(),
+                found: "expect Done".to_owned(),
+            })
+        );
+        assert_eq!(
+            check("set name = \"Ada\"\nexpect Guard: Hello,\\nAda.\nchoose 1"),
+            Err(TranscriptError::Mismatch {
+                line: 4,
+                expected: "the end of the transcript".to_owned(),
+                found: "expect Left".to_owned(),
+            })
+        );
+    }
+}
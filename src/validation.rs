@@ -0,0 +1,673 @@
+use crate::{
+    condition::{
+        dynamic::DynCondition,
+        satisfiability::{self, Term},
+        Condition,
+    },
+    library::{Library, SEPARATOR},
+    DialogueGraph,
+};
+use petgraph::{
+    graph::{EdgeIndex, NodeIndex},
+    visit::{Bfs, EdgeRef, Reversed},
+};
+use serde::Serialize;
+use std::fmt;
+
+/// The largest number of distinct path conditions tracked for a single node
+/// before it is assumed to be reachable under any conditions.
+const PATH_LIMIT: usize = 64;
+
+/// How serious a diagnostic is.
+#[derive(Copy, Clone, Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Content that is likely a mistake but does not break the dialogue.
+    Warning,
+    /// Content that breaks the dialogue.
+    Error,
+}
+
+/// A problem found in a `DialogueGraph`.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Diagnostic {
+    /// The edge's condition can never evaluate as true.
+    UnsatisfiableCondition {
+        /// The index of the edge.
+        edge: EdgeIndex,
+    },
+    /// The edge's condition contradicts the conditions on every path leading
+    /// to it.
+    ContradictoryPath {
+        /// The index of the edge.
+        edge: EdgeIndex,
+    },
+    /// The node is only reachable through edges that can never be traversed.
+    ConditionallyUnreachable {
+        /// The index of the node.
+        node: NodeIndex,
+    },
+    /// No edges lead to the node from the entry node.
+    UnreachableNode {
+        /// The index of the node.
+        node: NodeIndex,
+    },
+    /// No end of the conversation can be reached from the node, so a
+    /// conversation reaching it can never finish.
+    DeadEnd {
+        /// The index of the node.
+        node: NodeIndex,
+    },
+    /// The link refers to an id no node has.
+    UnresolvedLink {
+        /// The index of the link.
+        node: NodeIndex,
+        /// The id it refers to.
+        reference: String,
+    },
+    /// The edge calls a graph which is not in the library or has no nodes.
+    UnknownCall {
+        /// The index of the edge.
+        edge: EdgeIndex,
+        /// The name of the graph it calls.
+        graph: String,
+    },
+    /// The edge's condition refers to a node or edge which is not in the
+    /// graph.
+    DanglingCondition {
+        /// The index of the edge.
+        edge: EdgeIndex,
+    },
+    /// The edge's condition is of a type whose structure is not known, such
+    /// as a condition type of your own, so it cannot be analysed and is
+    /// assumed to be satisfiable.
+    UnknownCondition {
+        /// The index of the edge.
+        edge: EdgeIndex,
+        /// The name of the condition's type.
+        name: String,
+    },
+}
+
+impl Diagnostic {
+    /// Returns how serious the diagnostic is.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Diagnostic::UnsatisfiableCondition { .. }
+            | Diagnostic::ContradictoryPath { .. }
+            | Diagnostic::ConditionallyUnreachable { .. }
+            | Diagnostic::UnreachableNode { .. }
+            | Diagnostic::DeadEnd { .. }
+            | Diagnostic::UnknownCondition { .. } => Severity::Warning,
+            Diagnostic::UnresolvedLink { .. }
+            | Diagnostic::UnknownCall { .. }
+            | Diagnostic::DanglingCondition { .. } => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::UnsatisfiableCondition { edge } => write!(
+                f,
+                "edge {} can never be traversed: its condition is unsatisfiable",
+                edge.index()
+            ),
+            Diagnostic::ContradictoryPath { edge } => write!(
+                f,
+                "edge {} can never be traversed: its condition contradicts every path leading to it",
+                edge.index()
+            ),
+            Diagnostic::ConditionallyUnreachable { node } => write!(
+                f,
+                "node {} is only reachable through edges that can never be traversed",
+                node.index()
+            ),
+            Diagnostic::UnreachableNode { node } => write!(
+                f,
+                "node {} cannot be reached from the entry node",
+                node.index()
+            ),
+            Diagnostic::DeadEnd { node } => write!(
+                f,
+                "node {} is a dead end: no end of the conversation can be reached from it",
+                node.index()
+            ),
+            Diagnostic::UnresolvedLink { node, reference } => write!(
+                f,
+                "node {} links to {:?}, which no node has as its id",
+                node.index(),
+                reference
+            ),
+            Diagnostic::UnknownCall { edge, graph } => write!(
+                f,
+                "edge {} calls {:?}, which is not in the library or has no nodes",
+                edge.index(),
+                graph
+            ),
+            Diagnostic::DanglingCondition { edge } => write!(
+                f,
+                "edge {} has a condition referring to a node or edge which is not in the graph",
+                edge.index()
+            ),
+            Diagnostic::UnknownCondition { edge, name } => write!(
+                f,
+                "edge {} has a condition of type {:?}, which cannot be analysed",
+                edge.index(),
+                name
+            ),
+        }
+    }
+}
+
+/// The diagnostics found by validating a `DialogueGraph`.
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The diagnostics, in the order they were found.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    /// Create an empty report.
+    pub fn new() -> Self {
+        Self {
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Returns whether no problems were found.
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// Returns whether any diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity() == Severity::Error)
+    }
+}
+
+impl<T> DialogueGraph<T>
+where
+    T: Condition,
+{
+    /// Check the graph for dead content.
+    ///
+    /// Variables are assumed not to change while the conversation runs, so
+    /// comparisons on the path from the entry node to an edge must hold
+    /// together with the edge's own condition. Opaque conditions, such as
+    /// conditions of your own, could be true or false at any time.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::{Comparison, Compare}, validation::Diagnostic, DialogueGraph, Edge};
+    /// let mut graph = DialogueGraph::new();
+    /// let start = graph.data.add_node("Hello.".into());
+    /// let rich = graph.data.add_node("Welcome, my lord.".into());
+    /// let poorer = graph.data.add_node("Spare some change?".into());
+    /// graph.data.add_edge(start, rich, Edge::new(Compare::new("gold", Comparison::Greater, 100)));
+    /// let edge = graph.data.add_edge(rich, poorer, Edge::new(Compare::new("gold", Comparison::Less, 5)));
+    ///
+    /// let report = graph.validate();
+    ///
+    /// assert_eq!(report.diagnostics, vec![
+    ///     Diagnostic::ContradictoryPath { edge },
+    ///     Diagnostic::ConditionallyUnreachable { node: poorer },
+    /// ]);
+    /// ```
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::new();
+        self.check_conditions(&mut report);
+        report
+    }
+
+    /// Check the structure of the graph: that every node can be reached from
+    /// the entry node, that the conversation can finish from every node, and
+    /// that links, calls and conditions refer to things which exist.
+    ///
+    /// Calls and links qualified with the name of another graph are only
+    /// checked against a library, if one is given.
+    ///
+    /// # Example use
+    /// ```
+    /// # use dialogue_graph::{condition::True, validation::Diagnostic, DialogueGraph, Edge, Node};
+    /// let mut graph = DialogueGraph::new();
+    /// let start = graph.data.add_node("Hello.".into());
+    /// let lost = graph.data.add_node("Nobody hears this.".into());
+    /// let link = graph.data.add_node(Node::link("farewell"));
+    /// graph.data.add_edge(start, link, Edge::new(True::new()));
+    ///
+    /// let report = graph.validate_structure(None);
+    ///
+    /// assert_eq!(report.diagnostics, vec![
+    ///     Diagnostic::UnreachableNode { node: lost },
+    ///     Diagnostic::UnresolvedLink { node: link, reference: "farewell".to_owned() },
+    /// ]);
+    /// assert!(report.has_errors());
+    /// ```
+    pub fn validate_structure(&self, library: Option<&Library<T>>) -> ValidationReport {
+        let mut report = ValidationReport::new();
+        self.check_reachability(&mut report);
+        self.check_references(library, &mut report);
+        report
+    }
+
+    /// Report nodes which cannot be reached from the entry node, and reachable
+    /// nodes from which no node without outgoing edges can be reached.
+    fn check_reachability(&self, report: &mut ValidationReport) {
+        let entry = match self.entry() {
+            Some(entry) => entry,
+            None => return,
+        };
+        let mut reachable = vec![false; self.data.node_count()];
+        let mut bfs = Bfs::new(&self.data, entry);
+        while let Some(node) = bfs.next(&self.data) {
+            reachable[node.index()] = true;
+        }
+
+        let mut finishes = vec![false; self.data.node_count()];
+        let reversed = Reversed(&self.data);
+        for exit in self.data.externals(petgraph::Direction::Outgoing) {
+            let mut bfs = Bfs::new(reversed, exit);
+            while let Some(node) = bfs.next(reversed) {
+                finishes[node.index()] = true;
+            }
+        }
+
+        for node in self.data.node_indices() {
+            if !reachable[node.index()] {
+                report
+                    .diagnostics
+                    .push(Diagnostic::UnreachableNode { node });
+            } else if !finishes[node.index()] {
+                report.diagnostics.push(Diagnostic::DeadEnd { node });
+            }
+        }
+    }
+
+    /// Report links, calls and conditions which refer to things which do not
+    /// exist.
+    fn check_references(&self, library: Option<&Library<T>>, report: &mut ValidationReport) {
+        for node in self.data.node_indices() {
+            let reference = match &self.data[node].link {
+                Some(reference) => reference,
+                None => continue,
+            };
+            let found = match reference.find(SEPARATOR) {
+                None => self.find(reference).is_some(),
+                Some(end) => match library {
+                    Some(library) => library
+                        .get(&reference[..end])
+                        .and_then(|graph| graph.find(&reference[end + SEPARATOR.len()..]))
+                        .is_some(),
+                    None => true,
+                },
+            };
+            if !found {
+                report.diagnostics.push(Diagnostic::UnresolvedLink {
+                    node,
+                    reference: reference.clone(),
+                });
+            }
+        }
+
+        for edge in self.data.edge_indices() {
+            let weight = &self.data[edge];
+            if let (Some(graph), Some(library)) = (&weight.call, library) {
+                if library.get(graph).and_then(DialogueGraph::entry).is_none() {
+                    report.diagnostics.push(Diagnostic::UnknownCall {
+                        edge,
+                        graph: graph.clone(),
+                    });
+                }
+            }
+
+            let mut dangling = false;
+            let mut unknown = Vec::new();
+            self.check_condition(&weight.condition.to_dynamic(), &mut dangling, &mut unknown);
+            if dangling {
+                report
+                    .diagnostics
+                    .push(Diagnostic::DanglingCondition { edge });
+            }
+            for name in unknown {
+                report
+                    .diagnostics
+                    .push(Diagnostic::UnknownCondition { edge, name });
+            }
+        }
+    }
+
+    /// Find references to nodes or edges which are not in the graph, and the
+    /// names of opaque conditions, within a condition.
+    fn check_condition(
+        &self,
+        condition: &DynCondition,
+        dangling: &mut bool,
+        unknown: &mut Vec<String>,
+    ) {
+        match condition {
+            DynCondition::Not { condition } => self.check_condition(condition, dangling, unknown),
+            DynCondition::And { left, right } | DynCondition::Or { left, right } => {
+                self.check_condition(left, dangling, unknown);
+                self.check_condition(right, dangling, unknown);
+            }
+            DynCondition::Visited { node } | DynCondition::VisitCount { node, .. } => {
+                *dangling |= self.data.node_weight(*node).is_none();
+            }
+            DynCondition::EdgeTaken { edge } => {
+                *dangling |= self.data.edge_weight(*edge).is_none();
+            }
+            DynCondition::Opaque { name, .. } => {
+                if !unknown.contains(name) {
+                    unknown.push(name.clone());
+                }
+            }
+            DynCondition::True | DynCondition::False | DynCondition::Compare { .. } => {}
+        }
+    }
+
+    /// Report edges which can never be traversed and nodes which are only
+    /// reachable through them.
+    fn check_conditions(&self, report: &mut ValidationReport) {
+        // `None` for conditions too large to analyse, which are assumed to
+        // always be open.
+        let mut conditions: Vec<Option<Vec<Term>>> = Vec::with_capacity(self.data.edge_count());
+        let mut unknowns = 0;
+        for edge in self.data.raw_edges() {
+            conditions.push(satisfiability::disjunctive_normal_form(&independent(
+                &edge.weight.condition.to_dynamic(),
+                &mut unknowns,
+            )));
+        }
+        for edge in self.data.edge_indices() {
+            if is_unsatisfiable(&conditions[edge.index()]) {
+                report
+                    .diagnostics
+                    .push(Diagnostic::UnsatisfiableCondition { edge });
+            }
+        }
+
+        let entry = match self.entry() {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        // The conditions under which each node can be reached, or `None` if it
+        // cannot be.
+        let mut paths: Vec<Option<Vec<Term>>> = vec![None; self.data.node_count()];
+        let mut traversable = vec![false; self.data.edge_count()];
+        paths[entry.index()] = Some(vec![Term::default()]);
+        let mut queue = vec![entry];
+        while let Some(node) = queue.pop() {
+            let reaching = paths[node.index()].clone().unwrap_or_default();
+            for edge in self.data.edges(node) {
+                let always = [Term::default()];
+                let condition = match &conditions[edge.id().index()] {
+                    Some(condition) => condition.as_slice(),
+                    None => &always,
+                };
+
+                let mut changed = false;
+                for path in &reaching {
+                    for term in condition {
+                        if let Some(term) = path.conjoin(term) {
+                            traversable[edge.id().index()] = true;
+                            changed |=
+                                add_path(&mut paths[edge.target().index()], term.comparisons());
+                        }
+                    }
+                }
+                if changed {
+                    queue.push(edge.target());
+                }
+            }
+        }
+
+        for edge in self.data.edge_indices() {
+            let source = self.data.raw_edges()[edge.index()].source();
+            if !is_unsatisfiable(&conditions[edge.index()])
+                && paths[source.index()].is_some()
+                && !traversable[edge.index()]
+            {
+                report
+                    .diagnostics
+                    .push(Diagnostic::ContradictoryPath { edge });
+            }
+        }
+
+        let mut bfs = Bfs::new(&self.data, entry);
+        while let Some(node) = bfs.next(&self.data) {
+            if paths[node.index()].is_none() {
+                report
+                    .diagnostics
+                    .push(Diagnostic::ConditionallyUnreachable { node });
+            }
+        }
+    }
+}
+
+/// Returns the condition with every opaque condition in it made distinct from
+/// every other, numbering them from `unknowns`.
+///
+/// Opaque conditions may be conditions of your own which are not
+/// deterministic, so even two with the same type and serialized form can
+/// disagree, and nothing is known about how they relate.
+fn independent(condition: &DynCondition, unknowns: &mut u64) -> DynCondition {
+    match condition {
+        DynCondition::Not { condition } => DynCondition::not(independent(condition, unknowns)),
+        DynCondition::And { left, right } => {
+            let left = independent(left, unknowns);
+            DynCondition::and(left, independent(right, unknowns))
+        }
+        DynCondition::Or { left, right } => {
+            let left = independent(left, unknowns);
+            DynCondition::or(left, independent(right, unknowns))
+        }
+        DynCondition::Opaque { name, .. } => {
+            *unknowns += 1;
+            DynCondition::Opaque {
+                name: name.clone(),
+                value: (*unknowns).into(),
+            }
+        }
+        condition => condition.clone(),
+    }
+}
+
+/// Returns whether a condition in disjunctive normal form is known to be
+/// unsatisfiable.
+fn is_unsatisfiable(condition: &Option<Vec<Term>>) -> bool {
+    match condition {
+        Some(terms) => terms.is_empty(),
+        None => false,
+    }
+}
+
+/// Add a path condition to those reaching a node, returning whether it was
+/// not already covered by a less restrictive one.
+fn add_path(paths: &mut Option<Vec<Term>>, term: Term) -> bool {
+    let paths = paths.get_or_insert_with(Vec::new);
+    if paths.iter().any(|path| path.subsumes(&term)) {
+        return false;
+    }
+    paths.retain(|path| !term.subsumes(path));
+    paths.push(term);
+    if paths.len() > PATH_LIMIT {
+        *paths = vec![Term::default()];
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        condition::{And, Compare, Comparison, Not, True},
+        Edge, Node,
+    };
+
+    #[test]
+    fn test_validate_unsatisfiable_condition() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let edge = graph.data.add_edge(
+            a,
+            b,
+            Edge::new(And::new(True::new(), Not::new(True::new()))),
+        );
+
+        assert_eq!(
+            graph.validate().diagnostics,
+            vec![
+                Diagnostic::UnsatisfiableCondition { edge },
+                Diagnostic::ConditionallyUnreachable { node: b },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_opaque_conditions() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let chance = DynCondition::opaque("coin flip");
+        let _ = graph.data.add_edge(
+            a,
+            b,
+            Edge::new(DynCondition::and(
+                chance.clone(),
+                DynCondition::not(chance.clone()),
+            )),
+        );
+        let _ = graph.data.add_edge(b, a, Edge::new(chance));
+
+        assert!(graph.validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_reachable_through_other_path() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let c = graph.data.add_node("C".into());
+        let _ = graph.data.add_edge(
+            a,
+            b,
+            Edge::new(Compare::new("flag", Comparison::Equal, true)),
+        );
+        let _ = graph.data.add_edge(
+            a,
+            b,
+            Edge::new(Compare::new("flag", Comparison::Equal, false)),
+        );
+        let _ = graph.data.add_edge(
+            b,
+            c,
+            Edge::new(Compare::new("flag", Comparison::Equal, false)),
+        );
+
+        assert!(graph.validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_cycle() {
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let _ = graph.data.add_edge(
+            a,
+            b,
+            Edge::new(Compare::new("gold", Comparison::Greater, 5)),
+        );
+        let _ = graph.data.add_edge(
+            b,
+            a,
+            Edge::new(Compare::new("gold", Comparison::Greater, 10)),
+        );
+        let edge = graph
+            .data
+            .add_edge(b, a, Edge::new(Compare::new("gold", Comparison::Less, 3)));
+
+        assert_eq!(
+            graph.validate().diagnostics,
+            vec![Diagnostic::ContradictoryPath { edge }]
+        );
+    }
+
+    #[test]
+    fn test_validate_structure_dead_end() {
+        let mut graph = DialogueGraph::<True>::new();
+        let a = graph.data.add_node("A".into());
+        let b = graph.data.add_node("B".into());
+        let c = graph.data.add_node("C".into());
+        let d = graph.data.add_node("D".into());
+        let _ = graph.data.add_edge(a, b, Edge::new(True::new()));
+        let _ = graph.data.add_edge(a, c, Edge::new(True::new()));
+        let _ = graph.data.add_edge(c, d, Edge::new(True::new()));
+        let _ = graph.data.add_edge(d, c, Edge::new(True::new()));
+
+        assert_eq!(
+            graph.validate_structure(None).diagnostics,
+            vec![
+                Diagnostic::DeadEnd { node: c },
+                Diagnostic::DeadEnd { node: d },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_structure_references() {
+        let mut shop = DialogueGraph::new();
+        let _ = shop.data.add_node(Node::new("Welcome.").with_id("welcome"));
+        let mut library = Library::new();
+        let _ = library.insert("shop", shop);
+
+        let mut graph = DialogueGraph::new();
+        let a = graph.data.add_node("A".into());
+        let found = graph.data.add_node(Node::link("shop::welcome"));
+        let missing = graph.data.add_node(Node::link("shop::farewell"));
+        let call = graph
+            .data
+            .add_edge(a, found, Edge::new(DynCondition::True).with_call("inn"));
+        let condition = DynCondition::and(
+            DynCondition::Visited {
+                node: NodeIndex::new(9),
+            },
+            DynCondition::opaque("custom"),
+        );
+        let edge = graph.data.add_edge(a, missing, Edge::new(condition));
+
+        assert_eq!(
+            graph.validate_structure(Some(&library)).diagnostics,
+            vec![
+                Diagnostic::UnresolvedLink {
+                    node: missing,
+                    reference: "shop::farewell".to_owned()
+                },
+                Diagnostic::UnknownCall {
+                    edge: call,
+                    graph: "inn".to_owned()
+                },
+                Diagnostic::DanglingCondition { edge },
+                Diagnostic::UnknownCondition {
+                    edge,
+                    name: "str".to_owned()
+                },
+            ]
+        );
+        assert_eq!(graph.validate_structure(None).diagnostics.len(), 2);
+
+        let _ = graph.data.remove_edge(edge);
+        let _ = graph
+            .data
+            .add_edge(a, missing, Edge::new(DynCondition::opaque("custom")));
+        let report = graph.validate_structure(None);
+        assert_eq!(report.diagnostics.len(), 1);
+        assert!(!report.has_errors());
+    }
+}